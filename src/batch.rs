@@ -0,0 +1,464 @@
+use crate::core::MeccaApp;
+use crate::error::MecczError;
+use crate::interfaces::{GeocodingService, Location, QiblaCalculator, QiblaDirection};
+use futures::stream::{self, Stream, StreamExt};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Header names recognized as the location column, in order of preference.
+const LOCATION_COLUMNS: [&str; 4] = ["location", "address", "query", "place"];
+const LATITUDE_COLUMNS: [&str; 2] = ["latitude", "lat"];
+const LONGITUDE_COLUMNS: [&str; 4] = ["longitude", "lon", "lng", "long"];
+
+/// Identifier column used when none is named and the input has one.
+const DEFAULT_ID_COLUMN: &str = "id";
+
+/// Columns and keys of the output rows; identifier columns with these names are renamed.
+const OUTPUT_COLUMNS: [&str; 13] = [
+    "line", "input", "latitude", "longitude", "bearing", "direction", "distance_km", "magnetic", "rhumb_line", "error",
+    "error_code", "code", "field",
+];
+
+/// Locations resolved at the same time when no limit is given.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// File formats of batch input and output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchFormat {
+    Csv,
+    Jsonl, // one JSON object (or string) per line
+}
+
+impl BatchFormat {
+    /// The format of a file named `path`, if its extension tells.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "csv" | "txt" => Some(BatchFormat::Csv),
+            "jsonl" | "ndjson" | "json" => Some(BatchFormat::Jsonl),
+            _ => None,
+        }
+    }
+
+    /// JSON Lines when the first non-blank line is a JSON object or string, CSV otherwise.
+    pub fn sniff(text: &str) -> Self {
+        match text.lines().map(str::trim_start).find(|line| !line.is_empty()) {
+            Some(line) if line.starts_with('{') || (line.starts_with('"') && serde_json::from_str::<String>(line).is_ok()) => {
+                BatchFormat::Jsonl
+            }
+            _ => BatchFormat::Csv,
+        }
+    }
+}
+
+impl FromStr for BatchFormat {
+    type Err = MecczError;
+
+    fn from_str(input: &str) -> Result<Self, MecczError> {
+        match input.trim().to_lowercase().as_str() {
+            "csv" => Ok(BatchFormat::Csv),
+            "jsonl" | "ndjson" => Ok(BatchFormat::Jsonl),
+            _ => Err(MecczError::parse("batch format", format!("'{}' (expected csv or jsonl)", input.trim()))),
+        }
+    }
+}
+
+/// Columns (CSV headers or JSON keys) to read. Left empty, the location comes from a
+/// `location`, `address`, `query` or `place` column or from `latitude` and `longitude`,
+/// and an `id` column is kept when there is one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchColumns {
+    pub ids: Vec<String>,
+    pub location: Option<String>,
+}
+
+/// One location to resolve.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchRecord {
+    pub line: usize, // line of the input the record starts on, from 1
+    pub ids: Vec<String>, // values of the identifier columns, in the order of `BatchInput::id_columns`
+    pub input: Result<String, MecczError>, // the location text, or why the row could not be read
+}
+
+/// The records of an input file with the names of their identifier columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchInput {
+    pub id_columns: Vec<String>,
+    pub records: Vec<BatchRecord>,
+}
+
+impl BatchInput {
+    /// Reads `text` as CSV or JSON Lines. A CSV file needs a header naming its columns
+    /// unless each line is just a location, like `48.8566,2.3522` or `Paris, France`.
+    /// Rows that cannot be read become failed records; only a header lacking the
+    /// requested columns fails the whole input.
+    pub fn parse(text: &str, format: BatchFormat, columns: &BatchColumns) -> Result<Self, MecczError> {
+        match format {
+            BatchFormat::Csv => parse_csv(text, columns),
+            BatchFormat::Jsonl => Ok(parse_jsonl(text, columns)),
+        }
+    }
+}
+
+fn parse_csv(text: &str, columns: &BatchColumns) -> Result<BatchInput, MecczError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+    let mut rows = reader.records().peekable();
+
+    let header: Option<Vec<String>> = match rows.peek() {
+        Some(Ok(first)) => {
+            let names: Vec<String> = first.iter().map(str::to_lowercase).collect();
+            let named = columns.location.is_some() || !columns.ids.is_empty();
+            (named || LocationColumns::find(&names, None).is_some()).then_some(names)
+        }
+        _ => None,
+    };
+
+    let Some(header) = header else {
+        // Without a header each line is a location, commas included
+        let records = rows
+            .map(|row| match row {
+                Ok(row) => BatchRecord {
+                    line: row.position().map_or(0, |position| position.line() as usize),
+                    ids: Vec::new(),
+                    input: non_empty(row.iter().collect::<Vec<_>>().join(", ")),
+                },
+                Err(e) => csv_error(e),
+            })
+            .collect();
+        return Ok(BatchInput { id_columns: Vec::new(), records });
+    };
+    rows.next();
+
+    let location = LocationColumns::find(&header, columns.location.as_deref()).ok_or_else(|| {
+        MecczError::parse(
+            "batch columns",
+            match &columns.location {
+                Some(name) => format!("no column named '{}'", name),
+                None => "expected a location, address or query column, or latitude and longitude".to_string(),
+            },
+        )
+    })?;
+    let id_columns = id_columns(columns, |name| header.contains(&name.to_lowercase()));
+    let id_indices: Vec<usize> = id_columns
+        .iter()
+        .map(|name| header.iter().position(|column| *column == name.to_lowercase()))
+        .collect::<Option<_>>()
+        .ok_or_else(|| MecczError::parse("batch columns", format!("no column named '{}'", columns.ids.join("', '"))))?;
+
+    let records = rows
+        .map(|row| match row {
+            Ok(row) => BatchRecord {
+                line: row.position().map_or(0, |position| position.line() as usize),
+                ids: id_indices.iter().map(|&index| row.get(index).unwrap_or_default().to_string()).collect(),
+                input: location.read(|index| row.get(index).map(str::to_string)),
+            },
+            Err(e) => csv_error(e),
+        })
+        .collect();
+    Ok(BatchInput { id_columns, records })
+}
+
+fn csv_error(error: csv::Error) -> BatchRecord {
+    BatchRecord {
+        line: error.position().map_or(0, |position| position.line() as usize),
+        ids: Vec::new(),
+        input: Err(MecczError::parse("CSV row", error.to_string())),
+    }
+}
+
+fn parse_jsonl(text: &str, columns: &BatchColumns) -> BatchInput {
+    let lines: Vec<(usize, Result<Value, serde_json::Error>)> = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| (index + 1, serde_json::from_str(line)))
+        .collect();
+    // Without named identifiers, `id` is kept when any object has it
+    let id_columns = id_columns(columns, |name| {
+        lines.iter().any(|(_, value)| matches!(value, Ok(Value::Object(object)) if object.contains_key(name)))
+    });
+
+    let records = lines
+        .into_iter()
+        .map(|(line, value)| {
+            let (ids, input) = match value {
+                Ok(Value::String(location)) => (vec![String::new(); id_columns.len()], non_empty(location)),
+                Ok(Value::Object(object)) => {
+                    let ids = id_columns.iter().map(|name| object.get(name).map(json_text).unwrap_or_default()).collect();
+                    (ids, read_object(&object, columns.location.as_deref()))
+                }
+                Ok(_) => (Vec::new(), Err(MecczError::parse("JSON line", "expected an object or a string"))),
+                Err(e) => (Vec::new(), Err(MecczError::parse("JSON line", e.to_string()))),
+            };
+            BatchRecord { line, ids, input }
+        })
+        .collect();
+    BatchInput { id_columns, records }
+}
+
+fn read_object(object: &Map<String, Value>, location: Option<&str>) -> Result<String, MecczError> {
+    let keys: Vec<String> = object.keys().map(|key| key.to_lowercase()).collect();
+    let columns = LocationColumns::find(&keys, location).ok_or_else(|| {
+        MecczError::parse("JSON line", "expected a location, address or query key, or latitude and longitude")
+    })?;
+    let values: Vec<&Value> = object.values().collect();
+    columns.read(|index| values.get(index).map(|value| json_text(value)))
+}
+
+/// Value of a JSON field as text; strings lose their quotes.
+fn json_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// The identifier columns named in `columns`, or `id` when `exists` finds it.
+fn id_columns(columns: &BatchColumns, exists: impl Fn(&str) -> bool) -> Vec<String> {
+    if !columns.ids.is_empty() {
+        columns.ids.clone()
+    } else if exists(DEFAULT_ID_COLUMN) {
+        vec![DEFAULT_ID_COLUMN.to_string()]
+    } else {
+        Vec::new()
+    }
+}
+
+fn non_empty(location: String) -> Result<String, MecczError> {
+    let location = location.trim().to_string();
+    if location.is_empty() { Err(MecczError::parse("location", "empty")) } else { Ok(location) }
+}
+
+/// Where a row keeps its location: one text column or a latitude and a longitude column.
+enum LocationColumns {
+    Text(usize),
+    Coordinates(usize, usize),
+}
+
+impl LocationColumns {
+    /// Finds the columns among lowercase `names`, preferring the one called `requested`.
+    fn find(names: &[String], requested: Option<&str>) -> Option<Self> {
+        let position = |candidates: &[&str]| {
+            candidates.iter().find_map(|candidate| names.iter().position(|name| name == candidate))
+        };
+        if let Some(requested) = requested {
+            return position(&[requested.to_lowercase().as_str()]).map(LocationColumns::Text);
+        }
+        if let Some(index) = position(&LOCATION_COLUMNS) {
+            return Some(LocationColumns::Text(index));
+        }
+        Some(LocationColumns::Coordinates(position(&LATITUDE_COLUMNS)?, position(&LONGITUDE_COLUMNS)?))
+    }
+
+    fn read(&self, cell: impl Fn(usize) -> Option<String>) -> Result<String, MecczError> {
+        match *self {
+            LocationColumns::Text(index) => non_empty(cell(index).unwrap_or_default()),
+            LocationColumns::Coordinates(latitude, longitude) => {
+                let latitude = non_empty(cell(latitude).unwrap_or_default())
+                    .map_err(|_| MecczError::parse("latitude", "empty"))?;
+                let longitude = non_empty(cell(longitude).unwrap_or_default())
+                    .map_err(|_| MecczError::parse("longitude", "empty"))?;
+                Ok(format!("{},{}", latitude, longitude))
+            }
+        }
+    }
+}
+
+/// The outcome of one record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchResult {
+    pub line: usize,
+    pub ids: Vec<String>,
+    pub input: String, // empty when the row could not be read
+    pub outcome: Result<(Location, QiblaDirection), MecczError>,
+}
+
+/// Resolves every record with [`MeccaApp::get_location`], `concurrency` at a time, and
+/// yields the results in input order. Failures are results too: processing continues.
+pub fn process<'a, G, Q>(
+    app: &'a MeccaApp<G, Q>,
+    records: Vec<BatchRecord>,
+    concurrency: usize,
+) -> impl Stream<Item = BatchResult> + 'a
+where
+    G: GeocodingService,
+    Q: QiblaCalculator,
+{
+    stream::iter(records)
+        .map(move |record| async move {
+            let outcome = match &record.input {
+                Ok(input) => match app.get_location(input).await {
+                    Ok(location) => {
+                        let qibla = app.get_qibla(&location);
+                        Ok((location, qibla))
+                    }
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e.clone()),
+            };
+            BatchResult {
+                line: record.line,
+                ids: record.ids,
+                input: record.input.unwrap_or_default(),
+                outcome,
+            }
+        })
+        .buffered(concurrency.max(1))
+}
+
+/// Writes batch results as CSV or JSON Lines, one row per result.
+pub struct BatchWriter<W: Write> {
+    out: Output<W>,
+    id_columns: Vec<String>, // output names of the identifier columns
+    header_written: bool,
+}
+
+enum Output<W: Write> {
+    Csv(Box<csv::Writer<W>>),
+    Jsonl(W),
+}
+
+impl<W: Write> BatchWriter<W> {
+    /// Identifier columns named like an output column or like an earlier identifier
+    /// are written with an `id_` prefix, e.g. `id_bearing`.
+    pub fn new(out: W, format: BatchFormat, id_columns: Vec<String>) -> Self {
+        let out = match format {
+            BatchFormat::Csv => Output::Csv(Box::new(csv::Writer::from_writer(out))),
+            BatchFormat::Jsonl => Output::Jsonl(out),
+        };
+        let mut taken: Vec<String> = OUTPUT_COLUMNS.map(String::from).to_vec();
+        let id_columns = id_columns
+            .into_iter()
+            .map(|mut name| {
+                while taken.contains(&name.to_lowercase()) {
+                    name = format!("id_{}", name);
+                }
+                taken.push(name.to_lowercase());
+                name
+            })
+            .collect();
+        Self { out, id_columns, header_written: false }
+    }
+
+    pub fn write(&mut self, result: &BatchResult) -> std::io::Result<()> {
+        match &mut self.out {
+            Output::Csv(out) => {
+                if !self.header_written {
+                    out.write_record(csv_header(&self.id_columns)).map_err(std::io::Error::other)?;
+                    self.header_written = true;
+                }
+                out.write_record(csv_row(&self.id_columns, result)).map_err(std::io::Error::other)
+            }
+            Output::Jsonl(out) => {
+                let row = serde_json::to_string(&json_row(&self.id_columns, result)).map_err(std::io::Error::other)?;
+                writeln!(out, "{}", row)
+            }
+        }
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.out {
+            Output::Csv(out) => out.flush(),
+            Output::Jsonl(out) => out.flush(),
+        }
+    }
+}
+
+fn csv_header(id_columns: &[String]) -> Vec<String> {
+    let mut header = vec!["line".to_string()];
+    header.extend(id_columns.iter().cloned());
+    header.extend(["input", "latitude", "longitude", "bearing", "direction", "distance_km", "error_code", "error"].map(String::from));
+    header
+}
+
+fn csv_row(id_columns: &[String], result: &BatchResult) -> Vec<String> {
+    let mut row = vec![result.line.to_string()];
+    row.extend((0..id_columns.len()).map(|index| result.ids.get(index).cloned().unwrap_or_default()));
+    row.push(result.input.clone());
+    match &result.outcome {
+        Ok((location, qibla)) => row.extend([
+            location.latitude.to_string(),
+            location.longitude.to_string(),
+            format!("{:.2}", qibla.bearing),
+            qibla.direction.clone(),
+            format!("{:.1}", qibla.distance_km),
+            String::new(),
+            String::new(),
+        ]),
+        Err(e) => {
+            row.extend(std::iter::repeat_n(String::new(), 5));
+            row.extend([e.code().to_string(), e.to_string()]);
+        }
+    }
+    row
+}
+
+/// A JSON line: the result like `meccz --json`, or the error like a failed `meccz --json`.
+fn json_row<'a>(id_columns: &[String], result: &'a BatchResult) -> JsonRow<'a> {
+    let (location, qibla, error) = match &result.outcome {
+        Ok((location, qibla)) => (Some(location), Some(qibla), None),
+        Err(e) => (None, None, Some(e)),
+    };
+    JsonRow {
+        line: result.line,
+        ids: id_columns
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (name.clone(), result.ids.get(index).cloned().unwrap_or_default().into()))
+            .collect(),
+        input: &result.input,
+        location,
+        qibla,
+        error: error.map(ToString::to_string),
+        code: error.map(MecczError::code),
+        field: error.and_then(MecczError::field),
+    }
+}
+
+#[derive(Serialize)]
+struct JsonRow<'a> {
+    line: usize,
+    #[serde(flatten)]
+    ids: Map<String, Value>,
+    input: &'a str,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    location: Option<&'a Location>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    qibla: Option<&'a QiblaDirection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<&'a str>,
+}
+
+/// Counts of a finished batch.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub errors: BTreeMap<&'static str, usize>, // failures by error code
+}
+
+impl BatchSummary {
+    pub fn record(&mut self, result: &BatchResult) {
+        self.total += 1;
+        match &result.outcome {
+            Ok(_) => self.succeeded += 1,
+            Err(e) => {
+                self.failed += 1;
+                *self.errors.entry(e.code()).or_default() += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use crate::gazetteer::fold;
+use crate::error::MecczError;
+use crate::interfaces::{GeocodingCandidate, GeocodingService, Location, Place};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const CACHE_FORMAT_VERSION: u32 = 1;
+const DEFAULT_TTL_DAYS: i64 = 30;
+const DEFAULT_NEGATIVE_TTL_DAYS: i64 = 1;
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// One cached geocoding answer; `location` is `None` for a cached "Location not found".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub query: String, // query as first typed, before normalization
+    pub location: Option<Location>,
+    pub source: String, // geocoding service that answered
+    pub cached_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<GeocodingCandidate>, // all places found, when candidates were requested
+    #[serde(default)]
+    pub candidate_limit: usize, // limit of the lookup that produced `candidates`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reverse: bool, // a reverse lookup of `location` rather than an address query
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub place: Option<Place>, // answer of a reverse lookup, `None` when nothing is there
+}
+
+impl CacheEntry {
+    /// False for a cached "Location not found".
+    pub fn is_found(&self) -> bool {
+        if self.reverse { self.place.is_some() } else { self.location.is_some() }
+    }
+
+    fn key(&self) -> String {
+        match (&self.location, self.reverse) {
+            (Some(location), true) => reverse_key(&self.source, location),
+            _ => cache_key(&self.source, &self.query),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: BTreeMap<String, CacheEntry>, // keyed by source and normalized query
+}
+
+/// Which entries [`GeocodingCache::purge`] removes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PurgeFilter {
+    All,
+    Expired,
+    Negative,
+}
+
+/// A geocoding cache stored as a JSON file.
+pub struct GeocodingCache {
+    path: PathBuf,
+    entries: BTreeMap<String, CacheEntry>,
+    ttl: Duration,
+    negative_ttl: Duration,
+    max_entries: usize,
+    dirty: bool, // entries changed since the file was read or written
+}
+
+impl GeocodingCache {
+    /// Opens the cache at `path`; a missing file is an empty cache.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let file: CacheFile = serde_json::from_str(&text)
+                    .with_context(|| format!("Corrupt geocoding cache {}", path.display()))?;
+                if file.version != CACHE_FORMAT_VERSION {
+                    return Err(anyhow!("Unsupported geocoding cache version {} in {}", file.version, path.display()));
+                }
+                // Keys are rebuilt so that files keyed by query alone stay readable
+                file.entries.into_values().map(|entry| (entry.key(), entry)).collect()
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("Cannot read geocoding cache {}", path.display())),
+        };
+
+        Ok(Self {
+            path,
+            entries,
+            ttl: Duration::days(DEFAULT_TTL_DAYS),
+            negative_ttl: Duration::days(DEFAULT_NEGATIVE_TTL_DAYS),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            dirty: false,
+        })
+    }
+
+    /// How long a found location stays valid.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// How long a "Location not found" answer stays valid.
+    pub fn with_negative_ttl(mut self, negative_ttl: Duration) -> Self {
+        self.negative_ttl = negative_ttl;
+        self
+    }
+
+    /// Maximum number of entries kept; the oldest are evicted first.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether entries were added or removed since the file was read or written.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// All entries, most recently cached first.
+    pub fn entries(&self) -> Vec<&CacheEntry> {
+        let mut entries: Vec<&CacheEntry> = self.entries.values().collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.cached_at));
+        entries
+    }
+
+    pub fn is_expired(&self, entry: &CacheEntry, now: DateTime<Utc>) -> bool {
+        let ttl = if entry.is_found() { self.ttl } else { self.negative_ttl };
+        now - entry.cached_at >= ttl
+    }
+
+    /// The unexpired answer of the geocoder called `source` to `query`, if any.
+    pub fn get(&self, source: &str, query: &str, now: DateTime<Utc>) -> Option<&CacheEntry> {
+        self.entries
+            .get(&cache_key(source, query))
+            .filter(|entry| entry.source == source && !entry.reverse && !self.is_expired(entry, now))
+    }
+
+    /// The unexpired reverse lookup of `location` by the geocoder called `source`, to
+    /// within [`REVERSE_PRECISION`] decimal places.
+    pub fn get_place(&self, source: &str, location: &Location, now: DateTime<Utc>) -> Option<&CacheEntry> {
+        self.entries
+            .get(&reverse_key(source, location))
+            .filter(|entry| entry.source == source && entry.reverse && !self.is_expired(entry, now))
+    }
+
+    /// Stores an answer, evicting the oldest entries beyond the size limit.
+    pub fn insert(&mut self, entry: CacheEntry) {
+        self.dirty = true;
+        self.entries.insert(entry.key(), entry);
+        while self.entries.len() > self.max_entries {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.cached_at)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => self.entries.remove(&key),
+                None => break,
+            };
+        }
+    }
+
+    /// Removes the entries selected by `filter` and returns how many were removed.
+    pub fn purge(&mut self, filter: PurgeFilter, now: DateTime<Utc>) -> usize {
+        let before = self.entries.len();
+        match filter {
+            PurgeFilter::All => self.entries.clear(),
+            PurgeFilter::Expired => {
+                let expired: Vec<String> = self
+                    .entries
+                    .iter()
+                    .filter(|(_, entry)| self.is_expired(entry, now))
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                for key in expired {
+                    self.entries.remove(&key);
+                }
+            }
+            PurgeFilter::Negative => self.entries.retain(|_, entry| entry.is_found()),
+        }
+        let removed = before - self.entries.len();
+        self.dirty |= removed > 0;
+        removed
+    }
+
+    /// Writes the cache back to disk, creating the directory if needed.
+    pub fn save(&mut self) -> Result<()> {
+        let text = self.to_json()?;
+        write_cache_file(&self.path, &text)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn to_json(&self) -> Result<String> {
+        let file = CacheFile {
+            version: CACHE_FORMAT_VERSION,
+            entries: self.entries.clone(),
+        };
+        Ok(serde_json::to_string_pretty(&file)?)
+    }
+}
+
+fn write_cache_file(path: &Path, text: &str) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).with_context(|| format!("Cannot create cache directory {}", parent.display()))?;
+    }
+    // Write then rename so that an interrupted run never leaves a truncated cache
+    let temporary = path.with_extension("json.tmp");
+    std::fs::write(&temporary, text).with_context(|| format!("Cannot write geocoding cache {}", temporary.display()))?;
+    std::fs::rename(&temporary, path).with_context(|| format!("Cannot write geocoding cache {}", path.display()))?;
+    Ok(())
+}
+
+/// Columns of [`write_csv`]; the place columns are only filled for reverse lookups.
+pub const CSV_COLUMNS: [&str; 10] =
+    ["query", "latitude", "longitude", "source", "cached_at", "reverse", "place", "city", "country", "country_code"];
+
+/// Writes `entries` as CSV under a [`CSV_COLUMNS`] header, one row per entry. A "Location
+/// not found" answer has empty coordinates, or an empty place for a reverse lookup.
+pub fn write_csv<W: std::io::Write>(entries: &[&CacheEntry], out: W) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(CSV_COLUMNS)?;
+    for entry in entries {
+        let (latitude, longitude) = match &entry.location {
+            Some(location) => (location.latitude.to_string(), location.longitude.to_string()),
+            None => (String::new(), String::new()),
+        };
+        let place = entry.place.as_ref();
+        writer.write_record([
+            entry.query.clone(),
+            latitude,
+            longitude,
+            entry.source.clone(),
+            entry.cached_at.to_rfc3339(),
+            entry.reverse.to_string(),
+            place.map(|place| place.display_name.clone()).unwrap_or_default(),
+            place.and_then(|place| place.city.clone()).unwrap_or_default(),
+            place.and_then(|place| place.country.clone()).unwrap_or_default(),
+            place.and_then(|place| place.country_code.clone()).unwrap_or_default(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Cache key of a query: case, accents, punctuation and spacing are ignored.
+pub fn normalize_query(query: &str) -> String {
+    fold(query)
+}
+
+/// Entries are looked up by geocoder and query, so that no service is answered for another.
+fn cache_key(source: &str, query: &str) -> String {
+    format!("{}|{}", source, normalize_query(query))
+}
+
+/// Reverse lookups are keyed by coordinates rounded to this many decimals, about 11 m.
+pub const REVERSE_PRECISION: usize = 4;
+
+fn reverse_key(source: &str, location: &Location) -> String {
+    format!("{}|@{:.*},{:.*}", source, REVERSE_PRECISION, location.latitude, REVERSE_PRECISION, location.longitude)
+}
+
+/// `$XDG_CACHE_HOME/meccz/geocoding.json`, falling back to `~/.cache` (or `%LOCALAPPDATA%`).
+pub fn default_cache_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(base.join("meccz").join("geocoding.json"))
+}
+
+/// Wraps any geocoding service with a persistent [`GeocodingCache`].
+///
+/// Found locations and places and "Location not found" answers are cached; network
+/// and other errors are not. New answers are kept in memory until
+/// [`GeocodingService::persist`] writes them: answers not persisted before the geocoder
+/// is dropped are lost. Failing to save the cache never fails a lookup.
+pub struct CachedGeocoder<G: GeocodingService> {
+    inner: G,
+    cache: Mutex<GeocodingCache>,
+}
+
+impl<G: GeocodingService> CachedGeocoder<G> {
+    pub fn new(inner: G, cache: GeocodingCache) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(cache),
+        }
+    }
+
+    pub fn inner(&self) -> &G {
+        &self.inner
+    }
+
+    fn cached(&self, address: &str) -> Option<CacheEntry> {
+        self.cache
+            .lock()
+            .expect("geocoding cache lock poisoned")
+            .get(self.inner.source(), address, Utc::now())
+            .cloned()
+    }
+
+    fn store(&self, entry: CacheEntry) {
+        self.cache.lock().expect("geocoding cache lock poisoned").insert(entry);
+    }
+
+    /// Writes the cache if it changed. The file is written without holding the lock,
+    /// so that lookups carry on meanwhile.
+    fn save(&self) -> Result<()> {
+        let (path, text) = {
+            let mut cache = self.cache.lock().expect("geocoding cache lock poisoned");
+            if !cache.is_dirty() {
+                return Ok(());
+            }
+            cache.dirty = false;
+            (cache.path.clone(), cache.to_json()?)
+        };
+        write_cache_file(&path, &text).inspect_err(|_| {
+            // Try again next time
+            self.cache.lock().expect("geocoding cache lock poisoned").dirty = true;
+        })
+    }
+}
+
+#[async_trait]
+impl<G: GeocodingService + Send + Sync> GeocodingService for CachedGeocoder<G> {
+    async fn geocode(&self, address: &str) -> Result<Location, MecczError> {
+        if let Some(entry) = self.cached(address) {
+            return entry.location.ok_or_else(|| MecczError::LocationNotFound(address.to_string()));
+        }
+
+        let result = self.inner.geocode(address).await;
+        let location = match &result {
+            Ok(location) => Some(location.clone()),
+            Err(MecczError::LocationNotFound(_)) => None,
+            Err(_) => return result,
+        };
+        self.store(CacheEntry {
+            query: address.to_string(),
+            location,
+            source: self.inner.source().to_string(),
+            cached_at: Utc::now(),
+            candidates: Vec::new(),
+            candidate_limit: 0,
+            reverse: false,
+            place: None,
+        });
+        result
+    }
+
+    async fn geocode_candidates(&self, address: &str, limit: usize) -> Result<Vec<GeocodingCandidate>, MecczError> {
+        if let Some(entry) = self.cached(address) {
+            // A shorter list than its limit is every candidate there is
+            let complete = entry.candidate_limit >= limit || entry.candidates.len() < entry.candidate_limit;
+            match entry.location {
+                None => return Err(MecczError::LocationNotFound(address.to_string())),
+                Some(_) if entry.candidate_limit > 0 && complete => {
+                    return Ok(entry.candidates.into_iter().take(limit).collect());
+                }
+                Some(_) => {}
+            }
+        }
+
+        let result = self.inner.geocode_candidates(address, limit).await;
+        let candidates = match &result {
+            Ok(candidates) => candidates.clone(),
+            Err(MecczError::LocationNotFound(_)) => Vec::new(),
+            Err(_) => return result,
+        };
+        self.store(CacheEntry {
+            query: address.to_string(),
+            location: candidates.first().map(|candidate| candidate.location.clone()),
+            source: self.inner.source().to_string(),
+            cached_at: Utc::now(),
+            candidates,
+            candidate_limit: limit,
+            reverse: false,
+            place: None,
+        });
+        result
+    }
+
+    async fn reverse_geocode(&self, location: &Location) -> Result<Place, MecczError> {
+        let not_found = || MecczError::LocationNotFound(format!("{:.5}, {:.5}", location.latitude, location.longitude));
+        let cached = self
+            .cache
+            .lock()
+            .expect("geocoding cache lock poisoned")
+            .get_place(self.inner.source(), location, Utc::now())
+            .cloned();
+        if let Some(entry) = cached {
+            return entry.place.ok_or_else(not_found);
+        }
+
+        let result = self.inner.reverse_geocode(location).await;
+        let place = match &result {
+            Ok(place) => Some(place.clone()),
+            Err(MecczError::LocationNotFound(_)) => None,
+            Err(_) => return result,
+        };
+        self.store(CacheEntry {
+            query: format!("{:.*}, {:.*}", REVERSE_PRECISION, location.latitude, REVERSE_PRECISION, location.longitude),
+            location: Some(location.clone()),
+            source: self.inner.source().to_string(),
+            cached_at: Utc::now(),
+            candidates: Vec::new(),
+            candidate_limit: 0,
+            reverse: true,
+            place,
+        });
+        result
+    }
+
+    fn source(&self) -> &str {
+        self.inner.source()
+    }
+
+    fn persist(&self) -> Result<()> {
+        let saved = self.save();
+        let inner = self.inner.persist();
+        saved.and(inner)
+    }
+}
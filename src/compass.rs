@@ -0,0 +1,119 @@
+use crate::error::MecczError;
+use crate::solar::bearing_difference;
+use std::str::FromStr;
+
+/// The 32 traditional points of the mariner's compass, clockwise from North.
+const POINT_NAMES: [&str; 32] = [
+    "N", "NbE", "NNE", "NEbN", "NE", "NEbE", "ENE", "EbN",
+    "E", "EbS", "ESE", "SEbE", "SE", "SEbS", "SSE", "SbE",
+    "S", "SbW", "SSW", "SWbS", "SW", "SWbW", "WSW", "WbS",
+    "W", "WbN", "WNW", "NWbW", "NW", "NWbN", "NNW", "NbW",
+];
+
+/// Numbers of points that have traditional names.
+pub const NAMED_POINT_COUNTS: [usize; 4] = [4, 8, 16, 32];
+
+/// One direction of a [`CompassRose`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompassPoint {
+    pub name: String,
+    pub bearing: f64,
+}
+
+/// The directions used to name bearings and to lay out the compass table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompassRose {
+    points: Vec<CompassPoint>,
+}
+
+impl CompassRose {
+    /// A 4, 8, 16 or 32-point rose with the traditional names ("NbE", "NNE", ...).
+    pub fn named(count: usize) -> Result<Self, MecczError> {
+        if !NAMED_POINT_COUNTS.contains(&count) {
+            return Err(MecczError::parse("compass points", format!("{} (expected 4, 8, 16 or 32)", count)));
+        }
+        let stride = POINT_NAMES.len() / count;
+        Ok(Self::from_points(
+            (0..count)
+                .map(|index| CompassPoint {
+                    name: POINT_NAMES[index * stride].to_string(),
+                    bearing: index as f64 * 360.0 / count as f64,
+                })
+                .collect(),
+        ))
+    }
+
+    /// A point every `step` degrees from North, named by its bearing ("10°", "20°", ...).
+    pub fn with_step(step: f64) -> Result<Self, MecczError> {
+        if !(1.0..=180.0).contains(&step) {
+            return Err(MecczError::parse("compass step", format!("{}° (expected 1 to 180 degrees)", step)));
+        }
+        let count = (360.0 / step - 1e-9).ceil() as usize;
+        Ok(Self::from_points(
+            (0..count)
+                .map(|index| {
+                    let bearing = index as f64 * step;
+                    CompassPoint { name: format!("{}°", round(bearing)), bearing }
+                })
+                .collect(),
+        ))
+    }
+
+    /// A rose made of arbitrary points, e.g. those of an existing compass table.
+    pub fn from_points(points: Vec<CompassPoint>) -> Self {
+        Self { points }
+    }
+
+    pub fn points(&self) -> &[CompassPoint] {
+        &self.points
+    }
+
+    /// The point closest to `bearing`. A bearing halfway between two points takes
+    /// the clockwise one, so on the 8-point rose 22.5° is NE.
+    pub fn nearest(&self, bearing: f64) -> Option<&CompassPoint> {
+        self.points.iter().min_by(|a, b| {
+            let a = bearing_difference(bearing, a.bearing);
+            let b = bearing_difference(bearing, b.bearing);
+            a.abs().total_cmp(&b.abs()).then((a > 0.0).cmp(&(b > 0.0)))
+        })
+    }
+
+    /// Name of the point closest to `bearing`.
+    pub fn name(&self, bearing: f64) -> String {
+        self.nearest(bearing).map(|point| point.name.clone()).unwrap_or_default()
+    }
+}
+
+impl Default for CompassRose {
+    /// The 16-point rose of the compass table.
+    fn default() -> Self {
+        Self::named(16).expect("16 is a named point count")
+    }
+}
+
+impl FromStr for CompassRose {
+    type Err = MecczError;
+
+    /// `4`, `8`, `16` or `32` for a named rose, any other count for evenly spaced
+    /// points, or a step such as `10deg` or `10°`.
+    fn from_str(input: &str) -> Result<Self, MecczError> {
+        let input = input.trim().to_lowercase();
+        if let Some(step) = input.strip_suffix("deg").or_else(|| input.strip_suffix('°')) {
+            let step = step.trim().parse::<f64>().map_err(|_| MecczError::parse("compass step", input.clone()))?;
+            return Self::with_step(step);
+        }
+        match input.parse::<usize>() {
+            Ok(count) if NAMED_POINT_COUNTS.contains(&count) => Self::named(count),
+            Ok(count) if (2..=360).contains(&count) => Self::with_step(360.0 / count as f64),
+            _ => Err(MecczError::parse(
+                "compass points",
+                format!("'{}' (expected 4, 8, 16, 32, another count up to 360 or a step like 10deg)", input),
+            )),
+        }
+    }
+}
+
+/// Bearings are shown with at most 2 decimals.
+fn round(degrees: f64) -> f64 {
+    (degrees * 100.0).round() / 100.0
+}
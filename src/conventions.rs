@@ -0,0 +1,284 @@
+use crate::compass::CompassRose;
+use crate::interfaces::{CompassTable, Location, QiblaCalculator, QiblaDirection, Target};
+use crate::qibla::{build_compass_table, rhumb_line, GreatCircleCalculator};
+use crate::solar::{bearing_difference, SUNRISE_ALTITUDE};
+use serde::{Deserialize, Serialize};
+
+/// Obliquity of the ecliptic, the sun's declination at the December solstice (sign aside).
+const OBLIQUITY_DEGREES: f64 = 23.44;
+
+/// A way of orienting towards the target, historical or modern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QiblaConvention {
+    GreatCircle,
+    RhumbLine,
+    Cardinal,
+    WinterSunrise,
+    MedievalApproximation,
+}
+
+impl QiblaConvention {
+    pub const ALL: [QiblaConvention; 5] = [
+        QiblaConvention::GreatCircle,
+        QiblaConvention::RhumbLine,
+        QiblaConvention::Cardinal,
+        QiblaConvention::WinterSunrise,
+        QiblaConvention::MedievalApproximation,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            QiblaConvention::GreatCircle => "great-circle",
+            QiblaConvention::RhumbLine => "rhumb-line",
+            QiblaConvention::Cardinal => "cardinal",
+            QiblaConvention::WinterSunrise => "winter-sunrise",
+            QiblaConvention::MedievalApproximation => "medieval-approximation",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            QiblaConvention::GreatCircle => "Great circle",
+            QiblaConvention::RhumbLine => "Rhumb line",
+            QiblaConvention::Cardinal => "Cardinal qibla",
+            QiblaConvention::WinterSunrise => "Winter sunrise",
+            QiblaConvention::MedievalApproximation => "Medieval approximation",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            QiblaConvention::GreatCircle => "initial bearing of the shortest path",
+            QiblaConvention::RhumbLine => "constant compass course",
+            QiblaConvention::Cardinal => "cardinal point closest to the great circle",
+            QiblaConvention::WinterSunrise => "sunrise azimuth at the December solstice",
+            QiblaConvention::MedievalApproximation => "tan q = sin Δλ / sin Δφ",
+        }
+    }
+
+    /// Whether this convention can orient towards `target`. The winter sunrise is
+    /// fixed by the sun, so it only stands for the direction of the Kaaba.
+    pub fn applies_to(self, target: &Target) -> bool {
+        self != QiblaConvention::WinterSunrise || target.is_kaaba()
+    }
+
+    /// A calculator applying this convention towards `target`.
+    pub fn calculator(self, target: Target) -> Box<dyn QiblaCalculator + Send + Sync> {
+        match self {
+            QiblaConvention::GreatCircle => Box::new(GreatCircleCalculator::new().with_target(target)),
+            QiblaConvention::RhumbLine => Box::new(RhumbLineCalculator::new().with_target(target)),
+            QiblaConvention::Cardinal => Box::new(CardinalCalculator::new().with_target(target)),
+            QiblaConvention::WinterSunrise => Box::new(WinterSunriseCalculator::new().with_target(target)),
+            QiblaConvention::MedievalApproximation => Box::new(MedievalApproximationCalculator::new().with_target(target)),
+        }
+    }
+}
+
+/// The direction one convention yields and how far it is from the great-circle bearing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConventionComparison {
+    pub convention: QiblaConvention,
+    pub name: String,
+    pub direction: QiblaDirection,
+    pub deviation: f64, // convention bearing minus great-circle bearing, degrees in (-180, 180]
+}
+
+/// Applies every [`QiblaConvention`] at `location`. `reference` provides the target and
+/// the great-circle result (spherical or ellipsoidal) the others are measured against.
+/// The winter sunrise is only compared for the Kaaba, as it does not depend on the target.
+pub fn compare_conventions<Q: QiblaCalculator + ?Sized>(reference: &Q, location: &Location) -> Vec<ConventionComparison> {
+    let great_circle = reference.calculate_qibla(location);
+    QiblaConvention::ALL
+        .iter()
+        .filter(|&&convention| convention.applies_to(reference.target()))
+        .map(|&convention| {
+            let direction = match convention {
+                QiblaConvention::GreatCircle => great_circle.clone(),
+                _ => convention.calculator(reference.target().clone()).calculate_qibla(location),
+            };
+            ConventionComparison {
+                convention,
+                name: convention.name().to_string(),
+                deviation: bearing_difference(direction.bearing, great_circle.bearing),
+                direction,
+            }
+        })
+        .collect()
+}
+
+fn direction(bearing: f64, distance_km: f64) -> QiblaDirection {
+    let bearing = GreatCircleCalculator::normalize_bearing(bearing);
+    QiblaDirection {
+        bearing,
+        direction: GreatCircleCalculator::bearing_to_direction(bearing),
+        distance_km,
+        magnetic: None,
+        rhumb_line: None,
+    }
+}
+
+fn great_circle_distance(location: &Location, destination: &Location) -> f64 {
+    GreatCircleCalculator::calculate_distance(location.latitude, location.longitude, destination.latitude, destination.longitude)
+}
+
+/// Constant compass course (loxodrome), as followed by navigators.
+pub struct RhumbLineCalculator {
+    target: Target,
+}
+
+impl Default for RhumbLineCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RhumbLineCalculator {
+    pub fn new() -> Self {
+        Self { target: Target::kaaba() }
+    }
+
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl QiblaCalculator for RhumbLineCalculator {
+    fn calculate_direction(&self, location: &Location, destination: &Location) -> QiblaDirection {
+        let route = rhumb_line(location, destination);
+        direction(route.bearing, route.distance_km)
+    }
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    fn calculate_compass_table(&self, location: &Location) -> CompassTable {
+        build_compass_table(location, &self.calculate_qibla(location), &CompassRose::default())
+    }
+}
+
+/// The cardinal point closest to the great-circle bearing, as in the early mosques
+/// that simply faced south (Syria, al-Andalus) or west (Central Asia).
+pub struct CardinalCalculator {
+    target: Target,
+}
+
+impl Default for CardinalCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CardinalCalculator {
+    pub fn new() -> Self {
+        Self { target: Target::kaaba() }
+    }
+
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl QiblaCalculator for CardinalCalculator {
+    fn calculate_direction(&self, location: &Location, destination: &Location) -> QiblaDirection {
+        let great_circle = GreatCircleCalculator::new().calculate_direction(location, destination);
+        direction((great_circle.bearing / 90.0).round() * 90.0, great_circle.distance_km)
+    }
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    fn calculate_compass_table(&self, location: &Location) -> CompassTable {
+        build_compass_table(location, &self.calculate_qibla(location), &CompassRose::default())
+    }
+}
+
+/// Azimuth of sunrise at the December solstice, the orientation of many early
+/// Egyptian mosques. Where the sun does not rise that day the bearing is due south.
+///
+/// The bearing stands for the Kaaba and ignores the target: only the distance
+/// follows it. See [`QiblaConvention::applies_to`].
+pub struct WinterSunriseCalculator {
+    target: Target,
+}
+
+impl Default for WinterSunriseCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WinterSunriseCalculator {
+    pub fn new() -> Self {
+        Self { target: Target::kaaba() }
+    }
+
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl QiblaCalculator for WinterSunriseCalculator {
+    fn calculate_direction(&self, location: &Location, destination: &Location) -> QiblaDirection {
+        let latitude = location.latitude.to_radians();
+        let declination = (-OBLIQUITY_DEGREES).to_radians();
+        let altitude = SUNRISE_ALTITUDE.to_radians();
+
+        let cos_azimuth = (declination.sin() - altitude.sin() * latitude.sin()) / (altitude.cos() * latitude.cos());
+        let azimuth = cos_azimuth.clamp(-1.0, 1.0).acos().to_degrees();
+        direction(azimuth, great_circle_distance(location, destination))
+    }
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    fn calculate_compass_table(&self, location: &Location) -> CompassTable {
+        build_compass_table(location, &self.calculate_qibla(location), &CompassRose::default())
+    }
+}
+
+/// The approximate formula `tan q = sin Δλ / sin Δφ` found in many medieval
+/// qibla tables; it ignores the convergence of the meridians.
+pub struct MedievalApproximationCalculator {
+    target: Target,
+}
+
+impl Default for MedievalApproximationCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MedievalApproximationCalculator {
+    pub fn new() -> Self {
+        Self { target: Target::kaaba() }
+    }
+
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl QiblaCalculator for MedievalApproximationCalculator {
+    fn calculate_direction(&self, location: &Location, destination: &Location) -> QiblaDirection {
+        let delta_lat = (destination.latitude - location.latitude).to_radians();
+        let delta_lon = (destination.longitude - location.longitude).to_radians();
+        let bearing = delta_lon.sin().atan2(delta_lat.sin()).to_degrees();
+        direction(bearing, great_circle_distance(location, destination))
+    }
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    fn calculate_compass_table(&self, location: &Location) -> CompassTable {
+        build_compass_table(location, &self.calculate_qibla(location), &CompassRose::default())
+    }
+}
@@ -0,0 +1,733 @@
+use crate::error::{MecczError, Result};
+use crate::geocoding::{looks_like_coordinates, parse_coordinates};
+use crate::interfaces::Location;
+
+const WGS84_SEMI_MAJOR_AXIS_M: f64 = 6_378_137.0;
+const WGS84_FLATTENING: f64 = 1.0 / 298.257223563;
+
+/// UTM scale factor on the central meridian.
+const UTM_SCALE_FACTOR: f64 = 0.9996;
+const UTM_FALSE_EASTING_M: f64 = 500_000.0;
+const UTM_FALSE_NORTHING_SOUTH_M: f64 = 10_000_000.0;
+
+/// Latitude bands C to X, 8° each from 80°S (X is 12° tall).
+const LATITUDE_BANDS: &str = "CDEFGHJKLMNPQRSTUVWX";
+
+/// MGRS 100 km column letters for zone sets 1, 2 and 3, and the row letters.
+const MGRS_COLUMN_LETTERS: [&str; 3] = ["ABCDEFGH", "JKLMNPQR", "STUVWXYZ"];
+const MGRS_ROW_LETTERS: &str = "ABCDEFGHJKLMNPQRSTUV";
+
+const GEOHASH_ALPHABET: &str = "0123456789bcdefghjkmnpqrstuvwxyz";
+const GEOHASH_OUTPUT_LENGTH: usize = 9;
+
+const OLC_ALPHABET: &str = "23456789CFGHJMPQRVWX";
+const OLC_SEPARATOR_POSITION: usize = 8;
+const OLC_PAIR_RESOLUTIONS: [f64; 5] = [20.0, 1.0, 0.05, 0.0025, 0.000125];
+const OLC_GRID_ROWS: f64 = 5.0;
+const OLC_GRID_COLUMNS: f64 = 4.0;
+
+/// A textual coordinate notation that can be recognised, decoded and produced offline.
+pub trait CoordinateFormat: Send + Sync {
+    /// Short lowercase identifier, as accepted by `--output-coords`.
+    fn name(&self) -> &'static str;
+
+    /// True when `input` is written in this notation. Detection should be strict:
+    /// anything claimed here is never sent to a geocoder.
+    fn detect(&self, input: &str) -> bool;
+
+    fn parse(&self, input: &str) -> Result<Location>;
+
+    fn format(&self, location: &Location) -> String;
+}
+
+/// An ordered set of coordinate formats; the first one that detects an input decodes it.
+pub struct CoordinateFormats {
+    formats: Vec<Box<dyn CoordinateFormat>>,
+}
+
+impl CoordinateFormats {
+    /// An empty registry, to be filled with [`CoordinateFormats::register`].
+    pub fn empty() -> Self {
+        Self { formats: Vec::new() }
+    }
+
+    pub fn register(&mut self, format: Box<dyn CoordinateFormat>) {
+        self.formats.push(format);
+    }
+
+    /// The first registered format recognising `input`.
+    pub fn detect(&self, input: &str) -> Option<&dyn CoordinateFormat> {
+        self.formats.iter().find(|format| format.detect(input)).map(|format| format.as_ref())
+    }
+
+    /// Decodes `input` if any registered format recognises it.
+    pub fn parse(&self, input: &str) -> Option<Result<Location>> {
+        self.detect(input).map(|format| format.parse(input))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn CoordinateFormat> {
+        self.formats
+            .iter()
+            .find(|format| format.name().eq_ignore_ascii_case(name))
+            .map(|format| format.as_ref())
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.formats.iter().map(|format| format.name()).collect()
+    }
+
+    /// Formats `location` in the notation called `name`.
+    pub fn format(&self, name: &str, location: &Location) -> Result<String> {
+        self.get(name).map(|format| format.format(location)).ok_or_else(|| {
+            MecczError::parse("coordinate format", format!("unknown format {} (expected one of {})", name, self.names().join(", ")))
+        })
+    }
+}
+
+impl Default for CoordinateFormats {
+    /// UTM, MGRS, Open Location Code, geohash, then DMS and decimal degrees.
+    fn default() -> Self {
+        let mut formats = Self::empty();
+        formats.register(Box::new(UtmFormat));
+        formats.register(Box::new(MgrsFormat));
+        formats.register(Box::new(OpenLocationCodeFormat));
+        formats.register(Box::new(GeohashFormat));
+        formats.register(Box::new(DmsFormat));
+        formats.register(Box::new(DecimalFormat));
+        formats
+    }
+}
+
+/// Signed decimal degrees, `48.856600, 2.352200`.
+pub struct DecimalFormat;
+
+impl CoordinateFormat for DecimalFormat {
+    fn name(&self) -> &'static str {
+        "decimal"
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        looks_like_coordinates(input) || parse_coordinates(input).is_ok()
+    }
+
+    fn parse(&self, input: &str) -> Result<Location> {
+        parse_coordinates(input)
+    }
+
+    fn format(&self, location: &Location) -> String {
+        format!("{:.6}, {:.6}", location.latitude, location.longitude)
+    }
+}
+
+/// Degrees, minutes and seconds with hemisphere letters, `48°51'24.0"N 2°21'07.9"E`.
+pub struct DmsFormat;
+
+impl CoordinateFormat for DmsFormat {
+    fn name(&self) -> &'static str {
+        "dms"
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        input.contains(['°', 'º', '\'', '"', '′', '″'])
+    }
+
+    fn parse(&self, input: &str) -> Result<Location> {
+        parse_coordinates(input)
+    }
+
+    fn format(&self, location: &Location) -> String {
+        let latitude_hemisphere = if location.latitude < 0.0 { 'S' } else { 'N' };
+        let longitude_hemisphere = if location.longitude < 0.0 { 'W' } else { 'E' };
+        format!(
+            "{}{} {}{}",
+            format_dms(location.latitude.abs()),
+            latitude_hemisphere,
+            format_dms(location.longitude.abs()),
+            longitude_hemisphere
+        )
+    }
+}
+
+fn format_dms(value: f64) -> String {
+    let tenths_of_seconds = (value * 36_000.0).round() as u64;
+    let degrees = tenths_of_seconds / 36_000;
+    let minutes = tenths_of_seconds % 36_000 / 600;
+    let seconds = (tenths_of_seconds % 600) as f64 / 10.0;
+    format!("{}°{:02}'{:04.1}\"", degrees, minutes, seconds)
+}
+
+/// A position on the Universal Transverse Mercator grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UtmCoordinate {
+    pub zone: u8,
+    pub band: char,
+    pub easting: f64,
+    pub northing: f64,
+}
+
+impl UtmCoordinate {
+    pub fn is_southern(&self) -> bool {
+        self.band < 'N'
+    }
+}
+
+/// Series coefficients of Krüger's transverse Mercator projection, to sixth order
+/// in the third flattening (sub-millimetre within a UTM zone).
+struct TransverseMercator {
+    rectifying_radius: f64,
+    eccentricity: f64,
+    alpha: [f64; 6],
+    beta: [f64; 6],
+}
+
+impl TransverseMercator {
+    fn wgs84() -> Self {
+        let n = WGS84_FLATTENING / (2.0 - WGS84_FLATTENING);
+        let (n2, n3, n4, n5, n6) = (n * n, n.powi(3), n.powi(4), n.powi(5), n.powi(6));
+        Self {
+            rectifying_radius: WGS84_SEMI_MAJOR_AXIS_M / (1.0 + n) * (1.0 + n2 / 4.0 + n4 / 64.0 + n6 / 256.0),
+            eccentricity: (WGS84_FLATTENING * (2.0 - WGS84_FLATTENING)).sqrt(),
+            alpha: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0 + 41.0 * n4 / 180.0 - 127.0 * n5 / 288.0 + 7891.0 * n6 / 37800.0,
+                13.0 * n2 / 48.0 - 3.0 * n3 / 5.0 + 557.0 * n4 / 1440.0 + 281.0 * n5 / 630.0 - 1983433.0 * n6 / 1935360.0,
+                61.0 * n3 / 240.0 - 103.0 * n4 / 140.0 + 15061.0 * n5 / 26880.0 + 167603.0 * n6 / 181440.0,
+                49561.0 * n4 / 161280.0 - 179.0 * n5 / 168.0 + 6601661.0 * n6 / 7257600.0,
+                34729.0 * n5 / 80640.0 - 3418889.0 * n6 / 1995840.0,
+                212378941.0 * n6 / 319334400.0,
+            ],
+            beta: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 37.0 * n3 / 96.0 - n4 / 360.0 - 81.0 * n5 / 512.0 + 96199.0 * n6 / 604800.0,
+                n2 / 48.0 + n3 / 15.0 - 437.0 * n4 / 1440.0 + 46.0 * n5 / 105.0 - 1118711.0 * n6 / 3870720.0,
+                17.0 * n3 / 480.0 - 37.0 * n4 / 840.0 - 209.0 * n5 / 4480.0 + 5569.0 * n6 / 90720.0,
+                4397.0 * n4 / 161280.0 - 11.0 * n5 / 504.0 - 830251.0 * n6 / 7257600.0,
+                4583.0 * n5 / 161280.0 - 108847.0 * n6 / 3991680.0,
+                20648693.0 * n6 / 638668800.0,
+            ],
+        }
+    }
+
+    /// Projects to (x, y) in metres relative to the central meridian and the equator.
+    fn forward(&self, latitude: f64, longitude_from_central: f64) -> (f64, f64) {
+        let e = self.eccentricity;
+        let tau = latitude.to_radians().tan();
+        let lambda = longitude_from_central.to_radians();
+        let sigma = (e * (e * tau / (1.0 + tau * tau).sqrt()).atanh()).sinh();
+        let tau_prime = tau * (1.0 + sigma * sigma).sqrt() - sigma * (1.0 + tau * tau).sqrt();
+
+        let xi_prime = tau_prime.atan2(lambda.cos());
+        let eta_prime = (lambda.sin() / tau_prime.hypot(lambda.cos())).asinh();
+
+        let mut xi = xi_prime;
+        let mut eta = eta_prime;
+        for (j, alpha) in self.alpha.iter().enumerate() {
+            let k = 2.0 * (j + 1) as f64;
+            xi += alpha * (k * xi_prime).sin() * (k * eta_prime).cosh();
+            eta += alpha * (k * xi_prime).cos() * (k * eta_prime).sinh();
+        }
+
+        let scale = UTM_SCALE_FACTOR * self.rectifying_radius;
+        (scale * eta, scale * xi)
+    }
+
+    /// Inverse of [`TransverseMercator::forward`], returning (latitude, longitude from central meridian).
+    fn inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        let e = self.eccentricity;
+        let scale = UTM_SCALE_FACTOR * self.rectifying_radius;
+        let xi = y / scale;
+        let eta = x / scale;
+
+        let mut xi_prime = xi;
+        let mut eta_prime = eta;
+        for (j, beta) in self.beta.iter().enumerate() {
+            let k = 2.0 * (j + 1) as f64;
+            xi_prime -= beta * (k * xi).sin() * (k * eta).cosh();
+            eta_prime -= beta * (k * xi).cos() * (k * eta).sinh();
+        }
+
+        let tau_prime = xi_prime.sin() / eta_prime.sinh().hypot(xi_prime.cos());
+        let mut tau = tau_prime;
+        // Newton iteration from conformal to geodetic latitude, converges in a few steps
+        for _ in 0..10 {
+            let sigma = (e * (e * tau / (1.0 + tau * tau).sqrt()).atanh()).sinh();
+            let tau_i = tau * (1.0 + sigma * sigma).sqrt() - sigma * (1.0 + tau * tau).sqrt();
+            let delta = (tau_prime - tau_i) / (1.0 + tau_i * tau_i).sqrt() * (1.0 + (1.0 - e * e) * tau * tau)
+                / ((1.0 - e * e) * (1.0 + tau * tau).sqrt());
+            tau += delta;
+            if delta.abs() < 1e-12 {
+                break;
+            }
+        }
+
+        (tau.atan().to_degrees(), eta_prime.sinh().atan2(xi_prime.cos()).to_degrees())
+    }
+}
+
+fn central_meridian(zone: u8) -> f64 {
+    zone as f64 * 6.0 - 183.0
+}
+
+fn latitude_band(latitude: f64) -> Option<char> {
+    if !(-80.0..=84.0).contains(&latitude) {
+        return None;
+    }
+    let index = (((latitude + 80.0) / 8.0).floor() as usize).min(LATITUDE_BANDS.len() - 1);
+    LATITUDE_BANDS.chars().nth(index)
+}
+
+/// Southern edge of a latitude band, in degrees.
+fn band_south_edge(band: char) -> Option<f64> {
+    LATITUDE_BANDS.find(band).map(|index| index as f64 * 8.0 - 80.0)
+}
+
+/// Converts a location to UTM, including the Norway and Svalbard zone exceptions.
+pub fn to_utm(location: &Location) -> Result<UtmCoordinate> {
+    let band = latitude_band(location.latitude)
+        .ok_or(MecczError::OutOfRange { field: "latitude".to_string(), value: location.latitude, min: -80.0, max: 84.0 })?;
+
+    let longitude = (location.longitude + 180.0).rem_euclid(360.0) - 180.0;
+    let mut zone = ((((longitude + 180.0) / 6.0).floor() as i32).clamp(0, 59) + 1) as u8;
+    if band == 'V' && zone == 31 && longitude >= 3.0 {
+        zone = 32;
+    }
+    if band == 'X' {
+        zone = match longitude {
+            l if (0.0..9.0).contains(&l) => 31,
+            l if (9.0..21.0).contains(&l) => 33,
+            l if (21.0..33.0).contains(&l) => 35,
+            l if (33.0..42.0).contains(&l) => 37,
+            _ => zone,
+        };
+    }
+
+    let (x, y) = TransverseMercator::wgs84().forward(location.latitude, longitude - central_meridian(zone));
+    let northing = if location.latitude < 0.0 { y + UTM_FALSE_NORTHING_SOUTH_M } else { y };
+    Ok(UtmCoordinate {
+        zone,
+        band,
+        easting: x + UTM_FALSE_EASTING_M,
+        northing,
+    })
+}
+
+/// Converts a UTM position back to a location; only the band's hemisphere is used.
+pub fn from_utm(utm: &UtmCoordinate) -> Result<Location> {
+    if !(1..=60).contains(&utm.zone) {
+        return Err(MecczError::parse("UTM zone", format!("must be between 1 and 60, got {}", utm.zone)));
+    }
+    if band_south_edge(utm.band).is_none() {
+        return Err(MecczError::parse("UTM latitude band", format!("'{}' (expected C to X, without I and O)", utm.band)));
+    }
+    if !(100_000.0..1_000_000.0).contains(&utm.easting) {
+        return Err(MecczError::parse("UTM easting", format!("must be between 100000 and 999999 m, got {}", utm.easting)));
+    }
+    if !(0.0..=UTM_FALSE_NORTHING_SOUTH_M).contains(&utm.northing) {
+        return Err(MecczError::parse("UTM northing", format!("must be between 0 and 10000000 m, got {}", utm.northing)));
+    }
+
+    let y = if utm.is_southern() { utm.northing - UTM_FALSE_NORTHING_SOUTH_M } else { utm.northing };
+    let (latitude, longitude) = TransverseMercator::wgs84().inverse(utm.easting - UTM_FALSE_EASTING_M, y);
+    Ok(Location {
+        latitude,
+        longitude: (longitude + central_meridian(utm.zone) + 180.0).rem_euclid(360.0) - 180.0,
+    })
+}
+
+/// Splits `31U`, `31 U` or `31` into a zone number and the rest of the token.
+fn split_zone(token: &str) -> Option<(u8, &str)> {
+    let digits = token.chars().take_while(|c| c.is_ascii_digit()).count();
+    if !(1..=2).contains(&digits) {
+        return None;
+    }
+    Some((token[..digits].parse().ok()?, &token[digits..]))
+}
+
+/// Strips the `E`/`mE` or `N`/`mN` suffix some GPS units print after UTM values.
+fn parse_grid_metres(token: &str, suffix: char) -> Option<f64> {
+    let token = token.strip_suffix(suffix).unwrap_or(token);
+    let token = token.strip_suffix('M').unwrap_or(token);
+    if token.is_empty() || !token.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// Universal Transverse Mercator, `31U 452484 5411719`.
+///
+/// The letter after the zone is the latitude band. `S` is ambiguous with the
+/// southern hemisphere marker used by some GPS units: it is read as band S
+/// (32°N to 40°N) when the position falls inside that band, as South otherwise.
+pub struct UtmFormat;
+
+impl UtmFormat {
+    fn tokens(input: &str) -> Option<(u8, char, f64, f64)> {
+        let upper = input.trim().to_uppercase().replace(',', " ");
+        let tokens: Vec<&str> = upper.split_whitespace().collect();
+        let (zone, letter, rest) = match tokens.as_slice() {
+            [zone_band, rest @ ..] if split_zone(zone_band).is_some_and(|(_, band)| band.len() == 1) => {
+                let (zone, band) = split_zone(zone_band)?;
+                (zone, band.chars().next()?, rest)
+            }
+            [zone, band, rest @ ..] if band.len() == 1 => {
+                let (zone, remainder) = split_zone(zone)?;
+                if !remainder.is_empty() {
+                    return None;
+                }
+                (zone, band.chars().next()?, rest)
+            }
+            _ => return None,
+        };
+        if !letter.is_ascii_alphabetic() {
+            return None;
+        }
+        match rest {
+            [easting, northing] => Some((zone, letter, parse_grid_metres(easting, 'E')?, parse_grid_metres(northing, 'N')?)),
+            _ => None,
+        }
+    }
+}
+
+impl CoordinateFormat for UtmFormat {
+    fn name(&self) -> &'static str {
+        "utm"
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        Self::tokens(input).is_some()
+    }
+
+    fn parse(&self, input: &str) -> Result<Location> {
+        let (zone, band, easting, northing) =
+            Self::tokens(input).ok_or_else(|| MecczError::parse("UTM", "expected zone, band, easting, northing (e.g. 31U 452484 5411719)"))?;
+
+        let utm = UtmCoordinate { zone, band, easting, northing };
+        if band == 'S' {
+            let location = from_utm(&utm)?;
+            if latitude_band(location.latitude) == Some('S') {
+                return Ok(location);
+            }
+            // Not inside band S, so the letter marks the southern hemisphere
+            return from_utm(&UtmCoordinate { band: 'M', ..utm });
+        }
+        from_utm(&utm)
+    }
+
+    fn format(&self, location: &Location) -> String {
+        match to_utm(location) {
+            Ok(utm) => format!("{}{} {:.0} {:.0}", utm.zone, utm.band, utm.easting.floor(), utm.northing.floor()),
+            Err(e) => format!("n/a ({})", e),
+        }
+    }
+}
+
+/// Military Grid Reference System, `31U DQ 52484 11719`.
+pub struct MgrsFormat;
+
+impl MgrsFormat {
+    /// The reference without spaces, when it has a valid zone, band and square and
+    /// either digits or no inner spaces: `12 Rue` or `7 Elm` are left to the geocoder.
+    fn compact(input: &str) -> Option<String> {
+        let compact: String = input.split_whitespace().collect::<String>().to_uppercase();
+        let (zone, rest) = split_zone(&compact)?;
+        let letters: Vec<char> = rest.chars().take(3).collect();
+        let digits = rest.get(letters.len()..)?;
+        let [band, column, row] = letters[..] else {
+            return None;
+        };
+        let spaced = input.split_whitespace().count() > 1;
+        let valid = (1..=60).contains(&zone)
+            && band_south_edge(band).is_some()
+            && MGRS_COLUMN_LETTERS[(zone as usize - 1) % 3].contains(column)
+            && MGRS_ROW_LETTERS.contains(row)
+            && digits.chars().all(|c| c.is_ascii_digit())
+            && digits.len() % 2 == 0
+            && digits.len() <= 10
+            && (!digits.is_empty() || !spaced);
+        valid.then_some(compact)
+    }
+}
+
+impl CoordinateFormat for MgrsFormat {
+    fn name(&self) -> &'static str {
+        "mgrs"
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        Self::compact(input).is_some()
+    }
+
+    fn parse(&self, input: &str) -> Result<Location> {
+        let compact = Self::compact(input)
+            .ok_or_else(|| MecczError::parse("MGRS", "expected zone, band, square and digits (e.g. 31U DQ 52484 11719)"))?;
+        let (zone, rest) = split_zone(&compact).ok_or_else(|| MecczError::parse("MGRS zone", "missing zone number"))?;
+        let mut letters = rest.chars();
+        let (band, column, row) = match (letters.next(), letters.next(), letters.next()) {
+            (Some(band), Some(column), Some(row)) => (band, column, row),
+            _ => return Err(MecczError::parse("MGRS", "needs a band letter and two 100 km square letters")),
+        };
+        let digits = &rest[3..];
+
+        if !(1..=60).contains(&zone) {
+            return Err(MecczError::parse("MGRS zone", format!("must be between 1 and 60, got {}", zone)));
+        }
+        let band_south = band_south_edge(band)
+            .ok_or_else(|| MecczError::parse("MGRS latitude band", format!("'{}' (polar UPS areas are not supported)", band)))?;
+        let column_index = MGRS_COLUMN_LETTERS[(zone as usize - 1) % 3]
+            .find(column)
+            .ok_or_else(|| MecczError::parse("MGRS column letter", format!("'{}' for zone {}", column, zone)))?;
+        let row_index = MGRS_ROW_LETTERS
+            .find(row)
+            .ok_or_else(|| MecczError::parse("MGRS row letter", format!("'{}'", row)))?;
+
+        let precision = digits.len() / 2;
+        let scale = 10f64.powi(5 - precision as i32);
+        let (easting_digits, northing_digits) = digits.split_at(precision);
+        let within_square = |text: &str| match text {
+            "" => Ok(0.0),
+            _ => text
+                .parse::<f64>()
+                .map(|v| v * scale)
+                .map_err(|_| MecczError::parse("MGRS digits", format!("'{}' is not a number", text))),
+        };
+        // Centre of the cell given by the precision
+        let half_cell = scale / 2.0;
+        let easting = (column_index + 1) as f64 * 100_000.0 + within_square(easting_digits)? + half_cell;
+
+        let row_offset = if zone % 2 == 0 { 5 } else { 0 };
+        let row_100km = (row_index + MGRS_ROW_LETTERS.len() - row_offset) % MGRS_ROW_LETTERS.len();
+        let mut northing = row_100km as f64 * 100_000.0 + within_square(northing_digits)? + half_cell;
+
+        // Rows repeat every 2000 km: pick the cycle that falls inside the latitude band
+        let band_edge = to_utm(&Location { latitude: band_south, longitude: central_meridian(zone) })?;
+        let band_northing = (band_edge.northing / 100_000.0).floor() * 100_000.0;
+        while northing < band_northing {
+            northing += 2_000_000.0;
+        }
+
+        from_utm(&UtmCoordinate { zone, band, easting, northing })
+    }
+
+    fn format(&self, location: &Location) -> String {
+        let utm = match to_utm(location) {
+            Ok(utm) => utm,
+            Err(e) => return format!("n/a ({})", e),
+        };
+        let column_letters = MGRS_COLUMN_LETTERS[(utm.zone as usize - 1) % 3];
+        let column = column_letters
+            .chars()
+            .nth((utm.easting / 100_000.0).floor() as usize - 1)
+            .unwrap_or('?');
+        let row_offset = if utm.zone % 2 == 0 { 5 } else { 0 };
+        let row_index = ((utm.northing / 100_000.0).floor() as usize + row_offset) % MGRS_ROW_LETTERS.len();
+        let row = MGRS_ROW_LETTERS.chars().nth(row_index).unwrap_or('?');
+
+        format!(
+            "{}{} {}{} {:05} {:05}",
+            utm.zone,
+            utm.band,
+            column,
+            row,
+            (utm.easting.floor() as u64) % 100_000,
+            (utm.northing.floor() as u64) % 100_000
+        )
+    }
+}
+
+/// Geohash, `u09tvw0f6`. A hash shorter than 7 characters, without digits or shaped
+/// like a British postcode (`ec1v9hx`) must be prefixed with `geohash:` so that words
+/// and postcodes are still sent to the geocoder.
+pub struct GeohashFormat;
+
+impl GeohashFormat {
+    fn hash(input: &str) -> Option<(String, bool)> {
+        let trimmed = input.trim();
+        let (hash, explicit) = match trimmed.get(..8) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("geohash:") => (&trimmed[8..], true),
+            _ => (trimmed, false),
+        };
+        let hash = hash.trim().to_lowercase();
+        let valid = (1..=12).contains(&hash.len()) && hash.chars().all(|c| GEOHASH_ALPHABET.contains(c));
+        let plausible = explicit
+            || (hash.len() >= 7
+                && hash.chars().any(|c| c.is_ascii_digit())
+                && hash.chars().any(|c| c.is_ascii_alphabetic())
+                && !looks_like_uk_postcode(&hash));
+        (explicit || (valid && plausible)).then_some((hash, valid))
+    }
+}
+
+/// True for `ec1v9hx` or `sw1a2aa`: an outward code of one or two letters, a digit and
+/// maybe one more character, then a digit and two letters.
+fn looks_like_uk_postcode(text: &str) -> bool {
+    let chars: Vec<char> = text.chars().collect();
+    let Some((outward, inward)) = chars.split_last_chunk::<3>() else {
+        return false;
+    };
+    let letters = outward.iter().take_while(|c| c.is_ascii_alphabetic()).count();
+    inward[0].is_ascii_digit()
+        && inward[1..].iter().all(|c| c.is_ascii_alphabetic())
+        && (1..=2).contains(&letters)
+        && (letters + 1..=letters + 2).contains(&outward.len())
+        && outward[letters].is_ascii_digit()
+}
+
+impl CoordinateFormat for GeohashFormat {
+    fn name(&self) -> &'static str {
+        "geohash"
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        Self::hash(input).is_some()
+    }
+
+    fn parse(&self, input: &str) -> Result<Location> {
+        let (hash, valid) = Self::hash(input).ok_or_else(|| MecczError::parse("geohash", format!("'{}' is not a geohash", input)))?;
+        if !valid {
+            return Err(MecczError::parse("geohash", format!("'{}': use 1 to 12 characters from {}", hash, GEOHASH_ALPHABET)));
+        }
+
+        let mut latitude = (-90.0, 90.0);
+        let mut longitude = (-180.0, 180.0);
+        let mut even_bit = true;
+        for c in hash.chars() {
+            let value = GEOHASH_ALPHABET.find(c).expect("validated above");
+            for bit in (0..5).rev() {
+                let range: &mut (f64, f64) = if even_bit { &mut longitude } else { &mut latitude };
+                let middle = (range.0 + range.1) / 2.0;
+                if value >> bit & 1 == 1 {
+                    range.0 = middle;
+                } else {
+                    range.1 = middle;
+                }
+                even_bit = !even_bit;
+            }
+        }
+
+        Ok(Location {
+            latitude: (latitude.0 + latitude.1) / 2.0,
+            longitude: (longitude.0 + longitude.1) / 2.0,
+        })
+    }
+
+    fn format(&self, location: &Location) -> String {
+        let mut latitude = (-90.0, 90.0);
+        let mut longitude = (-180.0, 180.0);
+        let mut even_bit = true;
+        let mut hash = String::with_capacity(GEOHASH_OUTPUT_LENGTH);
+        let mut value = 0;
+        let mut bits = 0;
+        while hash.len() < GEOHASH_OUTPUT_LENGTH {
+            let (range, coordinate): (&mut (f64, f64), f64) = if even_bit {
+                (&mut longitude, location.longitude)
+            } else {
+                (&mut latitude, location.latitude)
+            };
+            let middle = (range.0 + range.1) / 2.0;
+            value <<= 1;
+            if coordinate >= middle {
+                value |= 1;
+                range.0 = middle;
+            } else {
+                range.1 = middle;
+            }
+            even_bit = !even_bit;
+            bits += 1;
+            if bits == 5 {
+                hash.push(GEOHASH_ALPHABET.as_bytes()[value] as char);
+                value = 0;
+                bits = 0;
+            }
+        }
+        hash
+    }
+}
+
+/// Open Location Code (Plus Code), `8FW4V75V+8Q`. Only full codes can be decoded
+/// offline; short codes such as `V75V+8Q Paris` need a reference locality.
+pub struct OpenLocationCodeFormat;
+
+impl CoordinateFormat for OpenLocationCodeFormat {
+    fn name(&self) -> &'static str {
+        "olc"
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        let first_word = input.split_whitespace().next().unwrap_or_default().to_uppercase();
+        let separators = first_word.matches('+').count();
+        separators == 1
+            && first_word.find('+').is_some_and(|position| position % 2 == 0 && position <= OLC_SEPARATOR_POSITION)
+            && first_word.chars().all(|c| c == '+' || c == '0' || OLC_ALPHABET.contains(c))
+    }
+
+    fn parse(&self, input: &str) -> Result<Location> {
+        let trimmed = input.trim();
+        if trimmed.split_whitespace().count() > 1 {
+            return Err(MecczError::parse("Plus Code", "short codes with a locality are not supported offline, use the full code"));
+        }
+        let code = trimmed.to_uppercase();
+        let separator = code.find('+').ok_or_else(|| MecczError::parse("Plus Code", "missing its '+' separator"))?;
+        if separator != OLC_SEPARATOR_POSITION {
+            return Err(MecczError::parse("Plus Code", format!("'{}' is a short code, use the full 8-character prefix", trimmed)));
+        }
+
+        let digits: Vec<char> = code.chars().filter(|&c| c != '+').collect();
+        let padding = digits.iter().position(|&c| c == '0').unwrap_or(digits.len());
+        if padding % 2 == 1 || digits[padding..].iter().any(|&c| c != '0') || (padding < digits.len() && digits.len() > OLC_SEPARATOR_POSITION) {
+            return Err(MecczError::parse("Plus Code", format!("padding in '{}'", trimmed)));
+        }
+        let digits = &digits[..padding];
+        if digits.len() < 2 {
+            return Err(MecczError::parse("Plus Code", format!("'{}' is too short", trimmed)));
+        }
+
+        if digits.len() % 2 == 1 && digits.len() <= 2 * OLC_PAIR_RESOLUTIONS.len() {
+            return Err(MecczError::parse(
+                "Plus Code",
+                format!("'{}' has an odd number of digits before the grid refinement", trimmed),
+            ));
+        }
+
+        let value = |c: char| OLC_ALPHABET.find(c).ok_or_else(|| MecczError::parse("Plus Code", format!("character '{}'", c)));
+        let mut south = -90.0;
+        let mut west = -180.0;
+        let (mut height, mut width) = (0.0, 0.0);
+        for (pair, resolution) in digits.chunks(2).take(OLC_PAIR_RESOLUTIONS.len()).zip(OLC_PAIR_RESOLUTIONS) {
+            south += value(pair[0])? as f64 * resolution;
+            height = resolution;
+            if let Some(&c) = pair.get(1) {
+                west += value(c)? as f64 * resolution;
+                width = resolution;
+            }
+        }
+        for &c in digits.iter().skip(2 * OLC_PAIR_RESOLUTIONS.len()) {
+            let index = value(c)? as f64;
+            height /= OLC_GRID_ROWS;
+            width /= OLC_GRID_COLUMNS;
+            south += (index / OLC_GRID_COLUMNS).floor() * height;
+            west += (index % OLC_GRID_COLUMNS) * width;
+        }
+
+        let latitude = (south + height / 2.0).min(90.0);
+        let longitude = (west + width / 2.0 + 180.0).rem_euclid(360.0) - 180.0;
+        Ok(Location { latitude, longitude })
+    }
+
+    fn format(&self, location: &Location) -> String {
+        let finest = OLC_PAIR_RESOLUTIONS[OLC_PAIR_RESOLUTIONS.len() - 1];
+        // Work in integer units of the finest cell to avoid floating point drift
+        let latitude_units = (((location.latitude.clamp(-90.0, 90.0) + 90.0) / finest).floor() as i64)
+            .min((180.0 / finest) as i64 - 1);
+        let longitude_units = ((((location.longitude + 180.0).rem_euclid(360.0)) / finest).floor()) as i64;
+
+        let alphabet = OLC_ALPHABET.as_bytes();
+        let mut code = String::new();
+        for resolution in OLC_PAIR_RESOLUTIONS {
+            let step = (resolution / finest).round() as i64;
+            code.push(alphabet[((latitude_units / step) % 20) as usize] as char);
+            code.push(alphabet[((longitude_units / step) % 20) as usize] as char);
+            if code.len() == OLC_SEPARATOR_POSITION {
+                code.push('+');
+            }
+        }
+        code
+    }
+}
@@ -1,9 +1,20 @@
 use crate::{
-    interfaces::{Application, CompassTable, GeocodingService, Location, QiblaCalculator, QiblaDirection},
-    geocoding::parse_coordinates,
+    compass::CompassRose,
+    conventions::{compare_conventions, ConventionComparison},
+    qibla::{build_compass_table, rhumb_line},
+    route::{Density, GreatCirclePath},
+    coordinates::CoordinateFormats,
+    error::Result,
+    geomagnetic::MagneticModel,
+    interfaces::{
+        Application, CompassTable, GeocodingCandidate, GeocodingService, Location, Place, QiblaCalculator, QiblaDirection,
+        RhumbLine, Target,
+    },
+    istiwa::{kaaba_zenith_transits, observe_zenith_transit, ZenithTransitObservation},
+    solar::{sun_qibla_times, SunQiblaTimes},
 };
-use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{FixedOffset, NaiveDate};
 
 pub struct MeccaApp<G, Q>
 where
@@ -12,6 +23,8 @@ where
 {
     geocoding_service: G,
     qibla_calculator: Q,
+    coordinate_formats: CoordinateFormats,
+    compass_rose: CompassRose,
 }
 
 impl<G, Q> MeccaApp<G, Q>
@@ -23,23 +36,126 @@ where
         Self {
             geocoding_service,
             qibla_calculator,
+            coordinate_formats: CoordinateFormats::default(),
+            compass_rose: CompassRose::default(),
         }
     }
 
+    /// Names directions and lays out compass tables with `rose` instead of the default
+    /// 16-point one.
+    pub fn with_compass_rose(mut self, rose: CompassRose) -> Self {
+        self.compass_rose = rose;
+        self
+    }
+
+    /// Replaces the coordinate notations recognised before falling back to geocoding.
+    pub fn with_coordinate_formats(mut self, coordinate_formats: CoordinateFormats) -> Self {
+        self.coordinate_formats = coordinate_formats;
+        self
+    }
+
+    pub fn coordinate_formats(&self) -> &CoordinateFormats {
+        &self.coordinate_formats
+    }
+
     pub async fn get_location(&self, input: &str) -> Result<Location> {
-        if let Ok(location) = parse_coordinates(input) {
-            Ok(location)
-        } else {
-            self.geocoding_service.geocode(input).await
+        match self.coordinate_formats.parse(input) {
+            Some(result) => result,
+            None => self.geocoding_service.geocode(input).await,
+        }
+    }
+
+    /// Places `input` may refer to, best first. Coordinates yield a single candidate.
+    pub async fn get_location_candidates(&self, input: &str, limit: usize) -> Result<Vec<GeocodingCandidate>> {
+        match self.coordinate_formats.parse(input) {
+            Some(result) => Ok(vec![GeocodingCandidate {
+                location: result?,
+                display_name: input.trim().to_string(),
+                kind: "coordinates".to_string(),
+                importance: None,
+                bounding_box: None,
+            }]),
+            None => self.geocoding_service.geocode_candidates(input, limit).await,
         }
     }
 
+    /// Names the place at `location` with the geocoding service's reverse lookup.
+    pub async fn describe_location(&self, location: &Location) -> Result<Place> {
+        self.geocoding_service.reverse_geocode(location).await
+    }
+
+    /// Writes the geocoding service's cache, if it has one. This may block.
+    pub fn persist(&self) -> anyhow::Result<()> {
+        self.geocoding_service.persist()
+    }
+
+    /// The destination of Qibla directions, the Kaaba unless the calculator was given another.
+    pub fn target(&self) -> &Target {
+        self.qibla_calculator.target()
+    }
+
     pub fn get_compass_table(&self, location: &Location) -> CompassTable {
-        self.qibla_calculator.calculate_compass_table(location)
+        build_compass_table(location, &self.qibla_calculator.calculate_qibla(location), &self.compass_rose)
     }
 
     pub fn get_qibla(&self, location: &Location) -> QiblaDirection {
-        self.qibla_calculator.calculate_qibla(location)
+        let mut qibla = self.qibla_calculator.calculate_qibla(location);
+        self.name_direction(&mut qibla.direction, qibla.bearing);
+        qibla
+    }
+
+    /// The constant-heading route from `location` to the target.
+    pub fn get_rhumb_line(&self, location: &Location) -> RhumbLine {
+        let mut route = rhumb_line(location, &self.target().location);
+        self.name_direction(&mut route.direction, route.bearing);
+        route
+    }
+
+    /// The Qibla direction extended with the bearing a magnetic compass should show,
+    /// both named on the app's rose.
+    pub fn get_magnetic_qibla(
+        &self,
+        model: &MagneticModel,
+        location: &Location,
+        altitude_km: f64,
+        date: NaiveDate,
+    ) -> Result<QiblaDirection> {
+        let mut qibla = model.magnetic_qibla(&self.get_qibla(location), location, altitude_km, date)?;
+        if let Some(magnetic) = &mut qibla.magnetic {
+            self.name_direction(&mut magnetic.direction, magnetic.bearing);
+        }
+        Ok(qibla)
+    }
+
+    /// Renames `direction` after `bearing` on the app's rose.
+    fn name_direction(&self, direction: &mut String, bearing: f64) {
+        *direction = self.compass_rose.name(bearing);
+    }
+
+    /// The great-circle path from `location` to the target, sampled at `density`.
+    pub fn get_path(&self, location: &Location, density: Density) -> GreatCirclePath {
+        GreatCirclePath::new(location, &self.target().location, density)
+    }
+
+    /// The bearing every qibla convention yields at `location`, compared with this
+    /// app's great-circle (or geodesic) result.
+    pub fn compare_conventions(&self, location: &Location) -> Vec<ConventionComparison> {
+        let mut comparisons = compare_conventions(&self.qibla_calculator, location);
+        for comparison in &mut comparisons {
+            self.name_direction(&mut comparison.direction.direction, comparison.direction.bearing);
+        }
+        comparisons
+    }
+
+    pub fn get_sun_qibla_times(&self, location: &Location, date: NaiveDate, offset: FixedOffset) -> SunQiblaTimes {
+        sun_qibla_times(&self.qibla_calculator, location, date, offset)
+    }
+
+    pub fn get_zenith_transit_observations(&self, location: &Location, year: i32) -> Vec<ZenithTransitObservation> {
+        kaaba_zenith_transits(year)
+            .iter()
+            .map(|transit| observe_zenith_transit(&self.qibla_calculator, transit, location))
+            .collect()
     }
 }
 
@@ -51,6 +167,6 @@ where
 {
     async fn run(&self, input: &str) -> Result<QiblaDirection> {
         let location = self.get_location(input).await?;
-        Ok(self.qibla_calculator.calculate_qibla(&location))
+        Ok(self.get_qibla(&location))
     }
 }
\ No newline at end of file
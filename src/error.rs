@@ -0,0 +1,112 @@
+use std::time::Duration;
+use thiserror::Error;
+
+/// Result of the location lookup API.
+pub type Result<T, E = MecczError> = std::result::Result<T, E>;
+
+/// Why a location could not be resolved, from parsing the input to the geocoder's answer.
+///
+/// Every variant has a stable [`code`](MecczError::code) for machine-readable output
+/// and a distinct [`exit_code`](MecczError::exit_code) for the command line.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum MecczError {
+    /// Input that cannot be read; `field` names the part at fault (`latitude`, `UTM zone`...).
+    #[error("Invalid {field}: {reason}")]
+    Parse { field: String, reason: String },
+
+    /// A well-formed coordinate outside its valid range.
+    #[error("{} must be between {min} and {max} degrees (got {value})", capitalize(field))]
+    OutOfRange { field: String, value: f64, min: f64, max: f64 },
+
+    /// The query matches no place, as opposed to a failed lookup.
+    #[error("Location not found: {0}")]
+    LocationNotFound(String),
+
+    #[error("{operation} is not supported by {service}")]
+    Unsupported { service: String, operation: String },
+
+    /// The service could not be reached or answered with an HTTP error status.
+    #[error("{service} request failed: {reason}")]
+    Transport { service: String, status: Option<u16>, reason: String },
+
+    #[error("{service} is rate limiting requests{}", retry_hint(*retry_after))]
+    RateLimited { service: String, retry_after: Option<Duration> },
+
+    /// The service answered with something that is not the expected document.
+    #[error("Malformed response from {service}: {reason}")]
+    MalformedResponse { service: String, reason: String },
+}
+
+impl MecczError {
+    pub fn parse(field: impl Into<String>, reason: impl Into<String>) -> Self {
+        MecczError::Parse {
+            field: field.into(),
+            reason: reason.into(),
+        }
+    }
+
+    pub fn malformed_response(service: impl Into<String>, reason: impl ToString) -> Self {
+        MecczError::MalformedResponse {
+            service: service.into(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Stable identifier of the error kind, e.g. `location_not_found`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            MecczError::Parse { .. } => "parse_error",
+            MecczError::OutOfRange { .. } => "out_of_range",
+            MecczError::LocationNotFound(_) => "location_not_found",
+            MecczError::Unsupported { .. } => "unsupported",
+            MecczError::Transport { .. } => "transport_error",
+            MecczError::RateLimited { .. } => "rate_limited",
+            MecczError::MalformedResponse { .. } => "malformed_response",
+        }
+    }
+
+    /// Process exit status of the command line for this error. 1 is left for other
+    /// failures and 2 for usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            MecczError::Parse { .. } => 3,
+            MecczError::OutOfRange { .. } => 4,
+            MecczError::LocationNotFound(_) => 5,
+            MecczError::Unsupported { .. } => 6,
+            MecczError::Transport { .. } => 7,
+            MecczError::RateLimited { .. } => 8,
+            MecczError::MalformedResponse { .. } => 9,
+        }
+    }
+
+    /// The input field at fault, for parse and range errors.
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            MecczError::Parse { field, .. } | MecczError::OutOfRange { field, .. } => Some(field),
+            _ => None,
+        }
+    }
+
+    /// True for failures worth retrying later: rate limiting, server errors (5xx),
+    /// timeouts and lost connections. Other HTTP statuses such as 403 or 404 are permanent.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            MecczError::RateLimited { .. } => true,
+            MecczError::Transport { status: Some(status), .. } => *status >= 500,
+            MecczError::Transport { status: None, .. } => true,
+            _ => false,
+        }
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn retry_hint(retry_after: Option<Duration>) -> String {
+    retry_after.map_or_else(String::new, |delay| format!(", retry after {} s", delay.as_secs()))
+}
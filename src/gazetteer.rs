@@ -0,0 +1,410 @@
+use crate::error::MecczError;
+use crate::interfaces::{GeocodingCandidate, GeocodingService, Location, Place};
+use crate::qibla::GreatCircleCalculator;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::Path;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Country codes and names shipped with the crate, used to filter by country.
+const COUNTRIES_TSV: &str = include_str!("../data/countries.tsv");
+
+/// GeoNames admin1 code file looked up next to the gazetteer, if present.
+const ADMIN1_FILE_NAME: &str = "admin1CodesASCII.txt";
+
+/// Farthest a place may be from a location to describe it when reverse geocoding.
+const MAX_REVERSE_DISTANCE_KM: f64 = 50.0;
+
+/// Number of tab separated columns in a GeoNames dump (`cities500.txt`, `allCountries.txt`).
+const GEONAMES_COLUMNS: usize = 19;
+
+/// One place of the gazetteer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GazetteerEntry {
+    pub geoname_id: u64,
+    pub name: String,
+    pub ascii_name: String,
+    pub alternate_names: Vec<String>,
+    pub location: Location,
+    pub feature_code: String, // GeoNames feature code, e.g. PPLC for a capital
+    pub country_code: String,
+    pub admin1_code: String,
+    pub population: u64,
+}
+
+/// A gazetteer entry matching a query, with the edit distance of the name that matched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GazetteerMatch<'a> {
+    pub entry: &'a GazetteerEntry,
+    pub distance: usize,
+}
+
+/// Geocoder answering from a local GeoNames dump, for machines without internet access.
+///
+/// Queries are matched on the name, ASCII name and alternate names, ignoring case,
+/// accents and punctuation, and tolerating small typos (one edit up to 7 letters,
+/// two beyond). Comma separated qualifiers (`Paris, France`, `Springfield, IL`)
+/// filter by country code or name and by admin1 code or name. Ties are broken by
+/// population.
+pub struct OfflineGeocoder {
+    entries: Vec<GazetteerEntry>,
+    names: HashMap<String, Vec<usize>>,
+    countries: HashMap<String, String>, // folded country name or code -> ISO code
+    admin1_names: HashMap<String, String>, // "FR.11" -> folded admin1 name
+    admin1_labels: HashMap<String, String>, // "FR.11" -> admin1 name as written
+    country_labels: HashMap<String, String>, // ISO code -> country name
+}
+
+impl OfflineGeocoder {
+    /// Loads a GeoNames dump, and `admin1CodesASCII.txt` from the same directory when present.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read gazetteer {}", path.display()))?;
+        let mut geocoder = Self::from_geonames(&text)
+            .with_context(|| format!("Invalid gazetteer {}", path.display()))?;
+
+        let admin1_path = path.with_file_name(ADMIN1_FILE_NAME);
+        if admin1_path.is_file() {
+            let admin1 = std::fs::read_to_string(&admin1_path)
+                .with_context(|| format!("Cannot read {}", admin1_path.display()))?;
+            geocoder = geocoder.with_admin1_names(&admin1);
+        }
+        Ok(geocoder)
+    }
+
+    /// Builds the geocoder from the text of a GeoNames `cities*.txt`-style dump.
+    pub fn from_geonames(text: &str) -> Result<Self> {
+        let mut entries = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            entries.push(parse_geonames_line(line).with_context(|| format!("line {}", number + 1))?);
+        }
+        if entries.is_empty() {
+            return Err(anyhow!("Gazetteer contains no places"));
+        }
+
+        let mut names: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, entry) in entries.iter().enumerate() {
+            let mut keys: Vec<String> = std::iter::once(&entry.name)
+                .chain(std::iter::once(&entry.ascii_name))
+                .chain(entry.alternate_names.iter())
+                .map(|name| fold(name))
+                .filter(|key| !key.is_empty())
+                .collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                names.entry(key).or_default().push(index);
+            }
+        }
+
+        Ok(Self {
+            entries,
+            names,
+            countries: country_names(),
+            admin1_names: HashMap::new(),
+            admin1_labels: HashMap::new(),
+            country_labels: country_labels(),
+        })
+    }
+
+    /// Adds admin1 (state, region) names from a GeoNames `admin1CodesASCII.txt` file.
+    pub fn with_admin1_names(mut self, text: &str) -> Self {
+        for line in text.lines() {
+            let mut fields = line.split('\t');
+            if let (Some(code), Some(name)) = (fields.next(), fields.next()) {
+                self.admin1_names.insert(code.to_string(), fold(name));
+                self.admin1_labels.insert(code.to_string(), name.to_string());
+            }
+        }
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The best `limit` places for `query`, closest name first, then most populated.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<GazetteerMatch<'_>> {
+        let mut parts = query.split(',').map(fold).filter(|part| !part.is_empty());
+        let Some(name) = parts.next() else {
+            return Vec::new();
+        };
+        let qualifiers: Vec<String> = parts.collect();
+
+        let mut matches = self.search_name(&name, &qualifiers);
+        if matches.is_empty() && qualifiers.is_empty() {
+            // "Paris France": try trailing words as qualifiers
+            let words: Vec<&str> = name.split(' ').collect();
+            for split in (1..words.len()).rev() {
+                let qualifier = words[split..].join(" ");
+                if self.countries.contains_key(&qualifier) || self.is_admin1_name(&qualifier) {
+                    matches = self.search_name(&words[..split].join(" "), &[qualifier]);
+                    if !matches.is_empty() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        matches.sort_by(|a, b| a.distance.cmp(&b.distance).then(b.entry.population.cmp(&a.entry.population)));
+        matches.truncate(limit);
+        matches
+    }
+
+    fn search_name(&self, name: &str, qualifiers: &[String]) -> Vec<GazetteerMatch<'_>> {
+        let accepts = |index: &usize| qualifiers.iter().all(|qualifier| self.qualifies(&self.entries[*index], qualifier));
+
+        if let Some(indices) = self.names.get(name) {
+            let exact: Vec<GazetteerMatch> = indices
+                .iter()
+                .filter(|index| accepts(index))
+                .map(|&index| GazetteerMatch { entry: &self.entries[index], distance: 0 })
+                .collect();
+            if !exact.is_empty() {
+                return exact;
+            }
+        }
+
+        let max_distance = match name.chars().count() {
+            0..=3 => return Vec::new(),
+            4..=7 => 1,
+            _ => 2,
+        };
+        let mut best: HashMap<usize, usize> = HashMap::new();
+        for (key, indices) in &self.names {
+            let Some(distance) = bounded_edit_distance(name, key, max_distance) else {
+                continue;
+            };
+            for index in indices.iter().filter(|index| accepts(index)) {
+                let current = best.entry(*index).or_insert(distance);
+                *current = (*current).min(distance);
+            }
+        }
+        best.into_iter()
+            .map(|(index, distance)| GazetteerMatch { entry: &self.entries[index], distance })
+            .collect()
+    }
+
+    fn qualifies(&self, entry: &GazetteerEntry, qualifier: &str) -> bool {
+        if self.countries.get(qualifier).is_some_and(|code| code.eq_ignore_ascii_case(&entry.country_code)) {
+            return true;
+        }
+        if entry.admin1_code.eq_ignore_ascii_case(qualifier) {
+            return true;
+        }
+        let admin1_key = format!("{}.{}", entry.country_code, entry.admin1_code);
+        self.admin1_names.get(&admin1_key).is_some_and(|name| name == qualifier)
+    }
+
+    /// The place closest to `location`, if one lies within `max_distance_km`.
+    pub fn nearest(&self, location: &Location, max_distance_km: f64) -> Option<&GazetteerEntry> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let distance = GreatCircleCalculator::calculate_distance(
+                    location.latitude,
+                    location.longitude,
+                    entry.location.latitude,
+                    entry.location.longitude,
+                );
+                (entry, distance)
+            })
+            .filter(|(_, distance)| *distance <= max_distance_km)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(entry, _)| entry)
+    }
+
+    /// "Name, Admin1, Country", leaving out the parts that are unknown.
+    fn display_name(&self, entry: &GazetteerEntry) -> String {
+        let admin1 = self.admin1_labels.get(&format!("{}.{}", entry.country_code, entry.admin1_code));
+        [Some(&entry.name), admin1, self.country_labels.get(&entry.country_code)]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn is_admin1_name(&self, qualifier: &str) -> bool {
+        self.admin1_names.values().any(|name| name == qualifier)
+    }
+}
+
+#[async_trait]
+impl GeocodingService for OfflineGeocoder {
+    async fn geocode(&self, address: &str) -> Result<Location, MecczError> {
+        self.search(address, 1)
+            .first()
+            .map(|found| found.entry.location.clone())
+            .ok_or_else(|| MecczError::LocationNotFound(address.to_string()))
+    }
+
+    async fn geocode_candidates(&self, address: &str, limit: usize) -> Result<Vec<GeocodingCandidate>, MecczError> {
+        let matches = self.search(address, limit.max(1));
+        if matches.is_empty() {
+            return Err(MecczError::LocationNotFound(address.to_string()));
+        }
+        Ok(matches
+            .into_iter()
+            .map(|found| GeocodingCandidate {
+                location: found.entry.location.clone(),
+                display_name: self.display_name(found.entry),
+                kind: found.entry.feature_code.clone(),
+                importance: Some(population_importance(found.entry.population)),
+                bounding_box: None,
+            })
+            .collect())
+    }
+
+    async fn reverse_geocode(&self, location: &Location) -> Result<Place, MecczError> {
+        let entry = self
+            .nearest(location, MAX_REVERSE_DISTANCE_KM)
+            .ok_or_else(|| MecczError::LocationNotFound(format!("{:.5}, {:.5}", location.latitude, location.longitude)))?;
+
+        Ok(Place {
+            display_name: self.display_name(entry),
+            city: Some(entry.name.clone()),
+            country: self.country_labels.get(&entry.country_code).cloned(),
+            country_code: Some(entry.country_code.clone()).filter(|code| !code.is_empty()),
+        })
+    }
+
+    fn source(&self) -> &str {
+        "offline"
+    }
+}
+
+fn parse_geonames_line(line: &str) -> Result<GazetteerEntry> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() < GEONAMES_COLUMNS {
+        return Err(anyhow!("expected {} tab separated columns, found {}", GEONAMES_COLUMNS, fields.len()));
+    }
+
+    let latitude: f64 = fields[4].parse().with_context(|| format!("invalid latitude '{}'", fields[4]))?;
+    let longitude: f64 = fields[5].parse().with_context(|| format!("invalid longitude '{}'", fields[5]))?;
+    Ok(GazetteerEntry {
+        geoname_id: fields[0].parse().with_context(|| format!("invalid geonameid '{}'", fields[0]))?,
+        name: fields[1].to_string(),
+        ascii_name: fields[2].to_string(),
+        alternate_names: fields[3]
+            .split(',')
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect(),
+        location: Location { latitude, longitude },
+        feature_code: fields[7].to_string(),
+        country_code: fields[8].to_string(),
+        admin1_code: fields[10].to_string(),
+        population: fields[14].parse().unwrap_or(0),
+    })
+}
+
+/// Maps a population to 0-1 on a log scale: 0 up to a thousand inhabitants,
+/// 1 from ten million.
+fn population_importance(population: u64) -> f64 {
+    (((population as f64 + 1.0).log10() - 3.0) / 4.0).clamp(0.0, 1.0)
+}
+
+/// ISO code to the first listed name of each embedded country.
+fn country_labels() -> HashMap<String, String> {
+    COUNTRIES_TSV
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            Some((fields.next()?.to_string(), fields.next()?.to_string()))
+        })
+        .collect()
+}
+
+/// Embedded country names and aliases, folded, plus the lowercase ISO codes.
+fn country_names() -> HashMap<String, String> {
+    let rows: Vec<Vec<&str>> = COUNTRIES_TSV
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+        .map(|line| line.split('\t').collect())
+        .collect();
+
+    let mut countries = HashMap::new();
+    for row in &rows {
+        countries.insert(row[0].to_lowercase(), row[0].to_string());
+        for name in &row[1..] {
+            countries.insert(fold(name), row[0].to_string());
+        }
+    }
+    // "Britain (UK)" is also known as "Britain" and "UK", unless a full name or alias says otherwise
+    for row in &rows {
+        for fragment in row[1..].iter().flat_map(|name| name.split(['(', ')'])) {
+            let folded = fold(fragment);
+            if !folded.is_empty() {
+                countries.entry(folded).or_insert_with(|| row[0].to_string());
+            }
+        }
+    }
+    countries
+}
+
+/// Lowercases, strips accents and replaces punctuation with single spaces,
+/// so that `Saint-Étienne` and `saint etienne` compare equal.
+pub fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.nfd().filter(|c| !is_combining_mark(*c)) {
+        match c {
+            'ß' => folded.push_str("ss"),
+            'æ' | 'Æ' => folded.push_str("ae"),
+            'œ' | 'Œ' => folded.push_str("oe"),
+            'ø' | 'Ø' => folded.push('o'),
+            'ł' | 'Ł' => folded.push('l'),
+            'đ' | 'Đ' => folded.push('d'),
+            'ı' => folded.push('i'),
+            'þ' | 'Þ' => folded.push_str("th"),
+            '&' => folded.push_str(" and "),
+            c if c.is_alphanumeric() => folded.extend(c.to_lowercase()),
+            _ => folded.push(' '),
+        }
+    }
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Edit distance between `a` and `b` counting adjacent transpositions as one edit
+/// (optimal string alignment), or `None` when it exceeds `max`.
+fn bounded_edit_distance(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    let mut before_previous = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 0..a.len() {
+        current[0] = i + 1;
+        let mut row_minimum = current[0];
+        for j in 0..b.len() {
+            let substitution = previous[j] + usize::from(a[i] != b[j]);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                current[j + 1] = current[j + 1].min(before_previous[j - 1] + 1);
+            }
+            row_minimum = row_minimum.min(current[j + 1]);
+        }
+        if row_minimum > max {
+            return None;
+        }
+        std::mem::swap(&mut before_previous, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    let distance = previous[b.len()];
+    (distance <= max).then_some(distance)
+}
@@ -1,71 +1,695 @@
-use crate::interfaces::{GeocodingService, Location};
-use anyhow::{anyhow, Result};
+use crate::error::{MecczError, Result};
+use crate::interfaces::{BoundingBox, GeocodingCandidate, GeocodingService, Location, Place};
 use async_trait::async_trait;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use crate::throttle::{RateLimiter, RetryPolicy, NOMINATIM_REQUESTS_PER_SECOND};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+/// Service name used in error messages.
+const NOMINATIM: &str = "Nominatim";
+
+/// Share of the best candidate's importance above which a runner-up makes a query ambiguous.
+const AMBIGUITY_RATIO: f64 = 0.75;
 
 #[derive(Deserialize)]
-struct NominatimResponse {
+pub(crate) struct NominatimResponse {
     lat: String,
     lon: String,
+    #[serde(default)]
+    display_name: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+    importance: Option<f64>,
+    boundingbox: Option<[String; 4]>, // south, north, west, east
 }
 
-pub struct NominatimGeocoder {
+/// Parses one of the numbers Nominatim sends as strings.
+fn response_number(name: &str, text: &str) -> Result<f64> {
+    text.parse()
+        .map_err(|_| MecczError::malformed_response(NOMINATIM, format!("{} '{}' is not a number", name, text)))
+}
+
+impl NominatimResponse {
+    pub(crate) fn into_candidate(self) -> Result<GeocodingCandidate> {
+        let bounding_box = match self.boundingbox {
+            Some([south, north, west, east]) => Some(BoundingBox {
+                south: response_number("bounding box", &south)?,
+                north: response_number("bounding box", &north)?,
+                west: response_number("bounding box", &west)?,
+                east: response_number("bounding box", &east)?,
+            }),
+            None => None,
+        };
+        Ok(GeocodingCandidate {
+            location: Location {
+                latitude: response_number("lat", &self.lat)?,
+                longitude: response_number("lon", &self.lon)?,
+            },
+            display_name: self.display_name,
+            kind: self.kind,
+            importance: self.importance,
+            bounding_box,
+        })
+    }
+}
+
+/// True when several candidates are about as prominent as the best one, so the
+/// user should choose. Candidates without an importance are always ambiguous.
+pub fn candidates_are_ambiguous(candidates: &[GeocodingCandidate]) -> bool {
+    match candidates {
+        [] | [_] => false,
+        [first, second, ..] => match (first.importance, second.importance) {
+            (Some(first), Some(second)) => second >= first * AMBIGUITY_RATIO,
+            _ => true,
+        },
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct NominatimReverseResponse {
+    display_name: Option<String>,
+    #[serde(default)]
+    address: NominatimAddress,
+    error: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+struct NominatimAddress {
+    city: Option<String>,
+    town: Option<String>,
+    village: Option<String>,
+    municipality: Option<String>,
+    hamlet: Option<String>,
+    country: Option<String>,
+    country_code: Option<String>,
+}
+
+impl NominatimReverseResponse {
+    pub(crate) fn into_place(self, location: &Location) -> Result<Place> {
+        let not_found = || MecczError::LocationNotFound(format!("{:.5}, {:.5}", location.latitude, location.longitude));
+        if self.error.is_some() {
+            return Err(not_found());
+        }
+        let display_name = self.display_name.ok_or_else(not_found)?;
+        let address = self.address;
+        Ok(Place {
+            display_name,
+            city: address.city.or(address.town).or(address.village).or(address.municipality).or(address.hamlet),
+            country: address.country,
+            country_code: address.country_code.map(|code| code.to_uppercase()),
+        })
+    }
+}
+
+/// Public Nominatim instance of OpenStreetMap.
+pub const NOMINATIM_URL: &str = "https://nominatim.openstreetmap.org";
+
+/// User-Agent sent unless [`HttpClient::with_user_agent`] says otherwise; the
+/// Nominatim usage policy asks every application to identify itself.
+pub const DEFAULT_USER_AGENT: &str = concat!("meccz/", env!("CARGO_PKG_VERSION"), " (+https://github.com/valdo404/meccz)");
+
+/// Longest wait for a response before the request is given up (and maybe retried).
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Limiter shared by every geocoder of the public Nominatim instance, so that the
+/// whole process stays within the usage policy.
+static PROCESS_LIMITER: LazyLock<Arc<RateLimiter>> =
+    LazyLock::new(|| Arc::new(RateLimiter::per_second(NOMINATIM_REQUESTS_PER_SECOND)));
+
+/// HTTP transport of the web geocoders: identifies itself, gives up on slow responses,
+/// spaces requests with an optional rate limiter and retries transient failures.
+#[derive(Clone)]
+pub struct HttpClient {
     client: reqwest::Client,
+    user_agent: String,
+    timeout: Duration,
+    headers: reqwest::header::HeaderMap, // sent with every request, e.g. an API key
+    limiter: Option<Arc<RateLimiter>>,
+    retry: RetryPolicy,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A failed attempt, with the delay the server asked for before the next one.
+struct FailedAttempt {
+    error: MecczError,
+    retry_after: Option<Duration>,
+}
+
+impl From<MecczError> for FailedAttempt {
+    fn from(error: MecczError) -> Self {
+        Self { error, retry_after: None }
+    }
+}
+
+impl HttpClient {
+    /// A client with the default User-Agent, timeout and retry policy, and no rate limit.
+    pub fn new() -> Self {
+        Self {
+            // Like `reqwest::Client::new`, only a broken TLS backend can fail here
+            client: build_client(DEFAULT_USER_AGENT, DEFAULT_REQUEST_TIMEOUT).expect("cannot create the HTTP client"),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+            headers: reqwest::header::HeaderMap::new(),
+            limiter: None,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Fails on a User-Agent that is not a valid header value, e.g. one with a line break.
+    pub fn with_user_agent(mut self, user_agent: &str) -> Result<Self> {
+        self.client = build_client(user_agent, self.timeout)?;
+        self.user_agent = user_agent.to_string();
+        Ok(self)
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<Self> {
+        self.client = build_client(&self.user_agent, timeout)?;
+        self.timeout = timeout;
+        Ok(self)
+    }
+
+    /// Adds a header to every request.
+    pub fn with_header(mut self, name: &str, value: &str) -> Result<Self> {
+        let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| MecczError::parse("header name", format!("'{}': {}", name, e)))?;
+        let value = reqwest::header::HeaderValue::from_str(value)
+            .map_err(|e| MecczError::parse("header value", format!("{}: {}", name, e)))?;
+        self.headers.insert(name, value);
+        Ok(self)
+    }
+
+    /// Spaces requests with `limiter`, which may be shared with other clients.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Fetches `url` and decodes its JSON body, retrying transient failures as the
+    /// policy allows. `service` names the server in errors.
+    pub async fn get_json<T: DeserializeOwned>(&self, service: &str, url: &str) -> Result<T> {
+        let mut retry = 0;
+        loop {
+            if let Some(limiter) = &self.limiter {
+                limiter.wait().await;
+            }
+            let failure = match self.attempt(service, url).await {
+                Ok(value) => return Ok(value),
+                Err(failure) => failure,
+            };
+            if retry >= self.retry.max_retries || !failure.error.is_transient() {
+                return Err(failure.error);
+            }
+            let Some(delay) = self.retry.delay(retry, failure.retry_after) else {
+                return Err(failure.error);
+            };
+            tokio::time::sleep(delay).await;
+            retry += 1;
+        }
+    }
+
+    /// One request, with every failure classified.
+    async fn attempt<T: DeserializeOwned>(&self, service: &str, url: &str) -> std::result::Result<T, FailedAttempt> {
+        let transport = |e: reqwest::Error| MecczError::Transport {
+            service: service.to_string(),
+            status: e.status().map(|status| status.as_u16()),
+            reason: if e.is_timeout() { format!("no response within {:?}", self.timeout) } else { e.to_string() },
+        };
+
+        let response = self.client.get(url).headers(self.headers.clone()).send().await.map_err(transport)?;
+
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let error = MecczError::RateLimited { service: service.to_string(), retry_after };
+            return Err(FailedAttempt { error, retry_after });
+        }
+        if !status.is_success() {
+            let error = MecczError::Transport {
+                service: service.to_string(),
+                status: Some(status.as_u16()),
+                reason: format!("HTTP {}", status),
+            };
+            return Err(FailedAttempt { error, retry_after });
+        }
+
+        let body = response.text().await.map_err(transport)?;
+        Ok(serde_json::from_str(&body).map_err(|e| MecczError::malformed_response(service, e))?)
+    }
+}
+
+fn build_client(user_agent: &str, timeout: Duration) -> Result<reqwest::Client> {
+    let header = reqwest::header::HeaderValue::from_str(user_agent)
+        .map_err(|_| MecczError::parse("user agent", format!("'{}' (expected printable ASCII)", user_agent.escape_debug())))?;
+    reqwest::Client::builder()
+        .user_agent(header)
+        .timeout(timeout)
+        .build()
+        .map_err(|e| MecczError::Transport { service: "HTTP client".to_string(), status: None, reason: e.to_string() })
+}
+
+/// Source of the answers of `service` at `base_url`, under which they are cached: the
+/// bare name for its public server, the name and the server otherwise (`pelias@http://...`),
+/// so that two servers never share cached answers.
+pub(crate) fn endpoint_source(service: &str, base_url: &str, public_url: Option<&str>) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    match public_url {
+        Some(public_url) if public_url.trim_end_matches('/') == base_url => service.to_string(),
+        _ => format!("{}@{}", service, base_url),
+    }
+}
+
+/// Geocoder backed by a Nominatim server, the public instance unless told otherwise.
+/// Requests to the public instance are spaced to at most one per second across the
+/// process, as its usage policy asks.
+pub struct NominatimGeocoder {
+    client: HttpClient,
+    base_url: String, // without a trailing slash
+    source: String, // "nominatim", with the server unless it is the public one
+    email: Option<String>, // sent with each request, as the usage policy suggests for heavy use
+}
+
+impl Default for NominatimGeocoder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl NominatimGeocoder {
     pub fn new() -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client: HttpClient::new().with_rate_limiter(Self::process_rate_limiter()),
+            base_url: NOMINATIM_URL.to_string(),
+            source: endpoint_source("nominatim", NOMINATIM_URL, Some(NOMINATIM_URL)),
+            email: None,
         }
     }
+
+    /// The one request per second limiter shared by the whole process.
+    pub fn process_rate_limiter() -> Arc<RateLimiter> {
+        PROCESS_LIMITER.clone()
+    }
+
+    /// Server to query instead of the public instance, e.g. `http://localhost:8080`.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self.source = endpoint_source("nominatim", &self.base_url, Some(NOMINATIM_URL));
+        self
+    }
+
+    /// Contact address sent with each request, so that the operators can reach you.
+    pub fn with_email(mut self, email: Option<String>) -> Self {
+        self.email = email;
+        self
+    }
+
+    /// Transport settings. Its rate limiter, or lack of one, replaces the process-wide
+    /// limiter: give it [`NominatimGeocoder::process_rate_limiter`] for the public instance.
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let mut url = format!("{}{}", self.base_url, path);
+        if let Some(email) = &self.email {
+            url.push_str(&format!("&email={}", urlencoding::encode(email)));
+        }
+        self.client.get_json(NOMINATIM, &url).await
+    }
+}
+
+/// A `Retry-After` value: a number of seconds or an HTTP date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delay.to_std().unwrap_or_default())
 }
 
 #[async_trait]
 impl GeocodingService for NominatimGeocoder {
     async fn geocode(&self, address: &str) -> Result<Location> {
+        let candidates = self.geocode_candidates(address, 1).await?;
+        Ok(candidates[0].location.clone())
+    }
+
+    async fn geocode_candidates(&self, address: &str, limit: usize) -> Result<Vec<GeocodingCandidate>> {
         let url = format!(
-            "https://nominatim.openstreetmap.org/search?format=json&q={}&limit=1",
-            urlencoding::encode(address)
+            "/search?format=json&q={}&limit={}",
+            urlencoding::encode(address),
+            limit.max(1)
         );
 
-        let response = self.client
-            .get(&url)
-            .header("User-Agent", "meccz/1.0")
-            .send()
-            .await?;
-
-        let results: Vec<NominatimResponse> = response.json().await?;
-        
+        let results: Vec<NominatimResponse> = self.get_json(&url).await?;
         if results.is_empty() {
-            return Err(anyhow!("Location not found: {}", address));
+            return Err(MecczError::LocationNotFound(address.to_string()));
         }
 
-        let result = &results[0];
-        
-        Ok(Location {
-            latitude: result.lat.parse()?,
-            longitude: result.lon.parse()?,
-        })
+        results.into_iter().map(NominatimResponse::into_candidate).collect()
+    }
+
+    async fn reverse_geocode(&self, location: &Location) -> Result<Place> {
+        // Zoom 10 describes the surrounding city rather than the nearest building
+        let url = format!(
+            "/reverse?format=json&lat={}&lon={}&zoom=10&addressdetails=1",
+            location.latitude, location.longitude
+        );
+
+        let result: NominatimReverseResponse = self.get_json(&url).await?;
+        result.into_place(location)
+    }
+
+    fn source(&self) -> &str {
+        &self.source
     }
 }
 
-pub fn parse_coordinates(input: &str) -> Result<Location> {
-    let parts: Vec<&str> = input.split(',').collect();
-    if parts.len() != 2 {
-        return Err(anyhow!("Expected format: latitude,longitude"));
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Latitude,
+    Longitude,
+}
+
+impl Axis {
+    fn name(self) -> &'static str {
+        match self {
+            Axis::Latitude => "latitude",
+            Axis::Longitude => "longitude",
+        }
     }
+}
 
-    let latitude = parts[0].trim().parse::<f64>()?;
-    let longitude = parts[1].trim().parse::<f64>()?;
+/// Parses a coordinate pair such as `48.8566, 2.3522`, `48°51'24"N 2°21'08"E`,
+/// `N 48° 51.4' E 2° 21.1'`, `48,8566 2,3522` or `48.8566; 2.3522`.
+///
+/// Hemisphere letters may prefix or suffix each value and, when present, decide
+/// which value is the latitude. Degrees, minutes and seconds may be marked with
+/// `°`, `'`, `"` (or their typographic variants) or separated by `:` or spaces.
+pub fn parse_coordinates(input: &str) -> Result<Location> {
+    let normalized = normalize_coordinate_text(input);
+    let (first, second) = split_coordinate_pair(&normalized)?;
+
+    let (latitude_text, longitude_text) = match (hemisphere_axis(first), hemisphere_axis(second)) {
+        (Some(Axis::Longitude), Some(Axis::Latitude)) => (second, first),
+        _ => (first, second),
+    };
+
+    let latitude = parse_coordinate_component(latitude_text, Axis::Latitude)?;
+    let longitude = parse_coordinate_component(longitude_text, Axis::Longitude)?;
 
     if !(-90.0..=90.0).contains(&latitude) {
-        return Err(anyhow!("Latitude must be between -90 and 90 degrees"));
+        return Err(MecczError::OutOfRange { field: "latitude".to_string(), value: latitude, min: -90.0, max: 90.0 });
     }
 
     if !(-180.0..=180.0).contains(&longitude) {
-        return Err(anyhow!("Longitude must be between -180 and 180 degrees"));
+        return Err(MecczError::OutOfRange { field: "longitude".to_string(), value: longitude, min: -180.0, max: 180.0 });
     }
 
     Ok(Location { latitude, longitude })
-}
\ No newline at end of file
+}
+
+/// True when `input` is clearly meant as coordinates (degree marks, hemisphere
+/// letters or at least two numbers and nothing else), so a parse failure should be
+/// reported instead of sending the text to a geocoder.
+pub fn looks_like_coordinates(input: &str) -> bool {
+    let normalized = normalize_coordinate_text(input);
+    let Ok(letters) = hemisphere_letters(&normalized) else {
+        return false;
+    };
+    let numbers = normalized
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .filter(|part| part.chars().any(|c| c.is_ascii_digit()))
+        .count();
+    numbers > 0 && (numbers >= 2 || !letters.is_empty() || normalized.contains(['°', '\'', '"']))
+}
+
+/// Maps typographic variants of the degree, minute, second and minus signs to ASCII.
+fn normalize_coordinate_text(input: &str) -> String {
+    input
+        .trim()
+        .replace("''", "\"")
+        .chars()
+        .map(|c| match c {
+            'º' | '˚' => '°',
+            '′' | '’' | '‘' | '´' | '`' => '\'',
+            '″' | '”' | '“' => '"',
+            '−' | '–' => '-',
+            '\u{00a0}' | '\t' => ' ',
+            other => other,
+        })
+        .collect()
+}
+
+fn is_hemisphere_letter(c: char) -> bool {
+    matches!(c.to_ascii_uppercase(), 'N' | 'S' | 'E' | 'W')
+}
+
+/// Positions of standalone hemisphere letters; any other text is rejected.
+fn hemisphere_letters(text: &str) -> Result<Vec<(usize, char)>> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut letters = Vec::new();
+    for (index, &(position, c)) in chars.iter().enumerate() {
+        if !c.is_alphabetic() {
+            continue;
+        }
+        let before = index.checked_sub(1).map(|i| chars[i].1);
+        let after = chars.get(index + 1).map(|&(_, c)| c);
+        let standalone = !before.is_some_and(char::is_alphabetic) && !after.is_some_and(char::is_alphabetic);
+        if !(standalone && is_hemisphere_letter(c)) {
+            let word: String = text[position..].chars().take_while(|c| c.is_alphabetic()).collect();
+            return Err(MecczError::parse("coordinates", format!("unexpected text '{}'", word)));
+        }
+        letters.push((position, c.to_ascii_uppercase()));
+    }
+    Ok(letters)
+}
+
+fn hemisphere_axis(component: &str) -> Option<Axis> {
+    component.chars().find(|c| c.is_alphabetic()).map(|c| match c.to_ascii_uppercase() {
+        'N' | 'S' => Axis::Latitude,
+        _ => Axis::Longitude,
+    })
+}
+
+fn trim_separators(text: &str) -> &str {
+    text.trim_matches(|c: char| c.is_whitespace() || c == ',' || c == ';')
+}
+
+/// Splits the text into its two coordinate components.
+fn split_coordinate_pair(text: &str) -> Result<(&str, &str)> {
+    let pair_error = || MecczError::parse("coordinates", "expected format latitude,longitude");
+
+    let letters = hemisphere_letters(text)?;
+    if text.contains(';') {
+        return match text.split(';').collect::<Vec<_>>().as_slice() {
+            [first, second] => Ok((first.trim(), second.trim())),
+            _ => Err(pair_error()),
+        };
+    }
+
+    if !letters.is_empty() {
+        let [(first_position, first_letter), (second_position, second_letter)] = letters[..] else {
+            return Err(MecczError::parse(
+                "coordinates",
+                format!("expected one hemisphere letter per coordinate, found {}", letters.len()),
+            ));
+        };
+        let first_axis = hemisphere_axis(&first_letter.to_string());
+        if first_axis == hemisphere_axis(&second_letter.to_string()) {
+            return Err(MecczError::parse(
+                "coordinates",
+                format!(
+                    "hemisphere letters {} and {} both describe the {}",
+                    first_letter,
+                    second_letter,
+                    first_axis.map_or("same axis", Axis::name)
+                ),
+            ));
+        }
+
+        let prefixed = trim_separators(&text[..first_position]).is_empty();
+        let split_at = if prefixed { second_position } else { first_position + 1 };
+        let (first, second) = text.split_at(split_at);
+        let (first, second) = (trim_separators(first), trim_separators(second));
+        if first.is_empty() || second.is_empty() || (prefixed && !second.chars().skip(1).any(|c| c.is_ascii_digit())) {
+            return Err(MecczError::parse("coordinates", "hemisphere letters must all be prefixes or all be suffixes"));
+        }
+        return Ok((first, second));
+    }
+
+    let commas: Vec<usize> = text.match_indices(',').map(|(i, _)| i).collect();
+    if commas.len() == 1 {
+        let (first, second) = text.split_at(commas[0]);
+        return Ok((first.trim(), second[1..].trim()));
+    }
+
+    // Several commas: one followed by a space separates, the others are decimal commas
+    let separators: Vec<usize> = commas
+        .iter()
+        .copied()
+        .filter(|&i| text[i + 1..].starts_with(' ') || i == 0 || !text[..i].ends_with(|c: char| c.is_ascii_digit()))
+        .collect();
+    if separators.len() == 1 {
+        let (first, second) = text.split_at(separators[0]);
+        return Ok((first.trim(), second[1..].trim()));
+    }
+    if !separators.is_empty() {
+        return Err(pair_error());
+    }
+
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let split_token = match tokens.len() {
+        2 => Some(1),
+        _ if text.contains('°') => tokens.iter().skip(1).position(|t| t.contains('°')).map(|i| i + 1),
+        4 | 6 => Some(tokens.len() / 2),
+        _ => None,
+    };
+    match split_token {
+        Some(index) => {
+            // Token slices point into `text`, so the split offset is the token's start
+            let offset = tokens[index].as_ptr() as usize - text.as_ptr() as usize;
+            let (first, second) = text.split_at(offset);
+            Ok((first.trim(), second.trim()))
+        }
+        None => Err(pair_error()),
+    }
+}
+
+/// Parses one coordinate in decimal degrees, degrees-decimal-minutes or
+/// degrees-minutes-seconds, with an optional sign or hemisphere letter.
+fn parse_coordinate_component(text: &str, axis: Axis) -> Result<f64> {
+    let name = axis.name();
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(MecczError::parse(name, "missing value"));
+    }
+
+    let mut body = text;
+    let mut negative = false;
+    if let Some(rest) = body.strip_prefix('-') {
+        negative = true;
+        body = rest;
+    } else if let Some(rest) = body.strip_prefix('+') {
+        body = rest;
+    }
+
+    let mut hemisphere = None;
+    if let Some(first) = body.chars().next().filter(|c| c.is_alphabetic()) {
+        hemisphere = Some(first.to_ascii_uppercase());
+        body = &body[first.len_utf8()..];
+    } else if let Some(last) = body.chars().last().filter(|c| c.is_alphabetic()) {
+        hemisphere = Some(last.to_ascii_uppercase());
+        body = &body[..body.len() - last.len_utf8()];
+    }
+
+    if let Some(letter) = hemisphere {
+        let valid = match axis {
+            Axis::Latitude => matches!(letter, 'N' | 'S'),
+            Axis::Longitude => matches!(letter, 'E' | 'W'),
+        };
+        if !valid {
+            return Err(MecczError::parse(name, format!("hemisphere '{}' does not apply to a {}", letter, name)));
+        }
+        if negative {
+            return Err(MecczError::parse(name, format!("use either a minus sign or hemisphere '{}', not both", letter)));
+        }
+        negative = matches!(letter, 'S' | 'W');
+    }
+
+    let parts = split_sexagesimal(body, name)?;
+    let [degrees, minutes, seconds] = parts;
+    let Some(degrees) = degrees else {
+        return Err(MecczError::parse(name, format!("missing degrees in '{}'", text)));
+    };
+    if minutes.is_some_and(|m| m >= 60.0) {
+        return Err(MecczError::parse(name, format!("minutes must be below 60 (got {})", minutes.unwrap_or_default())));
+    }
+    if seconds.is_some_and(|s| s >= 60.0) {
+        return Err(MecczError::parse(name, format!("seconds must be below 60 (got {})", seconds.unwrap_or_default())));
+    }
+
+    let value = degrees + minutes.unwrap_or(0.0) / 60.0 + seconds.unwrap_or(0.0) / 3600.0;
+    Ok(if negative { -value } else { value })
+}
+
+/// Splits `48°51'24.5"`, `48 51.4` or `48:51:24` into degrees, minutes and seconds.
+fn split_sexagesimal(body: &str, name: &str) -> Result<[Option<f64>; 3]> {
+    let mut parts: [Option<f64>; 3] = [None; 3];
+    let mut next_slot = 0;
+    let mut fractional_seen = false;
+
+    let mut chars = body.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() || c == ':' {
+            chars.next();
+            continue;
+        }
+        if !c.is_ascii_digit() && c != '.' {
+            return Err(MecczError::parse(name, format!("unexpected '{}' in '{}'", c, body.trim())));
+        }
+
+        let mut end = start;
+        while let Some(&(i, c)) = chars.peek() {
+            let decimal_comma = c == ',' && body[i + 1..].starts_with(|d: char| d.is_ascii_digit());
+            if c.is_ascii_digit() || c == '.' || decimal_comma {
+                end = i + c.len_utf8();
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        let number_text = body[start..end].replace(',', ".");
+        let number: f64 = number_text
+            .parse()
+            .map_err(|_| MecczError::parse(name, format!("'{}' is not a number", &body[start..end])))?;
+
+        while chars.peek().is_some_and(|&(_, c)| c == ' ') {
+            chars.next();
+        }
+        let slot = match chars.peek().map(|&(_, c)| c) {
+            Some('°') => Some(0),
+            Some('\'') => Some(1),
+            Some('"') => Some(2),
+            _ => None,
+        };
+        if slot.is_some() {
+            chars.next();
+        }
+        let slot = slot.unwrap_or(next_slot);
+
+        let unit = ["degrees", "minutes", "seconds"];
+        if slot >= 3 {
+            return Err(MecczError::parse(name, format!("too many numbers in '{}'", body.trim())));
+        }
+        if slot < next_slot || parts[slot].is_some() {
+            return Err(MecczError::parse(name, format!("{} given out of order in '{}'", unit[slot], body.trim())));
+        }
+        if fractional_seen {
+            return Err(MecczError::parse(name, "only the last of degrees, minutes and seconds may have decimals"));
+        }
+        fractional_seen = number.fract() != 0.0 || number_text.contains('.');
+        parts[slot] = Some(number);
+        next_slot = slot + 1;
+    }
+
+    Ok(parts)
+}
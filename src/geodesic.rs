@@ -0,0 +1,69 @@
+use crate::compass::CompassRose;
+use crate::interfaces::{CompassTable, Location, QiblaCalculator, QiblaDirection, Target};
+use crate::qibla::{build_compass_table, GreatCircleCalculator};
+use geographiclib_rs::{Geodesic, InverseGeodesic};
+
+/// Qibla calculator solving the inverse geodesic problem on the WGS-84 ellipsoid.
+///
+/// Uses Karney's algorithm (via GeographicLib), which stays accurate to a few
+/// nanometres for every pair of points, including nearly antipodal ones where
+/// Vincenty's iteration fails to converge.
+pub struct EllipsoidalCalculator {
+    geodesic: Geodesic,
+    target: Target,
+}
+
+impl Default for EllipsoidalCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EllipsoidalCalculator {
+    pub fn new() -> Self {
+        Self {
+            geodesic: Geodesic::wgs84(),
+            target: Target::kaaba(),
+        }
+    }
+
+    /// Computes bearings to `target` instead of the Kaaba.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Returns the geodesic distance in kilometers and the initial azimuth in degrees (-180..180].
+    pub(crate) fn inverse(&self, lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> (f64, f64) {
+        let (s12, azi1, _azi2, _a12): (f64, f64, f64, f64) = self.geodesic.inverse(lat1, lon1, lat2, lon2);
+        (s12 / 1000.0, azi1)
+    }
+}
+
+impl QiblaCalculator for EllipsoidalCalculator {
+    fn calculate_direction(&self, location: &Location, destination: &Location) -> QiblaDirection {
+        let (distance, azimuth) = self.inverse(
+            location.latitude,
+            location.longitude,
+            destination.latitude,
+            destination.longitude,
+        );
+        let bearing = GreatCircleCalculator::normalize_bearing(azimuth);
+
+        QiblaDirection {
+            bearing,
+            direction: GreatCircleCalculator::bearing_to_direction(bearing),
+            distance_km: distance,
+            magnetic: None,
+            rhumb_line: None,
+        }
+    }
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    fn calculate_compass_table(&self, location: &Location) -> CompassTable {
+        build_compass_table(location, &self.calculate_qibla(location), &CompassRose::default())
+    }
+}
@@ -0,0 +1,278 @@
+use crate::compass::{CompassPoint, CompassRose};
+use crate::error::{MecczError, Result};
+use crate::interfaces::{CompassTable, Location, MagneticBearing, NorthReference, QiblaDirection};
+use crate::qibla::{build_compass_table, GreatCircleCalculator};
+use chrono::{Datelike, NaiveDate};
+
+/// World Magnetic Model coefficients shipped with the crate (WMM.COF format).
+const WMM_COF: &str = include_str!("../data/WMM.COF");
+
+/// Reference radius of the geomagnetic spherical harmonic expansion, in km.
+const GEOMAGNETIC_REFERENCE_RADIUS_KM: f64 = 6371.2;
+const WGS84_SEMI_MAJOR_AXIS_KM: f64 = 6378.137;
+const WGS84_FLATTENING: f64 = 1.0 / 298.257223563;
+
+/// Number of years a WMM release is valid for after its epoch.
+const MODEL_LIFESPAN_YEARS: f64 = 5.0;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Coefficient {
+    n: usize,
+    m: usize,
+    g: f64,
+    h: f64,
+    g_dot: f64,
+    h_dot: f64,
+}
+
+/// A defect of a coefficient file, reported as a parse error of the file.
+fn cof_error(reason: impl Into<String>) -> MecczError {
+    MecczError::parse("coefficient file", reason)
+}
+
+fn cof_number<T: std::str::FromStr>(field: &str) -> Result<T> {
+    field.parse().map_err(|_| cof_error(format!("not a number: {}", field)))
+}
+
+/// Geomagnetic field components at a point, in nanotesla and degrees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MagneticField {
+    pub north_nt: f64,
+    pub east_nt: f64,
+    pub down_nt: f64,
+    pub horizontal_nt: f64,
+    pub total_nt: f64,
+    pub declination: f64, // degrees, positive east of true north
+    pub inclination: f64, // degrees, positive downwards
+}
+
+/// A spherical harmonic main-field model such as the World Magnetic Model.
+#[derive(Debug, Clone)]
+pub struct MagneticModel {
+    name: String,
+    epoch: f64,
+    max_degree: usize,
+    coefficients: Vec<Coefficient>,
+}
+
+impl MagneticModel {
+    /// The World Magnetic Model embedded in the crate.
+    pub fn wmm() -> Self {
+        Self::from_cof(WMM_COF).expect("embedded WMM.COF is valid")
+    }
+
+    /// Parses a model in the NOAA `.COF` coefficient file format.
+    pub fn from_cof(text: &str) -> Result<Self> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+
+        let header = lines.next().ok_or_else(|| cof_error("empty file"))?;
+        let mut header_fields = header.split_whitespace();
+        let epoch = cof_number(header_fields.next().ok_or_else(|| cof_error("missing model epoch in header"))?)?;
+        let name = header_fields
+            .next()
+            .ok_or_else(|| cof_error("missing model name in header"))?
+            .to_string();
+
+        let mut coefficients = Vec::new();
+        for line in lines {
+            if line.trim_start().starts_with("9999") {
+                break;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 6 {
+                return Err(cof_error(format!("malformed coefficient line: {}", line.trim())));
+            }
+            coefficients.push(Coefficient {
+                n: cof_number(fields[0])?,
+                m: cof_number(fields[1])?,
+                g: cof_number(fields[2])?,
+                h: cof_number(fields[3])?,
+                g_dot: cof_number(fields[4])?,
+                h_dot: cof_number(fields[5])?,
+            });
+        }
+
+        let max_degree = coefficients.iter().map(|c| c.n).max().unwrap_or(0);
+        if max_degree == 0 || coefficients.iter().any(|c| c.m > c.n) {
+            return Err(cof_error("no valid terms"));
+        }
+
+        Ok(Self {
+            name,
+            epoch,
+            max_degree,
+            coefficients,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn epoch(&self) -> f64 {
+        self.epoch
+    }
+
+    /// Decimal years between which the model is valid, end excluded.
+    pub fn validity(&self) -> (f64, f64) {
+        (self.epoch, self.epoch + MODEL_LIFESPAN_YEARS)
+    }
+
+    /// Evaluates the main field at `altitude_km` above the WGS-84 ellipsoid on `date`.
+    pub fn field(&self, location: &Location, altitude_km: f64, date: NaiveDate) -> Result<MagneticField> {
+        let year = decimal_year(date);
+        let (valid_from, valid_until) = self.validity();
+        if year < valid_from || year >= valid_until {
+            return Err(MecczError::parse(
+                "date",
+                format!("{} is outside the {} validity window ({:.1} to {:.1})", date, self.name, valid_from, valid_until),
+            ));
+        }
+
+        // Keep clear of the poles where the east component is undefined
+        let latitude = location.latitude.clamp(-89.999_999, 89.999_999).to_radians();
+        let longitude = location.longitude.to_radians();
+        let dt = year - self.epoch;
+
+        // Geodetic to geocentric spherical coordinates
+        let e2 = WGS84_FLATTENING * (2.0 - WGS84_FLATTENING);
+        let prime_vertical = WGS84_SEMI_MAJOR_AXIS_KM / (1.0 - e2 * latitude.sin().powi(2)).sqrt();
+        let p = (prime_vertical + altitude_km) * latitude.cos();
+        let z = (prime_vertical * (1.0 - e2) + altitude_km) * latitude.sin();
+        let radius = p.hypot(z);
+        let geocentric_latitude = (z / radius).asin();
+
+        let (legendre, legendre_derivative) = schmidt_legendre(self.max_degree, geocentric_latitude);
+
+        let mut north = 0.0;
+        let mut east = 0.0;
+        let mut down = 0.0;
+        for coefficient in &self.coefficients {
+            let (n, m) = (coefficient.n, coefficient.m);
+            let g = coefficient.g + dt * coefficient.g_dot;
+            let h = coefficient.h + dt * coefficient.h_dot;
+            let ratio = (GEOMAGNETIC_REFERENCE_RADIUS_KM / radius).powi(n as i32 + 2);
+            let (sin_ml, cos_ml) = (m as f64 * longitude).sin_cos();
+
+            north += ratio * (g * cos_ml + h * sin_ml) * legendre_derivative[n][m];
+            east += ratio * m as f64 * (g * sin_ml - h * cos_ml) * legendre[n][m];
+            down -= ratio * (n as f64 + 1.0) * (g * cos_ml + h * sin_ml) * legendre[n][m];
+        }
+        east /= geocentric_latitude.cos();
+
+        // Rotate from geocentric to geodetic axes
+        let psi = geocentric_latitude - latitude;
+        let north_nt = north * psi.cos() - down * psi.sin();
+        let down_nt = north * psi.sin() + down * psi.cos();
+        let east_nt = east;
+
+        let horizontal_nt = north_nt.hypot(east_nt);
+        Ok(MagneticField {
+            north_nt,
+            east_nt,
+            down_nt,
+            horizontal_nt,
+            total_nt: horizontal_nt.hypot(down_nt),
+            declination: east_nt.atan2(north_nt).to_degrees(),
+            inclination: down_nt.atan2(horizontal_nt).to_degrees(),
+        })
+    }
+
+    /// Magnetic declination in degrees, positive when magnetic north lies east of true north.
+    pub fn declination(&self, location: &Location, altitude_km: f64, date: NaiveDate) -> Result<f64> {
+        Ok(self.field(location, altitude_km, date)?.declination)
+    }
+
+    /// Returns `qibla` extended with the bearing a magnetic compass should show.
+    pub fn magnetic_qibla(
+        &self,
+        qibla: &QiblaDirection,
+        location: &Location,
+        altitude_km: f64,
+        date: NaiveDate,
+    ) -> Result<QiblaDirection> {
+        let declination = self.declination(location, altitude_km, date)?;
+        let (valid_from, valid_until) = self.validity();
+
+        let mut result = qibla.clone();
+        let bearing = GreatCircleCalculator::normalize_bearing(qibla.bearing - declination);
+        result.magnetic = Some(MagneticBearing {
+            bearing,
+            direction: GreatCircleCalculator::bearing_to_direction(bearing),
+            declination,
+            date,
+            model: self.name.clone(),
+            valid_from,
+            valid_until,
+        });
+        Ok(result)
+    }
+
+    /// Re-expresses a compass table in magnetic degrees: the compass points become
+    /// magnetic headings and the Qibla bearing is corrected by the local declination.
+    pub fn magnetic_compass_table(
+        &self,
+        table: &CompassTable,
+        altitude_km: f64,
+        date: NaiveDate,
+    ) -> Result<CompassTable> {
+        let declination = self.declination(&table.location, altitude_km, date)?;
+        let magnetic = QiblaDirection {
+            bearing: GreatCircleCalculator::normalize_bearing(table.qibla_bearing - declination),
+            direction: String::new(),
+            distance_km: table.direct_distance_km,
+            magnetic: None,
+            rhumb_line: None,
+        };
+
+        // Keep the rose of the true-North table
+        let rose = CompassRose::from_points(
+            table
+                .entries
+                .iter()
+                .map(|entry| CompassPoint { name: entry.direction.clone(), bearing: entry.bearing })
+                .collect(),
+        );
+        let mut result = build_compass_table(&table.location, &magnetic, &rose);
+        result.north_reference = NorthReference::Magnetic;
+        Ok(result)
+    }
+}
+
+/// Converts a calendar date to a decimal year (e.g. 2025-07-02 -> 2025.5).
+pub fn decimal_year(date: NaiveDate) -> f64 {
+    let days_in_year = if date.leap_year() { 366.0 } else { 365.0 };
+    date.year() as f64 + (date.ordinal0() as f64) / days_in_year
+}
+
+/// Schmidt semi-normalized associated Legendre functions P(n, m) of sin(latitude),
+/// and their derivatives with respect to colatitude.
+fn schmidt_legendre(max_degree: usize, geocentric_latitude: f64) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+    let x = geocentric_latitude.sin(); // cos(colatitude)
+    let s = geocentric_latitude.cos(); // sin(colatitude)
+
+    let mut p = vec![vec![0.0; max_degree + 1]; max_degree + 1];
+    let mut dp = vec![vec![0.0; max_degree + 1]; max_degree + 1];
+    p[0][0] = 1.0;
+
+    for n in 1..=max_degree {
+        for m in 0..=n {
+            if m == n {
+                let k = if n == 1 { 1.0 } else { ((2 * n - 1) as f64 / (2 * n) as f64).sqrt() };
+                p[n][n] = k * s * p[n - 1][n - 1];
+                dp[n][n] = k * (x * p[n - 1][n - 1] + s * dp[n - 1][n - 1]);
+            } else {
+                let nf = n as f64;
+                let mf = m as f64;
+                let norm = (nf * nf - mf * mf).sqrt();
+                let back = if n >= 2 { ((nf - 1.0).powi(2) - mf * mf).max(0.0).sqrt() } else { 0.0 };
+                let (p2, dp2) = if n >= 2 { (p[n - 2][m], dp[n - 2][m]) } else { (0.0, 0.0) };
+
+                p[n][m] = ((2.0 * nf - 1.0) * x * p[n - 1][m] - back * p2) / norm;
+                dp[n][m] = ((2.0 * nf - 1.0) * (x * dp[n - 1][m] - s * p[n - 1][m]) - back * dp2) / norm;
+            }
+        }
+    }
+
+    (p, dp)
+}
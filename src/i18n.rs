@@ -0,0 +1,796 @@
+use crate::conventions::QiblaConvention;
+use crate::error::MecczError;
+use crate::interfaces::{Location, Target};
+use std::str::FromStr;
+
+/// Marks a line as right-to-left for bidi-aware terminals.
+const RIGHT_TO_LEFT_MARK: char = '\u{200F}';
+/// First-strong isolate and pop directional isolate, around values inserted in RTL text.
+const FIRST_STRONG_ISOLATE: char = '\u{2068}';
+const POP_DIRECTIONAL_ISOLATE: char = '\u{2069}';
+
+/// Languages of the text output. JSON output is the same in every language.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    English,
+    Arabic,
+    Urdu,
+    Turkish,
+    Malay,
+    French,
+    Indonesian,
+}
+
+impl Language {
+    pub const ALL: [Language; 7] = [
+        Language::English,
+        Language::Arabic,
+        Language::Urdu,
+        Language::Turkish,
+        Language::Malay,
+        Language::French,
+        Language::Indonesian,
+    ];
+
+    /// ISO 639-1 code.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Arabic => "ar",
+            Language::Urdu => "ur",
+            Language::Turkish => "tr",
+            Language::Malay => "ms",
+            Language::French => "fr",
+            Language::Indonesian => "id",
+        }
+    }
+
+    pub fn is_right_to_left(self) -> bool {
+        matches!(self, Language::Arabic | Language::Urdu)
+    }
+
+    fn catalog(self) -> &'static Catalog {
+        match self {
+            Language::English => &ENGLISH,
+            Language::Arabic => &ARABIC,
+            Language::Urdu => &URDU,
+            Language::Turkish => &TURKISH,
+            Language::Malay => &MALAY,
+            Language::French => &FRENCH,
+            Language::Indonesian => &INDONESIAN,
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = MecczError;
+
+    /// A language code, optionally with a region and encoding as in `LANG` (`fr_FR.UTF-8`).
+    fn from_str(input: &str) -> Result<Self, MecczError> {
+        let code = input.trim().to_lowercase();
+        let code = code.split(['-', '_', '.']).next().unwrap_or_default();
+        Language::ALL
+            .into_iter()
+            .find(|language| language.code() == code)
+            .ok_or_else(|| {
+                let codes: Vec<&str> = Language::ALL.iter().map(|language| language.code()).collect();
+                MecczError::parse("language", format!("'{}' (expected one of {})", input.trim(), codes.join(", ")))
+            })
+    }
+}
+
+/// How numbers are written.
+struct NumberFormat {
+    decimal_separator: char,
+    group_separator: Option<char>, // thousands; none in English to keep its output unchanged
+    zero: char,                    // digit zero of the script, the other digits follow it
+    list_separator: &'static str,  // between latitude and longitude
+}
+
+/// Text of the CLI output. Templates take their arguments in place of `{}`, in order.
+pub struct Catalog {
+    pub direction_to: &'static str,
+    pub bearing: &'static str,
+    pub magnetic_bearing: &'static str,
+    pub direction: &'static str,
+    pub distance: &'static str,
+    pub kilometres: &'static str,
+    pub place: &'static str,
+    pub location: &'static str,
+    pub location_format: &'static str,
+    pub rhumb_title: &'static str,
+    pub rhumb_bearing: &'static str,
+    pub rhumb_distance: &'static str,
+    pub rhumb_explanation: &'static str,
+    pub qibla_direction: &'static str,
+    pub target_direction: &'static str,
+    pub magnetic: &'static str,
+    pub direct_distance: &'static str,
+    pub table_title: &'static str,
+    pub header_direction: &'static str,
+    pub header_bearing: &'static str,
+    pub header_difference: &'static str,
+    pub header_closest: &'static str,
+    pub header_reached_at: &'static str,
+    pub header_route: &'static str,
+    pub header_long_path: &'static str,
+    pub header_optimal: &'static str,
+    pub left: &'static str,
+    pub right: &'static str,
+    pub legend_optimal_qibla: &'static str,
+    pub legend_optimal: &'static str,
+    pub legend_closest: &'static str,
+    pub legend_reached_at: &'static str,
+    pub legend_route: &'static str,
+    pub legend_long_path: &'static str,
+    pub conventions_towards: &'static str,
+    pub header_convention: &'static str,
+    pub header_deviation: &'static str,
+    pub header_distance: &'static str,
+    pub legend_deviation: &'static str,
+    pub conventions: [(&'static str, &'static str); 5], // name and description, in QiblaConvention::ALL order
+    pub mecca: &'static str,
+    pub error: &'static str,
+    pub invalid_input: &'static str,       // field, reason
+    pub out_of_range: &'static str,        // field, minimum, maximum, value
+    pub location_not_found: &'static str,
+    pub unsupported: &'static str,         // operation, service
+    pub request_failed: &'static str,      // service, reason
+    pub rate_limited: &'static str,
+    pub rate_limited_retry: &'static str,  // service, seconds
+    pub malformed_response: &'static str,  // service, reason
+    points: [&'static str; 4], // N, E, S, W as written in compass point names
+    point_by: &'static str,    // "by" of the 32-point names (NbE)
+    point_separator: &'static str,
+    numbers: NumberFormat,
+}
+
+/// Translates the text output into one [`Language`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Localizer {
+    language: Language,
+}
+
+impl Localizer {
+    pub fn new(language: Language) -> Self {
+        Self { language }
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    pub fn text(&self) -> &'static Catalog {
+        self.language.catalog()
+    }
+
+    /// Fills `template` with `args`, one line at a time. In right-to-left languages each
+    /// line starts with a right-to-left mark and the arguments are isolated, so that
+    /// numbers and Latin place names keep their own direction.
+    pub fn format(&self, template: &str, args: &[&str]) -> String {
+        let mut args = args.iter();
+        let mut parts = template.split("{}");
+        let mut text = parts.next().unwrap_or_default().to_string();
+        for part in parts {
+            if let Some(arg) = args.next() {
+                if self.language.is_right_to_left() {
+                    text.push(FIRST_STRONG_ISOLATE);
+                    text.push_str(arg);
+                    text.push(POP_DIRECTIONAL_ISOLATE);
+                } else {
+                    text.push_str(arg);
+                }
+            }
+            text.push_str(part);
+        }
+        text.lines().map(|line| self.line(line)).collect::<Vec<_>>().join("\n")
+    }
+
+    /// `text` marked as a right-to-left line when the language needs it.
+    pub fn line(&self, text: &str) -> String {
+        if self.language.is_right_to_left() {
+            format!("{}{}", RIGHT_TO_LEFT_MARK, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// `value` with `decimals` decimals, in the digits and separators of the language.
+    pub fn number(&self, value: f64, decimals: usize) -> String {
+        self.format_number(value, decimals, true)
+    }
+
+    /// Like [`Localizer::number`] without thousands separators, for years.
+    pub fn ungrouped(&self, value: f64, decimals: usize) -> String {
+        self.format_number(value, decimals, false)
+    }
+
+    /// Latitude and longitude with 4 decimals, separated so that decimal commas stay readable.
+    pub fn coordinates(&self, location: &Location) -> String {
+        format!(
+            "{}{}{}",
+            self.number(location.latitude, 4),
+            self.text().numbers.list_separator,
+            self.number(location.longitude, 4)
+        )
+    }
+
+    fn format_number(&self, value: f64, decimals: usize, grouped: bool) -> String {
+        let numbers = &self.text().numbers;
+        let formatted = format!("{:.*}", decimals, value);
+        let (sign, digits) = match formatted.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", formatted.as_str()),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+        let mut text = sign.to_string();
+        for (index, digit) in integer.chars().enumerate() {
+            if let Some(separator) = numbers.group_separator
+                && grouped
+                && index > 0
+                && (integer.len() - index) % 3 == 0
+            {
+                text.push(separator);
+            }
+            text.push(digit);
+        }
+        if !fraction.is_empty() {
+            text.push(numbers.decimal_separator);
+            text.push_str(fraction);
+        }
+        text.chars().map(|c| localize_digit(c, numbers.zero)).collect()
+    }
+
+    /// Like [`Localizer::number`] with an explicit `+` on positive values.
+    pub fn signed(&self, value: f64, decimals: usize) -> String {
+        let text = self.number(value, decimals);
+        if text.starts_with('-') { text } else { format!("+{}", text) }
+    }
+
+    /// Translates a compass point name of a [`CompassRose`](crate::compass::CompassRose):
+    /// "NbE" or "SSW" letter by letter, "120°" by its digits.
+    pub fn direction(&self, name: &str) -> String {
+        let catalog = self.text();
+        if let Some(degrees) = name.strip_suffix('°') {
+            let digits: String = degrees.chars().map(|c| localize_digit(c, catalog.numbers.zero)).collect();
+            let digits = digits.replace('.', &catalog.numbers.decimal_separator.to_string());
+            return format!("{}°", digits);
+        }
+        let words: Option<Vec<&str>> = name
+            .chars()
+            .map(|letter| match letter {
+                'N' => Some(catalog.points[0]),
+                'E' => Some(catalog.points[1]),
+                'S' => Some(catalog.points[2]),
+                'W' => Some(catalog.points[3]),
+                'b' => Some(catalog.point_by),
+                _ => None,
+            })
+            .collect();
+        match words {
+            Some(words) => words.join(catalog.point_separator),
+            None => name.to_string(),
+        }
+    }
+
+    /// The message of `error`, with its numbers in the digits of the language. Field
+    /// names, reasons and service names are kept as they are.
+    pub fn error(&self, error: &MecczError) -> String {
+        let text = self.text();
+        match error {
+            MecczError::Parse { field, reason } => self.format(text.invalid_input, &[field, reason]),
+            MecczError::OutOfRange { field, value, min, max } => self.format(
+                text.out_of_range,
+                &[&capitalize(field), &self.shortest(*min), &self.shortest(*max), &self.shortest(*value)],
+            ),
+            MecczError::LocationNotFound(query) => self.format(text.location_not_found, &[query]),
+            MecczError::Unsupported { service, operation } => self.format(text.unsupported, &[operation, service]),
+            MecczError::Transport { service, reason, .. } => self.format(text.request_failed, &[service, reason]),
+            MecczError::RateLimited { service, retry_after: None } => self.format(text.rate_limited, &[service]),
+            MecczError::RateLimited { service, retry_after: Some(delay) } => {
+                self.format(text.rate_limited_retry, &[service, &self.shortest(delay.as_secs() as f64)])
+            }
+            MecczError::MalformedResponse { service, reason } => self.format(text.malformed_response, &[service, reason]),
+        }
+    }
+
+    /// `value` with as many decimals as it needs, as in error messages.
+    fn shortest(&self, value: f64) -> String {
+        let numbers = &self.text().numbers;
+        value
+            .to_string()
+            .chars()
+            .map(|c| if c == '.' { numbers.decimal_separator } else { localize_digit(c, numbers.zero) })
+            .collect()
+    }
+
+    /// Short destination name for headings, with "Mecca" translated.
+    pub fn target_label(&self, target: &Target) -> String {
+        if target.is_kaaba() { self.text().mecca.to_string() } else { target.name.clone() }
+    }
+
+    pub fn convention_name(&self, convention: QiblaConvention) -> &'static str {
+        self.convention_text(convention).0
+    }
+
+    pub fn convention_description(&self, convention: QiblaConvention) -> &'static str {
+        self.convention_text(convention).1
+    }
+
+    fn convention_text(&self, convention: QiblaConvention) -> (&'static str, &'static str) {
+        let index = QiblaConvention::ALL.iter().position(|c| *c == convention).unwrap_or_default();
+        self.text().conventions[index]
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn localize_digit(c: char, zero: char) -> char {
+    match c.to_digit(10) {
+        Some(digit) => char::from_u32(zero as u32 + digit).unwrap_or(c),
+        None => c,
+    }
+}
+
+const ENGLISH: Catalog = Catalog {
+    direction_to: "Direction to {}:",
+    bearing: "Bearing: {}° from North",
+    magnetic_bearing: "Magnetic bearing: {}° {} (declination {}° {}, {} valid {}-{})",
+    direction: "Direction: {}",
+    distance: "Distance: {} km",
+    kilometres: "km",
+    place: "Place: {}",
+    location: "Location: {}",
+    location_format: "Location: {} ({})",
+    rhumb_title: "Rhumb line (constant heading):",
+    rhumb_bearing: "Bearing: {}° from North ({}° from the great circle)",
+    rhumb_distance: "Distance: {} km ({} km, {}%)",
+    rhumb_explanation: "The great-circle bearing is only the initial heading of the shortest route: along\n\
+                        it the heading keeps changing. The rhumb line holds one compass heading all the way\n\
+                        (a straight line on a Mercator map) but is longer.",
+    qibla_direction: "Qibla Direction: {}°",
+    target_direction: "Direction to {}: {}°",
+    magnetic: "(magnetic)",
+    direct_distance: "Direct Distance to {}: {} km",
+    table_title: "Compass Direction Table - Distances to {} via Each Direction",
+    header_direction: "Direction",
+    header_bearing: "Bearing",
+    header_difference: "Diff",
+    header_closest: "Closest",
+    header_reached_at: "Reached at",
+    header_route: "Route",
+    header_long_path: "Long Path",
+    header_optimal: "Optimal",
+    left: "L",
+    right: "R",
+    legend_optimal_qibla: "* = Closest compass direction to actual Qibla bearing",
+    legend_optimal: "* = Closest compass direction to the actual bearing",
+    legend_closest: "Closest = Nearest the great circle of this heading passes, with {} to the Left or Right",
+    legend_reached_at: "Reached at = Distance travelled on this heading to that closest point",
+    legend_route: "Route = Reached at + Closest: follow the heading, then go straight to {}",
+    legend_long_path: "Long Path = Same, setting off in the opposite direction (around the world)",
+    conventions_towards: "Qibla conventions towards {}:",
+    header_convention: "Convention",
+    header_deviation: "Deviation",
+    header_distance: "Distance",
+    legend_deviation: "Deviation = convention bearing minus the great-circle bearing",
+    conventions: [
+        ("Great circle", "initial bearing of the shortest path"),
+        ("Rhumb line", "constant compass course"),
+        ("Cardinal qibla", "cardinal point closest to the great circle"),
+        ("Winter sunrise", "sunrise azimuth at the December solstice"),
+        ("Medieval approximation", "tan q = sin Δλ / sin Δφ"),
+    ],
+    mecca: "Mecca",
+    error: "Error: {}",
+    invalid_input: "Invalid {}: {}",
+    out_of_range: "{} must be between {} and {} degrees (got {})",
+    location_not_found: "Location not found: {}",
+    unsupported: "{} is not supported by {}",
+    request_failed: "{} request failed: {}",
+    rate_limited: "{} is rate limiting requests",
+    rate_limited_retry: "{} is rate limiting requests, retry after {} s",
+    malformed_response: "Malformed response from {}: {}",
+    points: ["N", "E", "S", "W"],
+    point_by: "b",
+    point_separator: "",
+    numbers: NumberFormat { decimal_separator: '.', group_separator: None, zero: '0', list_separator: ", " },
+};
+
+const ARABIC: Catalog = Catalog {
+    direction_to: "الاتجاه إلى {}:",
+    bearing: "السمت: {}° من الشمال",
+    magnetic_bearing: "السمت المغناطيسي: {}° {} (الانحراف {}° {}، {} صالح {}-{})",
+    direction: "الاتجاه: {}",
+    distance: "المسافة: {} كم",
+    kilometres: "كم",
+    place: "المكان: {}",
+    location: "الموقع: {}",
+    location_format: "الموقع: {} ({})",
+    rhumb_title: "خط الاتجاه الثابت (لوكسودروم):",
+    rhumb_bearing: "السمت: {}° من الشمال ({}° عن الدائرة العظمى)",
+    rhumb_distance: "المسافة: {} كم ({} كم، {}٪)",
+    rhumb_explanation: "سمت الدائرة العظمى ليس إلا الاتجاه الأولي لأقصر طريق، إذ يتغير الاتجاه على طوله.\n\
+                        أما خط الاتجاه الثابت فيحافظ على اتجاه البوصلة نفسه طوال الطريق\n\
+                        (خط مستقيم على خريطة مركاتور) لكنه أطول.",
+    qibla_direction: "اتجاه القبلة: {}°",
+    target_direction: "الاتجاه إلى {}: {}°",
+    magnetic: "(مغناطيسي)",
+    direct_distance: "المسافة المباشرة إلى {}: {} كم",
+    table_title: "جدول اتجاهات البوصلة - المسافات إلى {} عبر كل اتجاه",
+    header_direction: "الاتجاه",
+    header_bearing: "السمت",
+    header_difference: "الفرق",
+    header_closest: "أقرب نقطة",
+    header_reached_at: "عند",
+    header_route: "المسار",
+    header_long_path: "المسار الطويل",
+    header_optimal: "الأمثل",
+    left: "يسار",
+    right: "يمين",
+    legend_optimal_qibla: "* = أقرب اتجاه في البوصلة إلى سمت القبلة الفعلي",
+    legend_optimal: "* = أقرب اتجاه في البوصلة إلى السمت الفعلي",
+    legend_closest: "أقرب نقطة = أقل مسافة تمر بها الدائرة العظمى لهذا الاتجاه من {}، يسارًا أو يمينًا",
+    legend_reached_at: "عند = المسافة المقطوعة على هذا الاتجاه حتى تلك النقطة",
+    legend_route: "المسار = عند + أقرب نقطة: اتبع الاتجاه ثم توجه مباشرة إلى {}",
+    legend_long_path: "المسار الطويل = مثله مع الانطلاق في الاتجاه المعاكس (حول العالم)",
+    conventions_towards: "طرق تحديد القبلة نحو {}:",
+    header_convention: "الطريقة",
+    header_deviation: "الانحراف",
+    header_distance: "المسافة",
+    legend_deviation: "الانحراف = سمت الطريقة ناقص سمت الدائرة العظمى",
+    conventions: [
+        ("الدائرة العظمى", "السمت الأولي لأقصر طريق"),
+        ("خط الاتجاه الثابت", "اتجاه بوصلة ثابت"),
+        ("القبلة الجهوية", "الجهة الأصلية الأقرب إلى الدائرة العظمى"),
+        ("شروق الشتاء", "سمت شروق الشمس في انقلاب ديسمبر"),
+        ("تقريب العصور الوسطى", "tan q = sin Δλ / sin Δφ"),
+    ],
+    mecca: "مكة",
+    error: "خطأ: {}",
+    invalid_input: "{} غير صالح: {}",
+    out_of_range: "يجب أن يكون {} بين {} و{} درجة (القيمة {})",
+    location_not_found: "لم يُعثر على الموقع: {}",
+    unsupported: "{} غير مدعوم من {}",
+    request_failed: "فشل طلب {}: {}",
+    rate_limited: "{} يحدّ من معدل الطلبات",
+    rate_limited_retry: "{} يحدّ من معدل الطلبات، أعد المحاولة بعد {} ث",
+    malformed_response: "استجابة غير صالحة من {}: {}",
+    points: ["شمال", "شرق", "جنوب", "غرب"],
+    point_by: "ربع",
+    point_separator: " ",
+    numbers: NumberFormat { decimal_separator: '٫', group_separator: Some('٬'), zero: '٠', list_separator: "، " },
+};
+
+const URDU: Catalog = Catalog {
+    direction_to: "{} کی سمت:",
+    bearing: "زاویہ: شمال سے {}°",
+    magnetic_bearing: "مقناطیسی زاویہ: {}° {} (انحراف {}° {}، {} کارآمد {}-{})",
+    direction: "سمت: {}",
+    distance: "فاصلہ: {} کلومیٹر",
+    kilometres: "کلومیٹر",
+    place: "جگہ: {}",
+    location: "مقام: {}",
+    location_format: "مقام: {} ({})",
+    rhumb_title: "رمب لائن (مستقل سمت):",
+    rhumb_bearing: "زاویہ: شمال سے {}° (عظیم دائرے سے {}°)",
+    rhumb_distance: "فاصلہ: {} کلومیٹر ({} کلومیٹر، {}٪)",
+    rhumb_explanation: "عظیم دائرے کا زاویہ صرف مختصر ترین راستے کی ابتدائی سمت ہے، راستے میں سمت بدلتی رہتی ہے۔\n\
+                        رمب لائن پورے راستے ایک ہی قطب نما سمت پر رہتی ہے\n\
+                        (مرکیٹر نقشے پر سیدھی لکیر) مگر زیادہ لمبی ہے۔",
+    qibla_direction: "قبلہ کی سمت: {}°",
+    target_direction: "{} کی سمت: {}°",
+    magnetic: "(مقناطیسی)",
+    direct_distance: "{} تک براہ راست فاصلہ: {} کلومیٹر",
+    table_title: "قطب نما سمتوں کا جدول - ہر سمت سے {} تک فاصلے",
+    header_direction: "سمت",
+    header_bearing: "زاویہ",
+    header_difference: "فرق",
+    header_closest: "قریب ترین",
+    header_reached_at: "کہاں",
+    header_route: "راستہ",
+    header_long_path: "لمبا راستہ",
+    header_optimal: "بہترین",
+    left: "بائیں",
+    right: "دائیں",
+    legend_optimal_qibla: "* = قبلہ کے اصل زاویے کے قریب ترین قطب نما سمت",
+    legend_optimal: "* = اصل زاویے کے قریب ترین قطب نما سمت",
+    legend_closest: "قریب ترین = اس سمت کا عظیم دائرہ {} کے کتنا قریب سے گزرتا ہے، بائیں یا دائیں",
+    legend_reached_at: "کہاں = اس سمت میں اس قریب ترین نقطے تک طے کیا گیا فاصلہ",
+    legend_route: "راستہ = کہاں + قریب ترین: اس سمت پر چلیں، پھر سیدھا {} جائیں",
+    legend_long_path: "لمبا راستہ = یہی، مگر مخالف سمت میں روانہ ہو کر (دنیا کے گرد)",
+    conventions_towards: "{} کی طرف قبلہ کے طریقے:",
+    header_convention: "طریقہ",
+    header_deviation: "انحراف",
+    header_distance: "فاصلہ",
+    legend_deviation: "انحراف = طریقے کا زاویہ منفی عظیم دائرے کا زاویہ",
+    conventions: [
+        ("عظیم دائرہ", "مختصر ترین راستے کا ابتدائی زاویہ"),
+        ("رمب لائن", "مستقل قطب نما سمت"),
+        ("بنیادی سمت کا قبلہ", "عظیم دائرے کے قریب ترین بنیادی سمت"),
+        ("سردیوں کا طلوع آفتاب", "دسمبر کے انقلاب پر طلوع آفتاب کا زاویہ"),
+        ("قرون وسطیٰ کا تخمینہ", "tan q = sin Δλ / sin Δφ"),
+    ],
+    mecca: "مکہ",
+    error: "خرابی: {}",
+    invalid_input: "غلط {}: {}",
+    out_of_range: "{} کو {} اور {} درجے کے درمیان ہونا چاہیے ({} ملا)",
+    location_not_found: "مقام نہیں ملا: {}",
+    unsupported: "{} دستیاب نہیں ({})",
+    request_failed: "{} کی درخواست ناکام: {}",
+    rate_limited: "{} درخواستوں کی شرح محدود کر رہا ہے",
+    rate_limited_retry: "{} درخواستوں کی شرح محدود کر رہا ہے، {} سیکنڈ بعد دوبارہ کوشش کریں",
+    malformed_response: "{} سے غلط جواب: {}",
+    points: ["شمال", "مشرق", "جنوب", "مغرب"],
+    point_by: "ربع",
+    point_separator: " ",
+    numbers: NumberFormat { decimal_separator: '٫', group_separator: Some('٬'), zero: '۰', list_separator: "، " },
+};
+
+const TURKISH: Catalog = Catalog {
+    direction_to: "{} yönü:",
+    bearing: "Kerteriz: Kuzeyden {}°",
+    magnetic_bearing: "Manyetik kerteriz: {}° {} (sapma {}° {}, {} geçerlilik {}-{})",
+    direction: "Yön: {}",
+    distance: "Mesafe: {} km",
+    kilometres: "km",
+    place: "Yer: {}",
+    location: "Konum: {}",
+    location_format: "Konum: {} ({})",
+    rhumb_title: "Loksodrom (sabit rota):",
+    rhumb_bearing: "Kerteriz: Kuzeyden {}° (büyük daireye göre {}°)",
+    rhumb_distance: "Mesafe: {} km ({} km, %{})",
+    rhumb_explanation: "Büyük daire kerterizi en kısa yolun yalnızca başlangıç yönüdür: yol boyunca\n\
+                        yön sürekli değişir. Loksodrom bütün yol boyunca aynı pusula yönünü korur\n\
+                        (Mercator haritasında düz bir çizgi) ama daha uzundur.",
+    qibla_direction: "Kıble yönü: {}°",
+    target_direction: "{} yönü: {}°",
+    magnetic: "(manyetik)",
+    direct_distance: "{} ile doğrudan mesafe: {} km",
+    table_title: "Pusula Yön Tablosu - Her Yön Üzerinden {} Uzaklıkları",
+    header_direction: "Yön",
+    header_bearing: "Kerteriz",
+    header_difference: "Fark",
+    header_closest: "En yakın",
+    header_reached_at: "Ulaşılan",
+    header_route: "Rota",
+    header_long_path: "Uzun yol",
+    header_optimal: "En iyi",
+    left: "sol",
+    right: "sağ",
+    legend_optimal_qibla: "* = Gerçek kıble kerterizine en yakın pusula yönü",
+    legend_optimal: "* = Gerçek kerterize en yakın pusula yönü",
+    legend_closest: "En yakın = Bu yönün büyük dairesinin {} noktasına en çok yaklaştığı mesafe, solda ya da sağda",
+    legend_reached_at: "Ulaşılan = Bu yönde o en yakın noktaya kadar gidilen mesafe",
+    legend_route: "Rota = Ulaşılan + En yakın: yönü izleyip sonra doğrudan {} noktasına gitmek",
+    legend_long_path: "Uzun yol = Aynısı, ters yöne hareket ederek (dünyanın çevresinden)",
+    conventions_towards: "{} yönünde kıble yöntemleri:",
+    header_convention: "Yöntem",
+    header_deviation: "Sapma",
+    header_distance: "Mesafe",
+    legend_deviation: "Sapma = yöntemin kerterizi eksi büyük daire kerterizi",
+    conventions: [
+        ("Büyük daire", "en kısa yolun başlangıç kerterizi"),
+        ("Loksodrom", "sabit pusula rotası"),
+        ("Ana yön kıblesi", "büyük daireye en yakın ana yön"),
+        ("Kış gündoğumu", "Aralık gündönümünde güneşin doğuş azimutu"),
+        ("Orta Çağ yaklaşımı", "tan q = sin Δλ / sin Δφ"),
+    ],
+    mecca: "Mekke",
+    error: "Hata: {}",
+    invalid_input: "Geçersiz {}: {}",
+    out_of_range: "{} {} ile {} derece arasında olmalı ({} verildi)",
+    location_not_found: "Konum bulunamadı: {}",
+    unsupported: "{} desteklenmiyor ({})",
+    request_failed: "{} isteği başarısız: {}",
+    rate_limited: "{} istekleri sınırlıyor",
+    rate_limited_retry: "{} istekleri sınırlıyor, {} sn sonra yeniden deneyin",
+    malformed_response: "{} hatalı yanıt verdi: {}",
+    points: ["K", "D", "G", "B"],
+    point_by: "k",
+    point_separator: "",
+    numbers: NumberFormat { decimal_separator: ',', group_separator: Some('.'), zero: '0', list_separator: "; " },
+};
+
+const MALAY: Catalog = Catalog {
+    direction_to: "Arah ke {}:",
+    bearing: "Bering: {}° dari Utara",
+    magnetic_bearing: "Bering magnetik: {}° {} (serongan {}° {}, {} sah {}-{})",
+    direction: "Arah: {}",
+    distance: "Jarak: {} km",
+    kilometres: "km",
+    place: "Tempat: {}",
+    location: "Lokasi: {}",
+    location_format: "Lokasi: {} ({})",
+    rhumb_title: "Garis rumb (haluan tetap):",
+    rhumb_bearing: "Bering: {}° dari Utara ({}° dari bulatan besar)",
+    rhumb_distance: "Jarak: {} km ({} km, {}%)",
+    rhumb_explanation: "Bering bulatan besar hanyalah haluan awal laluan terpendek: sepanjang laluan\n\
+                        itu haluan sentiasa berubah. Garis rumb mengekalkan satu haluan kompas\n\
+                        (garis lurus pada peta Mercator) tetapi lebih panjang.",
+    qibla_direction: "Arah Kiblat: {}°",
+    target_direction: "Arah ke {}: {}°",
+    magnetic: "(magnetik)",
+    direct_distance: "Jarak terus ke {}: {} km",
+    table_title: "Jadual Arah Kompas - Jarak ke {} Melalui Setiap Arah",
+    header_direction: "Arah",
+    header_bearing: "Bering",
+    header_difference: "Beza",
+    header_closest: "Terdekat",
+    header_reached_at: "Dicapai pada",
+    header_route: "Laluan",
+    header_long_path: "Laluan jauh",
+    header_optimal: "Optimum",
+    left: "Ki",
+    right: "Ka",
+    legend_optimal_qibla: "* = Arah kompas paling hampir dengan bering Kiblat sebenar",
+    legend_optimal: "* = Arah kompas paling hampir dengan bering sebenar",
+    legend_closest: "Terdekat = Jarak paling dekat bulatan besar haluan ini dengan {}, di Kiri atau Kanan",
+    legend_reached_at: "Dicapai pada = Jarak yang dilalui pada haluan ini hingga titik terdekat itu",
+    legend_route: "Laluan = Dicapai pada + Terdekat: ikut haluan, kemudian terus ke {}",
+    legend_long_path: "Laluan jauh = Sama, tetapi bertolak ke arah bertentangan (mengelilingi dunia)",
+    conventions_towards: "Kaedah kiblat ke arah {}:",
+    header_convention: "Kaedah",
+    header_deviation: "Sisihan",
+    header_distance: "Jarak",
+    legend_deviation: "Sisihan = bering kaedah tolak bering bulatan besar",
+    conventions: [
+        ("Bulatan besar", "bering awal laluan terpendek"),
+        ("Garis rumb", "haluan kompas tetap"),
+        ("Kiblat mata angin", "mata angin paling hampir dengan bulatan besar"),
+        ("Matahari terbit musim sejuk", "azimut matahari terbit pada solstis Disember"),
+        ("Anggaran zaman pertengahan", "tan q = sin Δλ / sin Δφ"),
+    ],
+    mecca: "Makkah",
+    error: "Ralat: {}",
+    invalid_input: "{} tidak sah: {}",
+    out_of_range: "{} mesti antara {} dan {} darjah (diberi {})",
+    location_not_found: "Lokasi tidak ditemui: {}",
+    unsupported: "{} tidak disokong oleh {}",
+    request_failed: "Permintaan {} gagal: {}",
+    rate_limited: "{} mengehadkan kadar permintaan",
+    rate_limited_retry: "{} mengehadkan kadar permintaan, cuba lagi selepas {} s",
+    malformed_response: "Respons tidak sah daripada {}: {}",
+    points: ["U", "T", "S", "B"],
+    point_by: "b",
+    point_separator: "",
+    numbers: NumberFormat { decimal_separator: '.', group_separator: Some(','), zero: '0', list_separator: ", " },
+};
+
+const FRENCH: Catalog = Catalog {
+    direction_to: "Direction de {} :",
+    bearing: "Azimut : {}° depuis le Nord",
+    magnetic_bearing: "Azimut magnétique : {}° {} (déclinaison {}° {}, {} valide {}-{})",
+    direction: "Direction : {}",
+    distance: "Distance : {} km",
+    kilometres: "km",
+    place: "Lieu : {}",
+    location: "Position : {}",
+    location_format: "Position : {} ({})",
+    rhumb_title: "Loxodromie (cap constant) :",
+    rhumb_bearing: "Azimut : {}° depuis le Nord ({}° par rapport à l'orthodromie)",
+    rhumb_distance: "Distance : {} km ({} km, {} %)",
+    rhumb_explanation: "L'azimut orthodromique n'est que le cap initial de la route la plus courte : le\n\
+                        cap change ensuite tout au long du trajet. La loxodromie garde le même cap\n\
+                        (une droite sur une carte de Mercator) mais elle est plus longue.",
+    qibla_direction: "Direction de la Qibla : {}°",
+    target_direction: "Direction de {} : {}°",
+    magnetic: "(magnétique)",
+    direct_distance: "Distance directe jusqu'à {} : {} km",
+    table_title: "Table des directions - Distances jusqu'à {} selon chaque direction",
+    header_direction: "Direction",
+    header_bearing: "Azimut",
+    header_difference: "Écart",
+    header_closest: "Au plus près",
+    header_reached_at: "Atteint à",
+    header_route: "Trajet",
+    header_long_path: "Chemin long",
+    header_optimal: "Optimal",
+    left: "G",
+    right: "D",
+    legend_optimal_qibla: "* = Direction de la rose la plus proche de l'azimut de la Qibla",
+    legend_optimal: "* = Direction de la rose la plus proche de l'azimut exact",
+    legend_closest: "Au plus près = Distance minimale entre {} et le grand cercle de ce cap, à Gauche ou à Droite",
+    legend_reached_at: "Atteint à = Distance parcourue sur ce cap jusqu'à ce point",
+    legend_route: "Trajet = Atteint à + Au plus près : suivre le cap, puis aller droit vers {}",
+    legend_long_path: "Chemin long = Idem en partant dans la direction opposée (autour du monde)",
+    conventions_towards: "Conventions de qibla vers {} :",
+    header_convention: "Convention",
+    header_deviation: "Écart",
+    header_distance: "Distance",
+    legend_deviation: "Écart = azimut de la convention moins l'azimut orthodromique",
+    conventions: [
+        ("Orthodromie", "cap initial de la route la plus courte"),
+        ("Loxodromie", "cap constant"),
+        ("Qibla cardinale", "point cardinal le plus proche de l'orthodromie"),
+        ("Lever d'hiver", "azimut du lever du soleil au solstice de décembre"),
+        ("Approximation médiévale", "tan q = sin Δλ / sin Δφ"),
+    ],
+    mecca: "La Mecque",
+    error: "Erreur : {}",
+    invalid_input: "{} invalide : {}",
+    out_of_range: "{} : valeur attendue entre {} et {} degrés (reçu {})",
+    location_not_found: "Lieu introuvable : {}",
+    unsupported: "{} n'est pas pris en charge par {}",
+    request_failed: "La requête {} a échoué : {}",
+    rate_limited: "{} limite le débit des requêtes",
+    rate_limited_retry: "{} limite le débit des requêtes, réessayer dans {} s",
+    malformed_response: "Réponse invalide de {} : {}",
+    points: ["N", "E", "S", "O"],
+    point_by: "q",
+    point_separator: "",
+    numbers: NumberFormat { decimal_separator: ',', group_separator: Some('\u{202F}'), zero: '0', list_separator: "; " },
+};
+
+const INDONESIAN: Catalog = Catalog {
+    direction_to: "Arah ke {}:",
+    bearing: "Azimut: {}° dari Utara",
+    magnetic_bearing: "Azimut magnetik: {}° {} (deklinasi {}° {}, {} berlaku {}-{})",
+    direction: "Arah: {}",
+    distance: "Jarak: {} km",
+    kilometres: "km",
+    place: "Tempat: {}",
+    location: "Lokasi: {}",
+    location_format: "Lokasi: {} ({})",
+    rhumb_title: "Garis loksodrom (haluan tetap):",
+    rhumb_bearing: "Azimut: {}° dari Utara ({}° dari lingkaran besar)",
+    rhumb_distance: "Jarak: {} km ({} km, {}%)",
+    rhumb_explanation: "Azimut lingkaran besar hanyalah arah awal rute terpendek: sepanjang rute\n\
+                        arahnya terus berubah. Garis loksodrom mempertahankan satu arah kompas\n\
+                        (garis lurus pada peta Mercator) tetapi lebih panjang.",
+    qibla_direction: "Arah Kiblat: {}°",
+    target_direction: "Arah ke {}: {}°",
+    magnetic: "(magnetik)",
+    direct_distance: "Jarak langsung ke {}: {} km",
+    table_title: "Tabel Arah Kompas - Jarak ke {} Melalui Setiap Arah",
+    header_direction: "Arah",
+    header_bearing: "Azimut",
+    header_difference: "Selisih",
+    header_closest: "Terdekat",
+    header_reached_at: "Dicapai di",
+    header_route: "Rute",
+    header_long_path: "Jalur jauh",
+    header_optimal: "Optimal",
+    left: "Ki",
+    right: "Ka",
+    legend_optimal_qibla: "* = Arah kompas terdekat dengan azimut Kiblat sebenarnya",
+    legend_optimal: "* = Arah kompas terdekat dengan azimut sebenarnya",
+    legend_closest: "Terdekat = Jarak terdekat lingkaran besar arah ini ke {}, di Kiri atau Kanan",
+    legend_reached_at: "Dicapai di = Jarak yang ditempuh pada arah ini sampai titik terdekat itu",
+    legend_route: "Rute = Dicapai di + Terdekat: ikuti arah ini, lalu langsung menuju {}",
+    legend_long_path: "Jalur jauh = Sama, tetapi berangkat ke arah berlawanan (mengelilingi bumi)",
+    conventions_towards: "Metode kiblat menuju {}:",
+    header_convention: "Metode",
+    header_deviation: "Simpangan",
+    header_distance: "Jarak",
+    legend_deviation: "Simpangan = azimut metode dikurangi azimut lingkaran besar",
+    conventions: [
+        ("Lingkaran besar", "azimut awal rute terpendek"),
+        ("Loksodrom", "arah kompas tetap"),
+        ("Kiblat mata angin", "mata angin terdekat dengan lingkaran besar"),
+        ("Matahari terbit musim dingin", "azimut matahari terbit pada titik balik Desember"),
+        ("Pendekatan abad pertengahan", "tan q = sin Δλ / sin Δφ"),
+    ],
+    mecca: "Mekah",
+    error: "Galat: {}",
+    invalid_input: "{} tidak valid: {}",
+    out_of_range: "{} harus antara {} dan {} derajat (diberikan {})",
+    location_not_found: "Lokasi tidak ditemukan: {}",
+    unsupported: "{} tidak didukung oleh {}",
+    request_failed: "Permintaan {} gagal: {}",
+    rate_limited: "{} membatasi laju permintaan",
+    rate_limited_retry: "{} membatasi laju permintaan, coba lagi setelah {} dtk",
+    malformed_response: "Respons tidak valid dari {}: {}",
+    points: ["U", "T", "S", "B"],
+    point_by: "b",
+    point_separator: "",
+    numbers: NumberFormat { decimal_separator: ',', group_separator: Some('.'), zero: '0', list_separator: "; " },
+};
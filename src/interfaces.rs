@@ -1,5 +1,6 @@
 use async_trait::async_trait;
-use anyhow::Result;
+use crate::error::{MecczError, Result};
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -8,11 +9,80 @@ pub struct Location {
     pub longitude: f64,
 }
 
+/// A human readable description of where a location is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Place {
+    pub display_name: String,
+    pub city: Option<String>,
+    pub country: Option<String>,
+    pub country_code: Option<String>, // ISO 3166-1 alpha-2, upper case
+}
+
+/// A destination that bearings are computed to, the Kaaba unless configured otherwise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Target {
+    pub id: String, // registry identifier, e.g. "kaaba", or "custom" for coordinates given directly
+    pub name: String,
+    pub location: Location,
+}
+
+/// Extent of a geocoded place, in degrees.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub south: f64,
+    pub north: f64,
+    pub west: f64,
+    pub east: f64,
+}
+
+/// One of the places a geocoding query may refer to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeocodingCandidate {
+    pub location: Location,
+    pub display_name: String,
+    #[serde(rename = "type")]
+    pub kind: String, // kind of place, e.g. "city", "administrative", "PPLC"
+    pub importance: Option<f64>, // 0 to 1, higher for more prominent places
+    pub bounding_box: Option<BoundingBox>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct QiblaDirection {
     pub bearing: f64, // degrees from North (0-360)
-    pub direction: String, // Cardinal direction (N, NE, E, SE, S, SW, W, NW)
-    pub distance_km: f64, // distance to Mecca in kilometers
+    pub direction: String, // compass point on the app's rose, 16 points (N, NNE, NE, ENE, ...) by default
+    pub distance_km: f64, // distance to the target (Mecca by default) in kilometers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub magnetic: Option<MagneticBearing>, // compass correction, when a magnetic model was applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rhumb_line: Option<RhumbLine>, // constant-heading alternative, when requested
+}
+
+/// The constant-heading (loxodrome) route to the target, which crosses every meridian
+/// at the same angle but is longer than the great circle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RhumbLine {
+    pub bearing: f64, // constant heading, degrees from North (0-360)
+    pub direction: String,
+    pub distance_km: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MagneticBearing {
+    pub bearing: f64, // degrees from magnetic North (0-360)
+    pub direction: String, // compass point of the magnetic bearing, e.g. "SE"
+    pub declination: f64, // degrees, positive when magnetic North is east of true North
+    pub date: NaiveDate, // date the declination was evaluated for
+    pub model: String, // magnetic model name, e.g. WMM-2025
+    pub valid_from: f64, // decimal year the model is valid from
+    pub valid_until: f64, // decimal year the model expires
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NorthReference {
+    #[default]
+    True,
+    Magnetic,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -20,9 +90,15 @@ pub struct CompassEntry {
     pub direction: String,
     pub bearing: f64,
     pub angular_difference: f64, // degrees difference from Qibla direction
-    pub short_path_distance_km: f64, // distance via shorter great circle
-    pub long_path_distance_km: f64, // distance via longer great circle
+    pub short_path_distance_km: f64, // along this heading to the closest approach, then straight to the target
+    pub long_path_distance_km: f64, // the same via the opposite heading, the other way round this great circle
     pub is_optimal_direction: bool, // true if this is the closest to Qibla direction
+    #[serde(default)]
+    pub closest_approach_km: f64, // smallest distance to the target along this heading's great circle
+    #[serde(default)]
+    pub along_track_km: f64, // distance travelled on this heading until the closest approach
+    #[serde(default)]
+    pub cross_track_km: f64, // signed miss distance, positive when the target lies to the right
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -30,19 +106,105 @@ pub struct CompassTable {
     pub location: Location,
     pub qibla_bearing: f64,
     pub direct_distance_km: f64,
+    #[serde(default)]
+    pub north_reference: NorthReference, // whether bearings are relative to true or magnetic North
     pub entries: Vec<CompassEntry>,
 }
 
 #[async_trait]
-pub trait GeocodingService {
+pub trait GeocodingService: Send + Sync {
     async fn geocode(&self, address: &str) -> Result<Location>;
+
+    /// Up to `limit` places matching `address`, best first. Services that only know
+    /// one answer return it as the single candidate.
+    async fn geocode_candidates(&self, address: &str, limit: usize) -> Result<Vec<GeocodingCandidate>> {
+        let _ = limit;
+        let location = self.geocode(address).await?;
+        Ok(vec![GeocodingCandidate {
+            location,
+            display_name: address.to_string(),
+            kind: "place".to_string(),
+            importance: None,
+            bounding_box: None,
+        }])
+    }
+
+    /// Describes the place at `location`. Services without reverse lookup return an error.
+    async fn reverse_geocode(&self, location: &Location) -> Result<Place> {
+        let _ = location;
+        Err(MecczError::Unsupported {
+            service: self.source().to_string(),
+            operation: "Reverse geocoding".to_string(),
+        })
+    }
+
+    /// Short name of the service, recorded alongside cached results.
+    fn source(&self) -> &str {
+        "geocoder"
+    }
+
+    /// Writes what the service keeps between runs, such as a cache, to disk. This may
+    /// block, so async code should call it with `spawn_blocking`.
+    fn persist(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T: GeocodingService + ?Sized> GeocodingService for Box<T> {
+    async fn geocode(&self, address: &str) -> Result<Location> {
+        (**self).geocode(address).await
+    }
+
+    async fn geocode_candidates(&self, address: &str, limit: usize) -> Result<Vec<GeocodingCandidate>> {
+        (**self).geocode_candidates(address, limit).await
+    }
+
+    async fn reverse_geocode(&self, location: &Location) -> Result<Place> {
+        (**self).reverse_geocode(location).await
+    }
+
+    fn source(&self) -> &str {
+        (**self).source()
+    }
+
+    fn persist(&self) -> anyhow::Result<()> {
+        (**self).persist()
+    }
 }
 
 pub trait QiblaCalculator {
-    fn calculate_qibla(&self, location: &Location) -> QiblaDirection;
+    /// Bearing and distance from `location` to any `destination`.
+    fn calculate_direction(&self, location: &Location, destination: &Location) -> QiblaDirection;
+
+    /// The destination of [`QiblaCalculator::calculate_qibla`].
+    fn target(&self) -> &Target;
+
+    fn calculate_qibla(&self, location: &Location) -> QiblaDirection {
+        self.calculate_direction(location, &self.target().location)
+    }
+
     fn calculate_compass_table(&self, location: &Location) -> CompassTable;
 }
 
+impl<T: QiblaCalculator + ?Sized> QiblaCalculator for Box<T> {
+    fn calculate_direction(&self, location: &Location, destination: &Location) -> QiblaDirection {
+        (**self).calculate_direction(location, destination)
+    }
+
+    fn target(&self) -> &Target {
+        (**self).target()
+    }
+
+    fn calculate_qibla(&self, location: &Location) -> QiblaDirection {
+        (**self).calculate_qibla(location)
+    }
+
+    fn calculate_compass_table(&self, location: &Location) -> CompassTable {
+        (**self).calculate_compass_table(location)
+    }
+}
+
 #[async_trait]
 pub trait Application {
     async fn run(&self, input: &str) -> Result<QiblaDirection>;
@@ -0,0 +1,128 @@
+use crate::interfaces::{Location, QiblaCalculator};
+use crate::qibla::{KAABA_LATITUDE, KAABA_LONGITUDE};
+use crate::solar::{bearing_difference, declination_and_equation_of_time, julian_century, solar_position, SUNRISE_ALTITUDE};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Half-width of the window searched around the Kaaba's solar transit, in seconds.
+const TRANSIT_SEARCH_WINDOW_SECONDS: i64 = 1800;
+
+/// A solar transit at which the sun passes (almost) exactly overhead the Kaaba.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KaabaZenithTransit {
+    pub instant: DateTime<Utc>,
+    pub zenith_distance: f64, // degrees between the sun and the zenith of the Kaaba at that instant
+    pub declination: f64, // solar declination at that instant, degrees
+}
+
+/// What an observer at `location` sees at the moment of a Kaaba zenith transit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZenithTransitObservation {
+    pub transit: KaabaZenithTransit,
+    pub location: Location,
+    pub sun_azimuth: f64, // degrees from North; facing the sun means facing the Qibla
+    pub sun_altitude: f64,
+    pub sun_visible: bool,
+    pub shadow_direction: f64, // degrees from North; shadows point directly away from the Kaaba
+    pub qibla_bearing: f64,
+    pub deviation: f64, // sun azimuth minus Qibla bearing, degrees
+}
+
+fn kaaba() -> Location {
+    Location {
+        latitude: KAABA_LATITUDE,
+        longitude: KAABA_LONGITUDE,
+    }
+}
+
+/// UTC instant of the sun's upper transit over the Kaaba meridian on `date`.
+fn kaaba_transit(date: NaiveDate) -> DateTime<Utc> {
+    let midnight = Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).expect("midnight is valid"));
+    let mut minutes = 720.0 - 4.0 * KAABA_LONGITUDE;
+    // The equation of time changes slowly, two refinements are plenty
+    for _ in 0..2 {
+        let instant = midnight + Duration::milliseconds((minutes * 60_000.0) as i64);
+        let (_, equation_of_time) = declination_and_equation_of_time(julian_century(instant));
+        minutes = 720.0 - 4.0 * KAABA_LONGITUDE - equation_of_time;
+    }
+    midnight + Duration::milliseconds((minutes * 60_000.0) as i64)
+}
+
+fn zenith_distance(instant: DateTime<Utc>) -> f64 {
+    90.0 - solar_position(&kaaba(), instant).altitude
+}
+
+/// Refines a transit to the instant the sun is closest to the Kaaba's zenith.
+fn closest_approach(transit: DateTime<Utc>) -> KaabaZenithTransit {
+    let mut low = transit - Duration::seconds(TRANSIT_SEARCH_WINDOW_SECONDS);
+    let mut high = transit + Duration::seconds(TRANSIT_SEARCH_WINDOW_SECONDS);
+
+    // Golden-section search on the (unimodal) zenith distance
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    while high - low > Duration::seconds(1) {
+        let span = (high - low).num_milliseconds() as f64;
+        let left = high - Duration::milliseconds((span * ratio) as i64);
+        let right = low + Duration::milliseconds((span * ratio) as i64);
+        if zenith_distance(left) < zenith_distance(right) {
+            high = right;
+        } else {
+            low = left;
+        }
+    }
+
+    let middle = low + (high - low) / 2;
+    let instant = DateTime::from_timestamp((middle.timestamp_millis() as f64 / 1000.0).round() as i64, 0)
+        .unwrap_or(middle);
+    KaabaZenithTransit {
+        instant,
+        zenith_distance: zenith_distance(instant),
+        declination: solar_position(&kaaba(), instant).declination,
+    }
+}
+
+/// Finds the solar transits in `year` at which the sun stands overhead the Kaaba
+/// (Istiwa al-A'zam), typically around 27-28 May and 15-16 July.
+pub fn kaaba_zenith_transits(year: i32) -> Vec<KaabaZenithTransit> {
+    let Some(first_day) = NaiveDate::from_ymd_opt(year, 1, 1) else {
+        return Vec::new();
+    };
+
+    let mut transits = Vec::new();
+    let mut previous: Option<(DateTime<Utc>, f64)> = None;
+    for date in first_day.iter_days().take_while(|date| date.year() == year) {
+        let transit = kaaba_transit(date);
+        let offset = solar_position(&kaaba(), transit).declination - KAABA_LATITUDE;
+
+        if let Some((previous_transit, previous_offset)) = previous
+            && previous_offset.signum() != offset.signum()
+        {
+            // The declination crossed the Kaaba's latitude between these two transits
+            let candidate = if previous_offset.abs() < offset.abs() { previous_transit } else { transit };
+            transits.push(closest_approach(candidate));
+        }
+        previous = Some((transit, offset));
+    }
+    transits
+}
+
+/// Describes the sun as seen from `location` at the moment of `transit`.
+pub fn observe_zenith_transit<Q: QiblaCalculator + ?Sized>(
+    calculator: &Q,
+    transit: &KaabaZenithTransit,
+    location: &Location,
+) -> ZenithTransitObservation {
+    let position = solar_position(location, transit.instant);
+    // The transit is about the Kaaba whatever target the calculator is set to
+    let qibla = calculator.calculate_direction(location, &kaaba());
+
+    ZenithTransitObservation {
+        transit: transit.clone(),
+        location: location.clone(),
+        sun_azimuth: position.azimuth,
+        sun_altitude: position.altitude,
+        sun_visible: position.altitude > SUNRISE_ALTITUDE,
+        shadow_direction: (position.azimuth + 180.0).rem_euclid(360.0),
+        qibla_bearing: qibla.bearing,
+        deviation: bearing_difference(position.azimuth, qibla.bearing),
+    }
+}
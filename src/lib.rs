@@ -1,9 +1,31 @@
+pub mod batch;
+pub mod cache;
+pub mod compass;
+pub mod conventions;
+pub mod coordinates;
 pub mod core;
+pub mod error;
+pub mod gazetteer;
+pub mod i18n;
 pub mod geocoding;
+pub mod geodesic;
+pub mod geomagnetic;
+pub mod prayer;
+pub mod providers;
 pub mod qibla;
+pub mod render;
+pub mod route;
+pub mod server;
+pub mod solar;
+pub mod targets;
+pub mod terminal;
+pub mod throttle;
+pub mod timezone;
 pub mod interfaces;
+pub mod istiwa;
 
 pub use core::*;
+pub use error::MecczError;
 pub use interfaces::*;
 
 #[cfg(test)]
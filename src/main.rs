@@ -1,100 +1,1356 @@
-use clap::Parser;
+use chrono::{Datelike, NaiveDate};
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
 use meccz::{
+    batch::{BatchColumns, BatchFormat, BatchInput, BatchSummary, BatchWriter, DEFAULT_CONCURRENCY},
+    cache::{default_cache_path, write_csv, CachedGeocoder, GeocodingCache, PurgeFilter},
+    compass::CompassRose,
+    conventions::ConventionComparison,
+    coordinates::CoordinateFormats,
     core::MeccaApp,
-    geocoding::NominatimGeocoder,
+    gazetteer::OfflineGeocoder,
+    geocoding::{candidates_are_ambiguous, HttpClient, NominatimGeocoder, DEFAULT_USER_AGENT, NOMINATIM_URL},
+    geodesic::EllipsoidalCalculator,
+    geomagnetic::MagneticModel,
+    i18n::{Language, Localizer},
+    istiwa::{kaaba_zenith_transits, KaabaZenithTransit, ZenithTransitObservation},
+    providers::{PeliasGeocoder, PhotonGeocoder, TemplateConfig, TemplateGeocoder, PHOTON_URL},
+    prayer::{monthly_prayer_times, prayer_times, AsrMethod, CalculationMethod, HighLatitudeRule, PrayerOffset, PrayerSettings, PrayerTimes},
     qibla::GreatCircleCalculator,
+    render::{QiblaCard, DEFAULT_CARD_SIZE},
+    route::{Density, PathFormat},
+    server::{self, CorsOrigins},
+    solar::{bearing_difference, SunAlignment, SunQiblaTimes},
+    targets::Targets,
+    terminal::{display_width, Align, TerminalCompass, TextTable},
+    throttle::{RateLimitedGeocoder, RateLimiter, RetryPolicy},
+    timezone::TimeZoneSpec,
+    GeocodingCandidate, GeocodingService, Location, MecczError, NorthReference, Place, QiblaCalculator, QiblaDirection,
+    RhumbLine, Target,
 };
-use serde_json;
+use anyhow::Context;
+use futures::StreamExt;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Number of places requested when the user may have to choose between them.
+const CANDIDATE_LIMIT: usize = 10;
+
+type App = MeccaApp<Box<dyn GeocodingService + Send + Sync>, Box<dyn QiblaCalculator + Send + Sync>>;
 
 #[derive(Parser)]
 #[command(name = "meccz")]
 #[command(about = "Calculate the direction to Mecca (Qibla) from any location")]
 #[command(version = "1.0")]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
-    #[arg(help = "Location as coordinates (lat,lon) or address to geocode")]
-    location: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(required = true, allow_hyphen_values = true, help = "Location as coordinates (lat,lon, DMS, UTM, MGRS, geohash, Plus Code) or address to geocode")]
+    location: Option<String>,
     
-    #[arg(long, short, help = "Output result in JSON format")]
+    #[arg(long, short, global = true, help = "Output result in JSON format")]
     json: bool,
     
     #[arg(long, short, help = "Display compass table showing distance to Mecca from each direction")]
     table: bool,
+
+    #[arg(long, short, global = true, value_enum, default_value_t = EarthModel::Sphere, help = "Earth model used for bearing and distance")]
+    model: EarthModel,
+
+    #[arg(long, global = true, value_enum, default_value_t = GeocoderKind::Nominatim, help = "Service used to look up addresses")]
+    geocoder: GeocoderKind,
+
+    #[arg(long, global = true, value_name = "PATH", help = "GeoNames dump (e.g. cities500.txt) used by --geocoder offline")]
+    gazetteer: Option<PathBuf>,
+
+    #[arg(long, global = true, value_name = "URL", help = "Server of --geocoder nominatim, photon or pelias [default: the public instance]")]
+    geocoder_url: Option<String>,
+
+    #[arg(long, global = true, value_name = "KEY", help = "API key of --geocoder pelias or template")]
+    api_key: Option<String>,
+
+    #[arg(long, global = true, value_name = "PATH", help = "JSON description of the service used by --geocoder template")]
+    geocoder_config: Option<PathBuf>,
+
+    #[arg(long, global = true, value_name = "TEXT", default_value = DEFAULT_USER_AGENT, help = "User-Agent sent to the geocoding service, naming your application")]
+    user_agent: String,
+
+    #[arg(long, global = true, value_name = "ADDRESS", help = "Contact address sent to nominatim, as its usage policy asks of heavy users")]
+    email: Option<String>,
+
+    #[arg(long, global = true, value_name = "SECONDS", default_value = "10", value_parser = parse_timeout, help = "Time to wait for each response of the geocoding service")]
+    timeout: Duration,
+
+    #[arg(long, global = true, value_name = "N", default_value_t = RetryPolicy::default().max_retries, help = "Retries of a geocoding request that failed with 429, 5xx or a timeout")]
+    retries: u32,
+
+    #[arg(long, global = true, value_name = "N", value_parser = clap::value_parser!(u16).range(1..), help = "Use the N-th place matching an ambiguous address, without asking")]
+    pick: Option<u16>,
+
+    #[arg(long, global = true, help = "Do not read or write the geocoding cache")]
+    no_cache: bool,
+
+    #[arg(long, global = true, value_name = "PATH", help = "Geocoding cache file [default: ~/.cache/meccz/geocoding.json]")]
+    cache_file: Option<PathBuf>,
+
+    #[arg(long, global = true, value_name = "TARGET", value_parser = parse_target, help = "Destination of the bearing: kaaba (default), medina, aqsa, jerusalem or coordinates")]
+    target: Option<Target>,
+
+    #[arg(long, global = true, help = "Date (YYYY-MM-DD) used for date-dependent results [default: today]")]
+    date: Option<NaiveDate>,
+
+    #[arg(long, default_value_t = 0.0, help = "Altitude above the WGS-84 ellipsoid in meters, for the magnetic declination")]
+    altitude: f64,
+
+    #[arg(long, global = true, value_name = "LANG", default_value = "en", help = "Language of the text output: en, ar, ur, tr, ms, fr or id (JSON is unchanged)")]
+    lang: Language,
+
+    #[arg(long, global = true, value_name = "ROSE", help = "Compass rose for direction names and the table: 4, 8, 16, 32 points, another count or a step like 10deg")]
+    points: Option<CompassRose>,
+
+    #[arg(long, help = "Express compass table bearings relative to magnetic North")]
+    magnetic: bool,
+
+    #[arg(long, conflicts_with = "table", help = "Also report the constant-heading (rhumb line) bearing and distance")]
+    rhumb: bool,
+
+    #[arg(long, help = "List every place matching the address with its own Qibla direction")]
+    candidates: bool,
+
+    #[arg(long, conflicts_with_all = ["json", "candidates", "export"], help = "Draw the Qibla direction on a compass dial sized to the terminal")]
+    visual: bool,
+
+    #[arg(long, value_name = "FORMAT", conflicts_with_all = ["table", "rhumb", "candidates"], help = "Write the great-circle path to the destination as geojson, kml or gpx")]
+    export: Option<PathFormat>,
+
+    #[arg(long, value_name = "N", requires = "export", value_parser = clap::value_parser!(u32).range(2..=100_000), help = "Number of points along the exported path, both ends included")]
+    waypoints: Option<u32>,
+
+    #[arg(long, value_name = "KM", requires = "export", conflicts_with = "waypoints", value_parser = parse_spacing, help = "Largest distance between points of the exported path [default: 100]")]
+    spacing: Option<f64>,
+
+    #[arg(long, global = true, help = "Look up the place name of coordinates given as input, to check them")]
+    reverse: bool,
+
+    #[arg(long, value_name = "FORMAT", value_parser = parse_coordinate_format, help = "Echo the resolved location as decimal, dms, utm, mgrs, geohash or olc")]
+    output_coords: Option<String>,
+}
+
+fn parse_coordinate_format(name: &str) -> Result<String, String> {
+    let formats = CoordinateFormats::default();
+    match formats.get(name) {
+        Some(format) => Ok(format.name().to_string()),
+        None => Err(format!("expected one of {}", formats.names().join(", "))),
+    }
+}
+
+fn parse_spacing(input: &str) -> Result<f64, String> {
+    match input.trim().parse::<f64>() {
+        Ok(spacing) if spacing >= 1.0 => Ok(spacing),
+        Ok(_) => Err("the spacing must be at least 1 km".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_rate(input: &str) -> Result<f64, String> {
+    match input.trim().parse::<f64>() {
+        Ok(rate) if rate >= 0.0 && rate.is_finite() => Ok(rate),
+        Ok(_) => Err("the rate must be a positive number of requests per second".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_timeout(input: &str) -> Result<Duration, String> {
+    match input.trim().parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 && seconds.is_finite() => Ok(Duration::from_secs_f64(seconds)),
+        Ok(_) => Err("the timeout must be a positive number of seconds".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_target(input: &str) -> Result<Target, String> {
+    Targets::default()
+        .resolve(input, &CoordinateFormats::default())
+        .map_err(|e| e.to_string())
+}
+
+/// The resolved location written in the notation chosen with `--output-coords`.
+#[derive(Serialize)]
+struct FormattedCoordinates {
+    format: String,
+    value: String,
+}
+
+/// A result extended with the place name and `--output-coords` rendering of its location.
+#[derive(Serialize)]
+struct LocatedOutput<'a, T: Serialize> {
+    #[serde(flatten)]
+    result: &'a T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    place: Option<&'a Place>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    coordinates: Option<&'a FormattedCoordinates>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<&'a Target>, // only when not the Kaaba, to keep the default output unchanged
+}
+
+/// A geocoding candidate with the Qibla direction from it, for `--candidates`.
+#[derive(Serialize)]
+struct CandidateOutput<'a> {
+    #[serde(flatten)]
+    candidate: &'a GeocodingCandidate,
+    qibla: QiblaDirection,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Times of day when the sun stands in the Qibla direction or directly opposite it
+    Sun {
+        #[arg(allow_hyphen_values = true, help = "Location as coordinates (lat,lon, DMS, UTM, MGRS, geohash, Plus Code) or address to geocode")]
+        location: String,
+
+        #[arg(long, default_value = "local", help = "Time zone for the reported times (local, UTC, +03:00, Asia/Riyadh)")]
+        tz: TimeZoneSpec,
+    },
+    /// Instants when the sun passes directly over the Kaaba (Istiwa al-A'zam)
+    Zenith {
+        #[arg(allow_hyphen_values = true, help = "Optional location to describe the sun from at those instants")]
+        location: Option<String>,
+
+        #[arg(long, help = "Year to search [default: year of --date]")]
+        year: Option<i32>,
+
+        #[arg(long, default_value = "local", help = "Time zone for the reported local times (local, UTC, +03:00, Asia/Riyadh)")]
+        tz: TimeZoneSpec,
+    },
+    /// Daily prayer times for a location
+    Times {
+        #[arg(allow_hyphen_values = true, help = "Location as coordinates (lat,lon, DMS, UTM, MGRS, geohash, Plus Code) or address to geocode")]
+        location: String,
+
+        #[arg(long, default_value = "mwl", help = "Calculation method: mwl, isna, egypt, umm-al-qura, karachi, tehran, jafari, gulf, kuwait, qatar, singapore, france, turkey")]
+        method: CalculationMethod,
+
+        #[arg(long, default_value = "shafi", help = "Asr juristic method: shafi or hanafi")]
+        asr: AsrMethod,
+
+        #[arg(long, default_value = "middle-of-night", help = "High latitude rule: none, middle-of-night, one-seventh or angle-based")]
+        high_latitude: HighLatitudeRule,
+
+        #[arg(long, value_delimiter = ',', help = "Per-prayer minute offsets, e.g. fajr=2,isha=-3")]
+        offsets: Vec<PrayerOffset>,
+
+        #[arg(long, help = "Show the whole month of --date instead of a single day")]
+        month: bool,
+
+        #[arg(long, default_value = "local", help = "Time zone for the reported times (local, UTC, +03:00, Asia/Riyadh)")]
+        tz: TimeZoneSpec,
+
+        #[arg(long, value_enum, default_value_t = CalendarFormat::Text, help = "Output format")]
+        format: CalendarFormat,
+    },
+    /// Compare the bearing of historical and alternative qibla conventions
+    Compare {
+        #[arg(allow_hyphen_values = true, help = "Location as coordinates (lat,lon, DMS, UTM, MGRS, geohash, Plus Code) or address to geocode")]
+        location: String,
+    },
+    /// Draw the Qibla direction on a compass rose, as an SVG or PNG card
+    Render {
+        #[arg(allow_hyphen_values = true, help = "Location as coordinates (lat,lon, DMS, UTM, MGRS, geohash, Plus Code) or address to geocode")]
+        location: String,
+
+        #[arg(long, short, value_name = "PATH", help = "Output file; a .png extension needs the png feature, anything else is SVG")]
+        out: PathBuf,
+
+        #[arg(long, value_name = "PX", default_value_t = DEFAULT_CARD_SIZE, value_parser = clap::value_parser!(u32).range(100..=8000), help = "Width of the card in pixels")]
+        size: u32,
+
+        #[arg(long, short, help = "Also draw the directions of the compass table")]
+        table: bool,
+
+        #[arg(long, help = "Show magnetic North and the magnetic bearing")]
+        magnetic: bool,
+    },
+    /// Qibla directions for many locations read from a CSV or JSON Lines file
+    Batch {
+        #[arg(value_name = "FILE", help = "CSV or JSON Lines file of locations, or - for standard input [default: -]")]
+        input: Option<PathBuf>,
+
+        #[arg(long, value_name = "FORMAT", help = "Input format: csv or jsonl [default: from the extension or the content]")]
+        format: Option<BatchFormat>,
+
+        #[arg(long, short, value_name = "PATH", help = "Output file [default: standard output]")]
+        out: Option<PathBuf>,
+
+        #[arg(long, value_name = "FORMAT", help = "Output format: csv or jsonl [default: from --out, jsonl with --json, else csv]")]
+        output_format: Option<BatchFormat>,
+
+        #[arg(long = "id-column", value_name = "NAME", help = "Column identifying each row, copied to the output; repeat for several [default: id]")]
+        id_columns: Vec<String>,
+
+        #[arg(long, value_name = "NAME", help = "Column holding the location [default: location, address, query, place or latitude and longitude]")]
+        location_column: Option<String>,
+
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_CONCURRENCY as u16, value_parser = clap::value_parser!(u16).range(1..=256), help = "Locations resolved at the same time")]
+        concurrency: u16,
+
+        #[arg(long, value_name = "PER_SECOND", value_parser = parse_rate, help = "Geocoding requests per second, 0 for no limit [default: 1 with the public nominatim]")]
+        rate_limit: Option<f64>,
+    },
+    /// Serve the Qibla direction, compass table and geocoding over HTTP
+    Serve {
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1:8080", help = "Address and port to listen on")]
+        bind: std::net::SocketAddr,
+
+        #[arg(long = "cors-origin", value_name = "ORIGIN", help = "Browser origin allowed to call the API; repeat for several [default: any]")]
+        cors_origins: Vec<String>,
+    },
+    /// List the named destinations accepted by --target
+    Targets,
+    /// Inspect or clear the geocoding cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// List cached queries, most recent first
+    List,
+    /// Write all cached entries to standard output
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json, help = "Output format")]
+        format: ExportFormat,
+    },
+    /// Remove cached entries (all of them unless a filter is given)
+    Purge {
+        #[arg(long, conflicts_with = "negative", help = "Only remove expired entries")]
+        expired: bool,
+
+        #[arg(long, help = "Only remove cached \"Location not found\" answers")]
+        negative: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum CalendarFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EarthModel {
+    /// Spherical Earth (great circle, haversine)
+    Sphere,
+    /// WGS-84 ellipsoid (geodesic, Karney's algorithm)
+    Wgs84,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GeocoderKind {
+    /// OpenStreetMap Nominatim web service
+    Nominatim,
+    /// Photon (Komoot) web service
+    Photon,
+    /// Pelias-compatible web service at --geocoder-url
+    Pelias,
+    /// JSON web service described by --geocoder-config
+    Template,
+    /// Local GeoNames gazetteer given with --gazetteer
+    Offline,
+}
+
+impl GeocoderKind {
+    /// The geocoder, making at most `rate` requests per second when given.
+    fn geocoder(self, cli: &Cli, rate: Option<f64>) -> anyhow::Result<Box<dyn GeocodingService + Send + Sync>> {
+        let url = cli.geocoder_url.as_deref();
+        if url.is_some() && matches!(self, GeocoderKind::Template | GeocoderKind::Offline) {
+            anyhow::bail!("--geocoder-url only applies to nominatim, photon and pelias");
+        }
+        let limiter = match (rate, self, url) {
+            (Some(rate), _, _) => Some(Arc::new(RateLimiter::per_second(rate))),
+            // The public instance allows one request per second
+            (None, GeocoderKind::Nominatim, None) => Some(NominatimGeocoder::process_rate_limiter()),
+            (None, _, _) => None,
+        };
+        let mut client = HttpClient::new()
+            .with_user_agent(&cli.user_agent)?
+            .with_timeout(cli.timeout)?
+            .with_retry_policy(RetryPolicy { max_retries: cli.retries, ..RetryPolicy::default() });
+        if let Some(limiter) = limiter {
+            client = client.with_rate_limiter(limiter);
+        }
+
+        match self {
+            GeocoderKind::Nominatim => Ok(Box::new(
+                NominatimGeocoder::new()
+                    .with_base_url(url.unwrap_or(NOMINATIM_URL))
+                    .with_email(cli.email.clone())
+                    .with_client(client),
+            )),
+            GeocoderKind::Photon => Ok(Box::new(PhotonGeocoder::new().with_base_url(url.unwrap_or(PHOTON_URL)).with_client(client))),
+            GeocoderKind::Pelias => {
+                let url = url.ok_or_else(|| anyhow::anyhow!("--geocoder pelias requires --geocoder-url <url>"))?;
+                Ok(Box::new(PeliasGeocoder::new(url).with_api_key(cli.api_key.clone()).with_client(client)))
+            }
+            GeocoderKind::Template => {
+                let path = cli.geocoder_config.as_ref().ok_or_else(|| anyhow::anyhow!("--geocoder template requires --geocoder-config <path>"))?;
+                let config = TemplateConfig::from_path(path)?;
+                Ok(Box::new(TemplateGeocoder::new(config).with_api_key(cli.api_key.clone()).with_client(client)))
+            }
+            GeocoderKind::Offline => {
+                let path = cli.gazetteer.as_ref().ok_or_else(|| anyhow::anyhow!("--geocoder offline requires --gazetteer <path>"))?;
+                let geocoder = OfflineGeocoder::from_path(path)?;
+                match rate {
+                    Some(rate) if rate > 0.0 => Ok(Box::new(RateLimitedGeocoder::new(geocoder, RateLimiter::per_second(rate)))),
+                    _ => Ok(Box::new(geocoder)),
+                }
+            }
+        }
+    }
+}
+
+/// The geocoder selected on the command line, behind the cache unless `--no-cache`.
+/// `meccz batch --rate-limit` replaces the one request per second of the public nominatim.
+fn build_geocoder(cli: &Cli) -> anyhow::Result<Box<dyn GeocodingService + Send + Sync>> {
+    let rate = match &cli.command {
+        Some(Command::Batch { rate_limit, .. }) => *rate_limit,
+        _ => None,
+    };
+    let geocoder = cli.geocoder.geocoder(cli, rate)?;
+    if cli.no_cache {
+        return Ok(geocoder);
+    }
+    let Some(path) = cli.cache_file.clone().or_else(default_cache_path) else {
+        return Ok(geocoder);
+    };
+    match GeocodingCache::open(path) {
+        Ok(cache) => Ok(Box::new(CachedGeocoder::new(geocoder, cache))),
+        Err(e) => {
+            eprintln!("Warning: geocoding cache disabled: {:#}", e);
+            Ok(geocoder)
+        }
+    }
+}
+
+impl Cli {
+    /// The command or option whose text output has no translation, if one was given.
+    fn untranslated_output(&self) -> Option<&'static str> {
+        match &self.command {
+            Some(Command::Sun { .. }) => Some("sun"),
+            Some(Command::Zenith { .. }) => Some("zenith"),
+            Some(Command::Times { .. }) => Some("times"),
+            Some(Command::Batch { .. }) => Some("batch"),
+            Some(Command::Cache { .. }) => Some("cache"),
+            Some(Command::Targets) => Some("targets"),
+            None if self.candidates => Some("--candidates"),
+            _ => None,
+        }
+    }
+}
+
+impl EarthModel {
+    fn calculator(self, target: Target) -> Box<dyn QiblaCalculator + Send + Sync> {
+        match self {
+            EarthModel::Sphere => Box::new(GreatCircleCalculator::new().with_target(target)),
+            EarthModel::Wgs84 => Box::new(EllipsoidalCalculator::new().with_target(target)),
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
+    if cli.lang != Language::English && let Some(output) = cli.untranslated_output() {
+        Cli::command()
+            .error(ErrorKind::ArgumentConflict, format!("--lang is not supported by {}, whose output is only in English", output))
+            .exit();
+    }
 
-    let geocoder = NominatimGeocoder::new();
-    let calculator = GreatCircleCalculator::new();
-    let app = MeccaApp::new(geocoder, calculator);
-
-    match app.get_location(&cli.location).await {
-        Ok(location) => {
-            if cli.table {
-                let table = app.get_compass_table(&location);
-                if cli.json {
-                    let output = serde_json::to_string_pretty(&table)?;
-                    println!("{}", output);
-                } else {
-                    display_table(&table);
-                }
+    match &cli.command {
+        Some(Command::Cache { action }) => return run_cache(&cli, action),
+        Some(Command::Targets) => return run_targets(&cli),
+        _ => {}
+    }
+
+    let geocoder = match build_geocoder(&cli) {
+        Ok(geocoder) => geocoder,
+        Err(e) => exit_with_error(&e, &cli),
+    };
+    let calculator = cli.model.calculator(cli.target.clone().unwrap_or_else(Target::kaaba));
+    let mut app = MeccaApp::new(geocoder, calculator);
+    if let Some(rose) = cli.points.clone() {
+        app = app.with_compass_rose(rose);
+    }
+    let date = cli.date.unwrap_or_else(|| chrono::Local::now().date_naive());
+    let i18n = Localizer::new(cli.lang);
+
+    let app = Arc::new(app);
+    let result = run_command(&app, &cli, date, &i18n).await;
+    save_cache(&app);
+    result
+}
+
+/// Runs the command given on the command line. The caller saves the geocoding cache
+/// afterwards; paths that exit the process save it first.
+async fn run_command(app: &Arc<App>, cli: &Cli, date: NaiveDate, i18n: &Localizer) -> Result<(), Box<dyn std::error::Error>> {
+    match &cli.command {
+        Some(Command::Sun { location, tz }) => {
+            let location = resolve_location(app, cli, location).await;
+            let times = app.get_sun_qibla_times(&location, date, tz.offset_on(date));
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&times)?);
             } else {
-                let qibla = app.get_qibla(&location);
-                if cli.json {
-                    let output = serde_json::to_string_pretty(&qibla)?;
-                    println!("{}", output);
-                } else {
-                    println!("Direction to Mecca:");
-                    println!("Bearing: {:.2}° from North", qibla.bearing);
-                    println!("Direction: {}", qibla.direction);
-                    println!("Distance: {:.0} km", qibla.distance_km);
+                display_sun_times(&times, app.target());
+            }
+        }
+        Some(Command::Zenith { location, year, tz }) => {
+            let year = year.unwrap_or(date.year());
+            match location {
+                Some(input) => {
+                    let location = resolve_location(app, cli, input).await;
+                    let observations = app.get_zenith_transit_observations(&location, year);
+                    if cli.json {
+                        println!("{}", serde_json::to_string_pretty(&observations)?);
+                    } else {
+                        display_zenith_observations(year, &observations, tz);
+                    }
+                }
+                None => {
+                    let transits = kaaba_zenith_transits(year);
+                    if cli.json {
+                        println!("{}", serde_json::to_string_pretty(&transits)?);
+                    } else {
+                        display_zenith_transits(year, &transits, tz);
+                    }
                 }
             }
         }
-        Err(e) => {
+        Some(Command::Times { location, method, asr, high_latitude, offsets, month, tz, format }) => {
+            let location = resolve_location(app, cli, location).await;
+            let settings = PrayerSettings {
+                method: *method,
+                asr: *asr,
+                high_latitude: *high_latitude,
+                offsets: offsets.clone(),
+            };
+            let days = if *month {
+                monthly_prayer_times(&location, date.year(), date.month(), tz, &settings)?
+            } else {
+                vec![prayer_times(&location, date, tz.offset_on(date), &settings)]
+            };
+            let format = if cli.json { CalendarFormat::Json } else { *format };
+            match format {
+                CalendarFormat::Json if *month => println!("{}", serde_json::to_string_pretty(&days)?),
+                CalendarFormat::Json => println!("{}", serde_json::to_string_pretty(&days[0])?),
+                CalendarFormat::Csv => display_prayer_csv(&days),
+                CalendarFormat::Text => display_prayer_times(&location, &settings, &days),
+            }
+        }
+        Some(Command::Compare { location }) => {
+            let location = resolve_location(app, cli, location).await;
+            let comparisons = app.compare_conventions(&location);
             if cli.json {
-                let error_output = serde_json::json!({"error": e.to_string()});
-                println!("{}", serde_json::to_string_pretty(&error_output)?);
+                println!("{}", serde_json::to_string_pretty(&comparisons)?);
             } else {
-                eprintln!("Error: {}", e);
+                display_comparisons(&location, app.target(), &comparisons, i18n);
+            }
+        }
+        Some(Command::Render { location: input, out, size, table, magnetic }) => {
+            let location = resolve_location(app, cli, input).await;
+            let place = describe_coordinates(app, cli, input, &location).await;
+            let label = place.map(|place| place.display_name).unwrap_or_else(|| input.trim().to_string());
+            let options = RenderOptions { out, size: *size, table: *table, magnetic: *magnetic };
+            if let Err(e) = run_render(app, cli, &location, label, date, i18n, options) {
+                save_and_exit(app, &e, cli);
             }
-            std::process::exit(1);
+        }
+        Some(Command::Serve { bind, cors_origins }) => {
+            let origins = match cors_origins.as_slice() {
+                [] => CorsOrigins::Any,
+                list => CorsOrigins::List(list.to_vec()),
+            };
+            let bind = *bind;
+            let saving = tokio::spawn(save_cache_periodically(app.clone()));
+            let result = serve(server::router(app.clone(), &origins), bind).await;
+            saving.abort();
+            if let Err(e) = result {
+                save_and_exit(app, &e, cli);
+            }
+        }
+        Some(Command::Batch { .. }) => {
+            if let Err(e) = run_batch(app, cli).await {
+                save_and_exit(app, &e, cli);
+            }
+        }
+        Some(Command::Cache { .. } | Command::Targets) => unreachable!("handled before building the geocoder"),
+        None => {
+            let input = cli.location.as_deref().unwrap_or_default();
+            if cli.candidates {
+                return run_candidates(app, cli, input).await;
+            }
+            let location = resolve_location(app, cli, input).await;
+            if let Some(format) = cli.export {
+                run_export(app, cli, input, &location, format);
+                return Ok(());
+            }
+            let place = describe_coordinates(app, cli, input, &location).await;
+            run_qibla(app, cli, &location, place.as_ref(), date, i18n)?;
         }
     }
 
     Ok(())
 }
 
-fn display_table(table: &meccz::CompassTable) {
-    println!("Location: {:.4}, {:.4}", table.location.latitude, table.location.longitude);
-    println!("Qibla Direction: {:.1}°", table.qibla_bearing);
-    println!("Direct Distance to Mecca: {:.0} km", table.direct_distance_km);
-    println!();
-    println!("Compass Direction Table - Distances to Mecca via Each Direction");
-    println!("================================================================");
-    println!("{:<8} {:<8} {:<10} {:<12} {:<12} {:<8}", 
-        "Direction", "Bearing", "Diff°", "Short Path", "Long Path", "Optimal");
+fn run_targets(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let targets = Targets::default();
+    if cli.json {
+        println!("{}", serde_json::to_string_pretty(targets.all())?);
+        return Ok(());
+    }
+    println!("{:<12} {:>9} {:>10}  Name", "Target", "Latitude", "Longitude");
+    println!("{}", "-".repeat(60));
+    for target in targets.all() {
+        println!(
+            "{:<12} {:>9.4} {:>10.4}  {}",
+            target.id, target.location.latitude, target.location.longitude, target.name
+        );
+    }
+    Ok(())
+}
+
+fn run_cache(cli: &Cli, action: &CacheAction) -> Result<(), Box<dyn std::error::Error>> {
+    let path = cli
+        .cache_file
+        .clone()
+        .or_else(default_cache_path)
+        .ok_or("cannot locate the cache directory, use --cache-file")?;
+    let mut cache = GeocodingCache::open(path)?;
+    let now = chrono::Utc::now();
+
+    match action {
+        CacheAction::List if cli.json => {
+            println!("{}", serde_json::to_string_pretty(&cache.entries())?);
+        }
+        CacheAction::List => {
+            println!("Geocoding cache: {} ({} entries)", cache.path().display(), cache.len());
+            for entry in cache.entries() {
+                let result = match (&entry.location, &entry.place) {
+                    (_, Some(place)) if entry.reverse => place.display_name.clone(),
+                    (Some(location), _) if !entry.reverse => format!("{:.4}, {:.4}", location.latitude, location.longitude),
+                    _ => "not found".to_string(),
+                };
+                let expired = if cache.is_expired(entry, now) { "  (expired)" } else { "" };
+                println!(
+                    "  {:<32} {:<22} {:<10} {}{}",
+                    entry.query,
+                    result,
+                    entry.source,
+                    entry.cached_at.format("%Y-%m-%d %H:%M"),
+                    expired
+                );
+            }
+        }
+        CacheAction::Export { format: ExportFormat::Json } => {
+            println!("{}", serde_json::to_string_pretty(&cache.entries())?);
+        }
+        CacheAction::Export { format: ExportFormat::Csv } => {
+            write_csv(&cache.entries(), std::io::stdout().lock())?;
+        }
+        CacheAction::Purge { expired, negative } => {
+            let filter = match (expired, negative) {
+                (true, _) => PurgeFilter::Expired,
+                (_, true) => PurgeFilter::Negative,
+                _ => PurgeFilter::All,
+            };
+            let removed = cache.purge(filter, now);
+            cache.save()?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&serde_json::json!({"removed": removed, "remaining": cache.len()}))?);
+            } else {
+                println!("Removed {} entries, {} remaining", removed, cache.len());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints `error` as text or JSON and exits the process. Lookup failures carry their
+/// [`MecczError`] code and exit status; anything else is a generic `error` with status 1.
+fn exit_with_error(error: &anyhow::Error, cli: &Cli) -> ! {
+    let typed = error.downcast_ref::<MecczError>();
+    if cli.json {
+        let mut error_output = serde_json::json!({
+            "error": format!("{:#}", error),
+            "code": typed.map_or("error", MecczError::code),
+        });
+        if let Some(field) = typed.and_then(MecczError::field) {
+            error_output["field"] = field.into();
+        }
+        println!("{}", serde_json::to_string_pretty(&error_output).unwrap_or_default());
+    } else {
+        // Errors with added context keep their English chain
+        let i18n = Localizer::new(cli.lang);
+        let message = match typed {
+            Some(typed) if error.chain().count() == 1 => i18n.error(typed),
+            _ => format!("{:#}", error),
+        };
+        eprintln!("{}", i18n.format(i18n.text().error, &[&message]));
+    }
+    std::process::exit(typed.map_or(1, MecczError::exit_code));
+}
+
+/// Resolves the location argument, reporting failures and exiting the process.
+async fn resolve_location(app: &App, cli: &Cli, input: &str) -> Location {
+    match locate(app, cli, input).await {
+        Ok(location) => location,
+        // Keeps a "Location not found" answer
+        Err(e) => save_and_exit(app, &e, cli),
+    }
+}
+
+/// Picks the place meant by `input`: the `--pick` candidate, the user's choice when
+/// the address is ambiguous and we can ask, the best match otherwise.
+async fn locate(app: &App, cli: &Cli, input: &str) -> anyhow::Result<Location> {
+    let interactive = !cli.json && std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+    if cli.pick.is_none() && !interactive {
+        return Ok(app.get_location(input).await?);
+    }
+
+    let candidates = app.get_location_candidates(input, CANDIDATE_LIMIT).await?;
+    let index = match cli.pick {
+        Some(pick) => usize::from(pick - 1),
+        None if candidates_are_ambiguous(&candidates) => choose_candidate(input, &candidates)?,
+        None => 0,
+    };
+    candidates
+        .get(index)
+        .map(|candidate| candidate.location.clone())
+        .ok_or_else(|| anyhow::anyhow!("--pick {} is out of range: \"{}\" matches {} places", index + 1, input, candidates.len()))
+}
+
+/// Asks on the terminal which of the candidates the user meant.
+fn choose_candidate(input: &str, candidates: &[GeocodingCandidate]) -> anyhow::Result<usize> {
+    eprintln!("\"{}\" matches several places:", input);
+    for (number, candidate) in candidates.iter().enumerate() {
+        eprintln!("  {:>2}. {} ({})", number + 1, candidate.display_name, candidate.kind);
+    }
+
+    let stdin = std::io::stdin();
+    loop {
+        eprint!("Choose a place [1-{}, default 1]: ", candidates.len());
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        if stdin.lock().read_line(&mut answer)? == 0 {
+            return Ok(0);
+        }
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(0);
+        }
+        match answer.parse::<usize>() {
+            Ok(number) if (1..=candidates.len()).contains(&number) => return Ok(number - 1),
+            _ => eprintln!("Please enter a number between 1 and {}.", candidates.len()),
+        }
+    }
+}
+
+/// Lists every place matching `input` with the Qibla direction from each.
+async fn run_candidates(app: &App, cli: &Cli, input: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let candidates = match app.get_location_candidates(input, CANDIDATE_LIMIT).await {
+        Ok(candidates) => candidates,
+        Err(e) => save_and_exit(app, &e.into(), cli),
+    };
+    let outputs: Vec<CandidateOutput> = candidates
+        .iter()
+        .map(|candidate| CandidateOutput {
+            candidate,
+            qibla: app.get_qibla(&candidate.location),
+        })
+        .collect();
+
+    if cli.json {
+        println!("{}", serde_json::to_string_pretty(&outputs)?);
+        return Ok(());
+    }
+
+    println!("{:<4} {:>8} {:>10} {:<16} Place", "#", "Bearing", "Distance", "Type");
     println!("{}", "-".repeat(70));
-    
-    // Sort entries by short path distance to show best routes first
-    let mut sorted_entries = table.entries.clone();
-    sorted_entries.sort_by(|a, b| a.short_path_distance_km.partial_cmp(&b.short_path_distance_km).unwrap());
-    
-    for entry in &sorted_entries {
-        let optimal_marker = if entry.is_optimal_direction { "*" } else { "" };
-        println!("{:<8} {:<8.1}° {:<10.1}° {:<12.0} {:<12.0} {:<8}", 
-            entry.direction, 
-            entry.bearing, 
-            entry.angular_difference,
-            entry.short_path_distance_km,
-            entry.long_path_distance_km,
-            optimal_marker
+    for (number, output) in outputs.iter().enumerate() {
+        println!(
+            "{:<4} {:>7.2}° {:>7.0} km {:<16} {}",
+            number + 1,
+            output.qibla.bearing,
+            output.qibla.distance_km,
+            output.candidate.kind,
+            output.candidate.display_name
         );
     }
-    
+    Ok(())
+}
+
+/// Names the place of coordinates typed by the user when `--reverse` asks for it, so
+/// they can check them. Addresses are skipped: the user already knows where they are.
+async fn describe_coordinates(app: &App, cli: &Cli, input: &str, location: &Location) -> Option<Place> {
+    if !cli.reverse || app.coordinate_formats().detect(input).is_none() {
+        return None;
+    }
+    match app.describe_location(location).await {
+        Ok(place) => Some(place),
+        Err(MecczError::LocationNotFound(_)) => None,
+        Err(e) => {
+            eprintln!("Warning: place name unavailable: {}", e);
+            None
+        }
+    }
+}
+
+/// Prints the great-circle path from `location` to the target in `format`.
+fn run_export(app: &App, cli: &Cli, input: &str, location: &Location, format: PathFormat) {
+    let density = match (cli.waypoints, cli.spacing) {
+        (Some(count), _) => Density::Waypoints(count as usize),
+        (None, Some(spacing)) => Density::SpacingKm(spacing),
+        (None, None) => Density::default(),
+    };
+    let path = app.get_path(location, density);
+    let name = format!("Qibla from {} to {}", input.trim(), app.target().name);
+    print!("{}", format.render(&path, &name));
+}
+
+/// Reads the locations of `meccz batch`, writes a row for each and reports a summary on
+/// standard error. Rows that fail are written with their error and do not stop the batch.
+async fn run_batch(app: &App, cli: &Cli) -> anyhow::Result<()> {
+    let Some(Command::Batch { input, format, out, output_format, id_columns, location_column, concurrency, .. }) = &cli.command else {
+        unreachable!("run_batch is only called for the batch command");
+    };
+    let input = input.as_ref().filter(|path| path.as_os_str() != "-");
+    let text = match input {
+        Some(path) => std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?,
+        None => std::io::read_to_string(std::io::stdin()).context("Cannot read standard input")?,
+    };
+    let format = format
+        .or_else(|| input.and_then(|path| BatchFormat::from_path(path)))
+        .unwrap_or_else(|| BatchFormat::sniff(&text));
+    let columns = BatchColumns { ids: id_columns.clone(), location: location_column.clone() };
+    let batch = BatchInput::parse(&text, format, &columns)?;
+
+    let output_format = output_format
+        .or_else(|| out.as_ref().and_then(|path| BatchFormat::from_path(path)))
+        .unwrap_or(if cli.json { BatchFormat::Jsonl } else { BatchFormat::Csv });
+    let sink: Box<dyn Write> = match out {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path).with_context(|| format!("Cannot write {}", path.display()))?,
+        )),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let mut writer = BatchWriter::new(sink, output_format, batch.id_columns.clone());
+
+    let started = std::time::Instant::now();
+    let total = batch.records.len();
+    let progress = out.is_some() && std::io::stderr().is_terminal();
+    let mut summary = BatchSummary::default();
+    let mut results = std::pin::pin!(meccz::batch::process(app, batch.records, *concurrency as usize));
+    while let Some(result) = results.next().await {
+        summary.record(&result);
+        writer.write(&result).context("Cannot write the batch output")?;
+        if progress {
+            eprint!("\r{}/{} locations", summary.total, total);
+        }
+    }
+    writer.flush().context("Cannot write the batch output")?;
+    if progress {
+        eprintln!();
+    }
+
+    let elapsed = started.elapsed().as_secs_f64();
+    if cli.json {
+        let mut report = serde_json::to_value(&summary)?;
+        report["elapsed_seconds"] = elapsed.into();
+        eprintln!("{}", report);
+    } else {
+        let errors: Vec<String> = summary.errors.iter().map(|(code, count)| format!("{} {}", count, code)).collect();
+        let detail = if errors.is_empty() { String::new() } else { format!(" ({})", errors.join(", ")) };
+        eprintln!(
+            "Processed {} locations in {:.1} s: {} succeeded, {} failed{}",
+            summary.total, elapsed, summary.succeeded, summary.failed, detail
+        );
+    }
+    Ok(())
+}
+
+/// How often `serve` writes new geocoding answers to the cache.
+const CACHE_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Saves the cache every [`CACHE_SAVE_INTERVAL`] on a blocking thread, so that a
+/// killed server loses at most that much.
+async fn save_cache_periodically(app: Arc<App>) {
+    let mut interval = tokio::time::interval(CACHE_SAVE_INTERVAL);
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let app = app.clone();
+        let _ = tokio::task::spawn_blocking(move || save_cache(&app)).await;
+    }
+}
+
+/// Writes new geocoding answers to the cache file; failing to do so is only a warning.
+fn save_cache(app: &App) {
+    if let Err(e) = app.persist() {
+        eprintln!("Warning: geocoding cache not saved: {:#}", e);
+    }
+}
+
+/// Saves the geocoding cache, then reports `error` and exits like [`exit_with_error`].
+fn save_and_exit(app: &App, error: &anyhow::Error, cli: &Cli) -> ! {
+    save_cache(app);
+    exit_with_error(error, cli)
+}
+
+async fn serve(router: axum::Router, bind: std::net::SocketAddr) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(bind)
+        .await
+        .with_context(|| format!("Cannot listen on {}", bind))?;
+    eprintln!("Listening on http://{} (API description at /openapi.json)", listener.local_addr()?);
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    Ok(())
+}
+
+/// Options of the `render` subcommand.
+struct RenderOptions<'a> {
+    out: &'a PathBuf,
+    size: u32,
+    table: bool,
+    magnetic: bool,
+}
+
+fn run_render(
+    app: &App,
+    cli: &Cli,
+    location: &Location,
+    label: String,
+    date: NaiveDate,
+    i18n: &Localizer,
+    options: RenderOptions,
+) -> anyhow::Result<()> {
+    let mut qibla = app.get_qibla(location);
+    if options.magnetic {
+        match app.get_magnetic_qibla(&MagneticModel::wmm(), location, 0.0, date) {
+            Ok(corrected) => qibla = corrected,
+            Err(e) => eprintln!("Warning: magnetic bearing unavailable: {}", e),
+        }
+    }
+    let mut card = QiblaCard::new(qibla, app.target().clone())
+        .with_label(label)
+        .with_size(options.size)
+        .with_localizer(*i18n);
+    if options.table {
+        card = card.with_table(app.get_compass_table(location));
+    }
+
+    let is_png = options
+        .out
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+    let bytes = if is_png { render_png(&card)? } else { card.to_svg().into_bytes() };
+    std::fs::write(options.out, bytes)
+        .map_err(|e| anyhow::anyhow!("Cannot write {}: {}", options.out.display(), e))?;
+
+    let format = if is_png { "png" } else { "svg" };
+    if cli.json {
+        println!("{}", serde_json::json!({ "path": options.out, "format": format }));
+    } else {
+        println!("Wrote {}", options.out.display());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "png")]
+fn render_png(card: &QiblaCard) -> anyhow::Result<Vec<u8>> {
+    card.to_png()
+}
+
+#[cfg(not(feature = "png"))]
+fn render_png(_card: &QiblaCard) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("PNG output requires building with --features png; use a .svg file instead")
+}
+
+fn run_qibla(
+    app: &App,
+    cli: &Cli,
+    location: &Location,
+    place: Option<&Place>,
+    date: NaiveDate,
+    i18n: &Localizer,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let magnetic_model = MagneticModel::wmm();
+    let altitude_km = cli.altitude / 1000.0;
+    let coordinates = match &cli.output_coords {
+        Some(format) => Some(FormattedCoordinates {
+            format: format.clone(),
+            value: app.coordinate_formats().format(format, location)?,
+        }),
+        None => None,
+    };
+
+    let target = Some(app.target()).filter(|target| !target.is_kaaba());
+
+    if cli.table {
+        let mut table = app.get_compass_table(location);
+        if cli.magnetic {
+            match magnetic_model.magnetic_compass_table(&table, altitude_km, date) {
+                Ok(magnetic) => table = magnetic,
+                Err(e) => eprintln!("Warning: magnetic bearings unavailable: {}", e),
+            }
+        }
+        if cli.json {
+            let output = serde_json::to_string_pretty(&LocatedOutput { result: &table, place, coordinates: coordinates.as_ref(), target })?;
+            println!("{}", output);
+        } else {
+            if cli.visual {
+                display_compass(&app.get_qibla(location), i18n);
+            }
+            display_table(&table, app.target(), place, coordinates.as_ref(), i18n);
+        }
+    } else {
+        let mut qibla = app.get_qibla(location);
+        match app.get_magnetic_qibla(&magnetic_model, location, altitude_km, date) {
+            Ok(corrected) => qibla = corrected,
+            Err(e) => eprintln!("Warning: magnetic bearing unavailable: {}", e),
+        }
+        if cli.rhumb {
+            qibla.rhumb_line = Some(app.get_rhumb_line(location));
+        }
+        if cli.json {
+            let output = serde_json::to_string_pretty(&LocatedOutput { result: &qibla, place, coordinates: coordinates.as_ref(), target })?;
+            println!("{}", output);
+        } else {
+            if cli.visual {
+                display_compass(&qibla, i18n);
+            }
+            let text = i18n.text();
+            if let Some(place) = place {
+                println!("{}", i18n.format(text.place, &[&place.display_name]));
+            }
+            if let Some(coordinates) = &coordinates {
+                println!("{}", i18n.format(text.location_format, &[&coordinates.value, &coordinates.format]));
+            }
+            println!("{}", i18n.format(text.direction_to, &[&i18n.target_label(app.target())]));
+            println!("{}", i18n.format(text.bearing, &[&i18n.number(qibla.bearing, 2)]));
+            if let Some(magnetic) = &qibla.magnetic {
+                let side = i18n.direction(if magnetic.declination >= 0.0 { "E" } else { "W" });
+                println!(
+                    "{}",
+                    i18n.format(
+                        text.magnetic_bearing,
+                        &[
+                            &i18n.number(magnetic.bearing, 2),
+                            &i18n.direction(&magnetic.direction),
+                            &i18n.number(magnetic.declination.abs(), 2),
+                            &side,
+                            &magnetic.model,
+                            &i18n.ungrouped(magnetic.valid_from, 1),
+                            &i18n.ungrouped(magnetic.valid_until, 1),
+                        ]
+                    )
+                );
+            }
+            println!("{}", i18n.format(text.direction, &[&i18n.direction(&qibla.direction)]));
+            println!("{}", i18n.format(text.distance, &[&i18n.number(qibla.distance_km, 0)]));
+            if let Some(rhumb) = &qibla.rhumb_line {
+                display_rhumb_line(&qibla, rhumb, i18n);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Draws the compass dial for `--visual`, in color on a terminal unless `NO_COLOR` is set.
+/// Output that is not a terminal is laid out for `COLUMNS` columns, or 80.
+fn display_compass(qibla: &QiblaDirection, i18n: &Localizer) {
+    let stdout = std::io::stdout();
+    let width = terminal_size::terminal_size_of(&stdout)
+        .map(|(width, _)| width.0 as usize)
+        .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+        .unwrap_or(80);
+    let color = stdout.is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let compass = TerminalCompass::new(qibla).with_width(width).with_color(color).with_localizer(*i18n);
+    for line in compass.lines() {
+        println!("{}", line);
+    }
+    println!();
+}
+
+fn display_rhumb_line(qibla: &QiblaDirection, rhumb: &RhumbLine, i18n: &Localizer) {
+    let text = i18n.text();
+    let extra = rhumb.distance_km - qibla.distance_km;
+    println!();
+    println!("{}", i18n.format(text.rhumb_title, &[]));
+    println!(
+        "{}",
+        i18n.format(
+            text.rhumb_bearing,
+            &[&i18n.number(rhumb.bearing, 2), &i18n.signed(bearing_difference(rhumb.bearing, qibla.bearing), 2)]
+        )
+    );
+    println!("{}", i18n.format(text.direction, &[&i18n.direction(&rhumb.direction)]));
+    println!(
+        "{}",
+        i18n.format(
+            text.rhumb_distance,
+            &[
+                &i18n.number(rhumb.distance_km, 0),
+                &i18n.signed(extra, 0),
+                &i18n.signed(100.0 * extra / qibla.distance_km.max(f64::EPSILON), 1),
+            ]
+        )
+    );
+    println!();
+    println!("{}", i18n.format(text.rhumb_explanation, &[]));
+}
+
+fn display_comparisons(location: &Location, target: &Target, comparisons: &[ConventionComparison], i18n: &Localizer) {
+    let text = i18n.text();
+    println!("{}", i18n.format(text.location, &[&i18n.coordinates(location)]));
+    println!("{}", i18n.format(text.conventions_towards, &[&target.name]));
+    println!();
+    let header = format!(
+        "{:<24} {:>9} {:>10} {:>9} {:>10}",
+        text.header_convention, text.header_bearing, text.header_deviation, text.header_direction, text.header_distance
+    );
+    println!("{}", i18n.line(&header));
+    println!("{}", "-".repeat(header.chars().count().max(66)));
+    for comparison in comparisons {
+        let row = format!(
+            "{:<24} {:>9} {:>10} {:>9} {:>10}",
+            i18n.convention_name(comparison.convention),
+            format!("{}°", i18n.number(comparison.direction.bearing, 2)),
+            format!("{}°", i18n.signed(comparison.deviation, 2)),
+            i18n.direction(&comparison.direction.direction),
+            format!("{} {}", i18n.number(comparison.direction.distance_km, 0), text.kilometres)
+        );
+        println!("{}", i18n.line(&row));
+    }
+    println!();
+    println!("{}", i18n.format(text.legend_deviation, &[]));
+    for convention in comparisons.iter().map(|comparison| comparison.convention) {
+        let line = format!("  {}: {}", i18n.convention_name(convention), i18n.convention_description(convention));
+        println!("{}", i18n.line(&line));
+    }
+}
+
+fn display_sun_times(times: &SunQiblaTimes, target: &Target) {
+    println!("Location: {:.4}, {:.4}", times.location.latitude, times.location.longitude);
+    println!("Date: {}", times.date);
+    if target.is_kaaba() {
+        println!("Qibla Direction: {:.1}°", times.qibla_bearing);
+    } else {
+        println!("Direction to {}: {:.1}°", target.name, times.qibla_bearing);
+    }
+    println!();
+
+    if times.events.is_empty() {
+        println!("The sun does not pass the Qibla bearing or its reciprocal on this day.");
+        return;
+    }
+
+    println!("{:<16} {:<22} {:>9} {:>9}", "Time", "Sun position", "Azimuth", "Altitude");
+    println!("{}", "-".repeat(59));
+    for event in &times.events {
+        let position = match event.alignment {
+            SunAlignment::TowardsQibla => "In Qibla direction",
+            SunAlignment::AwayFromQibla => "Opposite Qibla",
+        };
+        let visibility = if event.visible { "" } else { "  (below horizon)" };
+        println!(
+            "{:<16} {:<22} {:>8.1}° {:>8.1}°{}",
+            event.time.format("%H:%M:%S %:z"),
+            position,
+            event.azimuth,
+            event.altitude,
+            visibility
+        );
+    }
+
+    println!();
+    println!("In Qibla direction = face the sun to face the Qibla");
+    println!("Opposite Qibla = turn your back to the sun; your shadow points to the Qibla");
+}
+
+fn display_zenith_transits(year: i32, transits: &[KaabaZenithTransit], tz: &TimeZoneSpec) {
+    println!("Sun directly over the Kaaba in {} (Istiwa al-A'zam):", year);
+    for transit in transits {
+        let local = transit.instant.with_timezone(&tz.offset_on(transit.instant.date_naive()));
+        println!(
+            "  {} UTC  ({} local)  zenith distance {:.2}°",
+            transit.instant.format("%Y-%m-%d %H:%M:%S"),
+            local.format("%H:%M:%S %:z"),
+            transit.zenith_distance
+        );
+    }
+    println!();
+    println!("At these instants, anyone who can see the sun faces the Qibla by facing it.");
+}
+
+fn display_zenith_observations(year: i32, observations: &[ZenithTransitObservation], tz: &TimeZoneSpec) {
+    let transits: Vec<KaabaZenithTransit> = observations.iter().map(|o| o.transit.clone()).collect();
+    display_zenith_transits(year, &transits, tz);
+
+    if let Some(first) = observations.first() {
+        println!();
+        println!("From {:.4}, {:.4} (Qibla {:.1}°):", first.location.latitude, first.location.longitude, first.qibla_bearing);
+    }
+    for observation in observations {
+        let date = observation.transit.instant.format("%Y-%m-%d");
+        if observation.sun_visible {
+            println!(
+                "  {}: sun at {:.1}° azimuth, {:.1}° altitude; face the sun, shadows point to {:.1}°",
+                date, observation.sun_azimuth, observation.sun_altitude, observation.shadow_direction
+            );
+        } else {
+            println!("  {}: sun below the horizon ({:.1}° altitude), not observable", date, observation.sun_altitude);
+        }
+    }
+}
+
+const PRAYER_COLUMNS: [&str; 7] = ["Fajr", "Sunrise", "Dhuhr", "Asr", "Maghrib", "Isha", "Midnight"];
+
+fn prayer_columns(day: &PrayerTimes) -> [String; 7] {
+    [&day.fajr, &day.sunrise, &day.dhuhr, &day.asr, &day.maghrib, &day.isha, &day.midnight]
+        .map(|time| time.map_or_else(|| "--:--".to_string(), |t| t.format("%H:%M").to_string()))
+}
+
+fn display_prayer_times(location: &Location, settings: &PrayerSettings, days: &[PrayerTimes]) {
+    println!("Location: {:.4}, {:.4}", location.latitude, location.longitude);
+    println!(
+        "Method: {}, Asr: {:?}, High latitudes: {:?}",
+        settings.method.name(),
+        settings.asr,
+        settings.high_latitude
+    );
+    if let Some(time) = days.iter().find_map(|day| day.dhuhr) {
+        println!("Time zone: UTC{}", time.format("%:z"));
+    }
+    println!();
+
+    print!("{:<12}", "Date");
+    for column in PRAYER_COLUMNS {
+        print!(" {:>8}", column);
+    }
+    println!();
+    println!("{}", "-".repeat(12 + 9 * PRAYER_COLUMNS.len()));
+    for day in days {
+        print!("{:<12}", day.date.format("%a %d %b"));
+        for time in prayer_columns(day) {
+            print!(" {:>8}", time);
+        }
+        println!();
+    }
+}
+
+fn display_prayer_csv(days: &[PrayerTimes]) {
+    println!("date,{}", PRAYER_COLUMNS.map(|c| c.to_lowercase()).join(","));
+    for day in days {
+        let times = [&day.fajr, &day.sunrise, &day.dhuhr, &day.asr, &day.maghrib, &day.isha, &day.midnight]
+            .map(|time| time.map(|t| t.to_rfc3339()).unwrap_or_default());
+        println!("{},{}", day.date, times.join(","));
+    }
+}
+
+fn display_table(
+    table: &meccz::CompassTable,
+    target: &Target,
+    place: Option<&Place>,
+    coordinates: Option<&FormattedCoordinates>,
+    i18n: &Localizer,
+) {
+    let text = i18n.text();
+    let label = i18n.target_label(target);
+    if let Some(place) = place {
+        println!("{}", i18n.format(text.place, &[&place.display_name]));
+    }
+    match coordinates {
+        Some(coordinates) => println!("{}", i18n.format(text.location_format, &[&coordinates.value, &coordinates.format])),
+        None => println!("{}", i18n.format(text.location, &[&i18n.coordinates(&table.location)])),
+    }
+    let bearing = i18n.number(table.qibla_bearing, 1);
+    let heading = if target.is_kaaba() {
+        i18n.format(text.qibla_direction, &[&bearing])
+    } else {
+        i18n.format(text.target_direction, &[&target.name, &bearing])
+    };
+    match table.north_reference {
+        NorthReference::True => println!("{}", heading),
+        NorthReference::Magnetic => println!("{} {}", heading, text.magnetic),
+    }
+    println!("{}", i18n.format(text.direct_distance, &[&label, &i18n.number(table.direct_distance_km, 0)]));
+    println!();
+    let title = i18n.format(text.table_title, &[&label]);
+    println!("{}", title);
+    println!("{}", "=".repeat(display_width(&title)));
+
+    let km = |value: f64| format!("{} {}", i18n.number(value, 0), text.kilometres);
+    let degrees = |value: String| format!("{}°", value);
+    let mut layout = TextTable::new([
+        (text.header_direction, Align::Left),
+        (text.header_bearing, Align::Right),
+        (text.header_difference, Align::Right),
+        (text.header_closest, Align::Right),
+        ("", Align::Left),
+        (text.header_reached_at, Align::Right),
+        (text.header_route, Align::Right),
+        (text.header_long_path, Align::Right),
+        (text.header_optimal, Align::Left),
+    ]);
+
+    // Sort entries by route distance to show best headings first
+    let mut sorted_entries: Vec<&meccz::CompassEntry> = table.entries.iter().collect();
+    sorted_entries.sort_by(|a, b| a.short_path_distance_km.total_cmp(&b.short_path_distance_km));
+
+    for entry in sorted_entries {
+        let optimal_marker = if entry.is_optimal_direction { "*" } else { "" };
+        let side = match entry.cross_track_km {
+            miss if miss.abs() < 0.5 => "",
+            miss if miss > 0.0 => text.right,
+            _ => text.left,
+        };
+        layout.push_row(vec![
+            i18n.direction(&entry.direction),
+            degrees(i18n.number(entry.bearing, 1)),
+            degrees(i18n.number(entry.angular_difference, 1)),
+            km(entry.closest_approach_km),
+            side.to_string(),
+            km(entry.along_track_km),
+            km(entry.short_path_distance_km),
+            km(entry.long_path_distance_km),
+            optimal_marker.to_string(),
+        ]);
+    }
+    for line in layout.lines() {
+        println!("{}", i18n.line(&line));
+    }
+
     println!();
-    println!("* = Closest compass direction to actual Qibla bearing");
-    println!("Short Path = Distance if traveling in this direction (shorter route)");
-    println!("Long Path = Distance if traveling opposite direction (around the world)");
+    let legend_optimal = if target.is_kaaba() { text.legend_optimal_qibla } else { text.legend_optimal };
+    println!("{}", i18n.format(legend_optimal, &[]));
+    println!("{}", i18n.format(text.legend_closest, &[&label]));
+    println!("{}", i18n.format(text.legend_reached_at, &[]));
+    println!("{}", i18n.format(text.legend_route, &[&label]));
+    println!("{}", i18n.format(text.legend_long_path, &[]));
 }
@@ -0,0 +1,505 @@
+use crate::interfaces::Location;
+use crate::solar::{declination_and_equation_of_time, julian_century};
+use crate::timezone::TimeZoneSpec;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Sun depression at sunrise and sunset: refraction plus the solar semi-diameter.
+const SUNRISE_ANGLE: f64 = 0.833;
+
+/// Conventions published by the main Islamic authorities for Fajr and Isha.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CalculationMethod {
+    /// Muslim World League
+    Mwl,
+    /// Islamic Society of North America
+    Isna,
+    /// Egyptian General Authority of Survey
+    Egypt,
+    /// Umm al-Qura University, Makkah
+    UmmAlQura,
+    /// University of Islamic Sciences, Karachi
+    Karachi,
+    /// Institute of Geophysics, University of Tehran
+    Tehran,
+    /// Shia Ithna-Ashari, Leva Institute, Qum
+    Jafari,
+    /// Gulf region
+    Gulf,
+    /// Kuwait
+    Kuwait,
+    /// Qatar
+    Qatar,
+    /// Majlis Ugama Islam Singapura
+    Singapore,
+    /// Union des Organisations Islamiques de France
+    France,
+    /// Diyanet İşleri Başkanlığı, Turkey
+    Turkey,
+}
+
+/// How Isha (or Maghrib) is determined: a sun depression angle or a fixed delay.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TwilightRule {
+    Angle(f64),
+    MinutesAfter(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MidnightMode {
+    /// Midway between sunset and sunrise
+    Standard,
+    /// Midway between sunset and Fajr
+    Jafari,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MethodParameters {
+    pub fajr_angle: f64,
+    pub maghrib: TwilightRule,
+    pub isha: TwilightRule,
+    pub midnight: MidnightMode,
+}
+
+impl CalculationMethod {
+    pub const ALL: [CalculationMethod; 13] = [
+        CalculationMethod::Mwl,
+        CalculationMethod::Isna,
+        CalculationMethod::Egypt,
+        CalculationMethod::UmmAlQura,
+        CalculationMethod::Karachi,
+        CalculationMethod::Tehran,
+        CalculationMethod::Jafari,
+        CalculationMethod::Gulf,
+        CalculationMethod::Kuwait,
+        CalculationMethod::Qatar,
+        CalculationMethod::Singapore,
+        CalculationMethod::France,
+        CalculationMethod::Turkey,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CalculationMethod::Mwl => "mwl",
+            CalculationMethod::Isna => "isna",
+            CalculationMethod::Egypt => "egypt",
+            CalculationMethod::UmmAlQura => "umm-al-qura",
+            CalculationMethod::Karachi => "karachi",
+            CalculationMethod::Tehran => "tehran",
+            CalculationMethod::Jafari => "jafari",
+            CalculationMethod::Gulf => "gulf",
+            CalculationMethod::Kuwait => "kuwait",
+            CalculationMethod::Qatar => "qatar",
+            CalculationMethod::Singapore => "singapore",
+            CalculationMethod::France => "france",
+            CalculationMethod::Turkey => "turkey",
+        }
+    }
+
+    pub fn parameters(self) -> MethodParameters {
+        use TwilightRule::{Angle, MinutesAfter};
+        let (fajr_angle, maghrib, isha, midnight) = match self {
+            CalculationMethod::Mwl => (18.0, MinutesAfter(0.0), Angle(17.0), MidnightMode::Standard),
+            CalculationMethod::Isna => (15.0, MinutesAfter(0.0), Angle(15.0), MidnightMode::Standard),
+            CalculationMethod::Egypt => (19.5, MinutesAfter(0.0), Angle(17.5), MidnightMode::Standard),
+            CalculationMethod::UmmAlQura => (18.5, MinutesAfter(0.0), MinutesAfter(90.0), MidnightMode::Standard),
+            CalculationMethod::Karachi => (18.0, MinutesAfter(0.0), Angle(18.0), MidnightMode::Standard),
+            CalculationMethod::Tehran => (17.7, Angle(4.5), Angle(14.0), MidnightMode::Jafari),
+            CalculationMethod::Jafari => (16.0, Angle(4.0), Angle(14.0), MidnightMode::Jafari),
+            CalculationMethod::Gulf => (19.5, MinutesAfter(0.0), MinutesAfter(90.0), MidnightMode::Standard),
+            CalculationMethod::Kuwait => (18.0, MinutesAfter(0.0), Angle(17.5), MidnightMode::Standard),
+            CalculationMethod::Qatar => (18.0, MinutesAfter(0.0), MinutesAfter(90.0), MidnightMode::Standard),
+            CalculationMethod::Singapore => (20.0, MinutesAfter(0.0), Angle(18.0), MidnightMode::Standard),
+            CalculationMethod::France => (12.0, MinutesAfter(0.0), Angle(12.0), MidnightMode::Standard),
+            CalculationMethod::Turkey => (18.0, MinutesAfter(0.0), Angle(17.0), MidnightMode::Standard),
+        };
+        MethodParameters {
+            fajr_angle,
+            maghrib,
+            isha,
+            midnight,
+        }
+    }
+}
+
+impl FromStr for CalculationMethod {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let normalized = input.trim().to_lowercase().replace('_', "-");
+        let alias = match normalized.as_str() {
+            "makkah" | "umm-alqura" | "ummalqura" => "umm-al-qura",
+            "uoif" => "france",
+            "diyanet" => "turkey",
+            other => other,
+        };
+        CalculationMethod::ALL
+            .into_iter()
+            .find(|method| method.name() == alias)
+            .ok_or_else(|| {
+                let names: Vec<&str> = CalculationMethod::ALL.iter().map(|m| m.name()).collect();
+                anyhow!("Unknown calculation method: {} (expected one of {})", input, names.join(", "))
+            })
+    }
+}
+
+/// Juristic convention for the shadow length that starts Asr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AsrMethod {
+    /// Shadow equals the object's length plus its noon shadow (Shafi'i, Maliki, Hanbali)
+    #[default]
+    Shafi,
+    /// Shadow equals twice the object's length plus its noon shadow
+    Hanafi,
+}
+
+impl AsrMethod {
+    fn shadow_factor(self) -> f64 {
+        match self {
+            AsrMethod::Shafi => 1.0,
+            AsrMethod::Hanafi => 2.0,
+        }
+    }
+}
+
+impl FromStr for AsrMethod {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "shafi" | "standard" => Ok(AsrMethod::Shafi),
+            "hanafi" => Ok(AsrMethod::Hanafi),
+            _ => Err(anyhow!("Unknown Asr method: {} (expected shafi or hanafi)", input)),
+        }
+    }
+}
+
+/// Fallback used when twilight never gets dark enough for the Fajr or Isha angle,
+/// as in summer above roughly 48° of latitude.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HighLatitudeRule {
+    /// No adjustment; times may be missing
+    None,
+    /// Fajr and Isha are limited to half of the night
+    #[default]
+    MiddleOfNight,
+    /// Fajr and Isha are limited to a seventh of the night
+    OneSeventh,
+    /// Fajr and Isha are limited to (angle / 60) of the night
+    AngleBased,
+}
+
+impl FromStr for HighLatitudeRule {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input.trim().to_lowercase().replace('_', "-").as_str() {
+            "none" => Ok(HighLatitudeRule::None),
+            "middle-of-night" | "night-middle" => Ok(HighLatitudeRule::MiddleOfNight),
+            "one-seventh" => Ok(HighLatitudeRule::OneSeventh),
+            "angle-based" => Ok(HighLatitudeRule::AngleBased),
+            _ => Err(anyhow!(
+                "Unknown high latitude rule: {} (expected none, middle-of-night, one-seventh or angle-based)",
+                input
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Prayer {
+    Fajr,
+    Sunrise,
+    Dhuhr,
+    Asr,
+    Maghrib,
+    Isha,
+}
+
+impl FromStr for Prayer {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "fajr" => Ok(Prayer::Fajr),
+            "sunrise" => Ok(Prayer::Sunrise),
+            "dhuhr" | "zuhr" => Ok(Prayer::Dhuhr),
+            "asr" => Ok(Prayer::Asr),
+            "maghrib" => Ok(Prayer::Maghrib),
+            "isha" => Ok(Prayer::Isha),
+            _ => Err(anyhow!("Unknown prayer: {}", input)),
+        }
+    }
+}
+
+/// A per-prayer adjustment in minutes, written `prayer=minutes` (e.g. `isha=-2`).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PrayerOffset {
+    pub prayer: Prayer,
+    pub minutes: f64,
+}
+
+impl FromStr for PrayerOffset {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let (prayer, minutes) = input
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected prayer=minutes, got: {}", input))?;
+        Ok(PrayerOffset {
+            prayer: prayer.parse()?,
+            minutes: minutes
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid minutes for {}: {}", prayer.trim(), minutes))?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrayerSettings {
+    pub method: CalculationMethod,
+    pub asr: AsrMethod,
+    pub high_latitude: HighLatitudeRule,
+    pub offsets: Vec<PrayerOffset>,
+}
+
+impl Default for PrayerSettings {
+    fn default() -> Self {
+        Self {
+            method: CalculationMethod::Mwl,
+            asr: AsrMethod::Shafi,
+            high_latitude: HighLatitudeRule::MiddleOfNight,
+            offsets: Vec::new(),
+        }
+    }
+}
+
+impl PrayerSettings {
+    fn offset_minutes(&self, prayer: Prayer) -> f64 {
+        self.offsets
+            .iter()
+            .filter(|offset| offset.prayer == prayer)
+            .map(|offset| offset.minutes)
+            .sum()
+    }
+}
+
+/// Prayer times of one day; a time is `None` when the sun never reaches the
+/// required position and no high latitude rule applies.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrayerTimes {
+    pub date: NaiveDate,
+    pub fajr: Option<DateTime<FixedOffset>>,
+    pub sunrise: Option<DateTime<FixedOffset>>,
+    pub dhuhr: Option<DateTime<FixedOffset>>,
+    pub asr: Option<DateTime<FixedOffset>>,
+    pub maghrib: Option<DateTime<FixedOffset>>,
+    pub isha: Option<DateTime<FixedOffset>>,
+    pub midnight: Option<DateTime<FixedOffset>>,
+}
+
+/// Computes one day of prayer times, following the PrayTimes.org algorithm.
+pub fn prayer_times(location: &Location, date: NaiveDate, offset: FixedOffset, settings: &PrayerSettings) -> PrayerTimes {
+    let calculator = DayCalculator {
+        location,
+        date,
+        settings,
+        parameters: settings.method.parameters(),
+    };
+    let hours = calculator.compute();
+    let midnight_utc = Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).expect("midnight is valid"));
+
+    let to_time = |prayer: Option<Prayer>, utc_hours: f64| -> Option<DateTime<FixedOffset>> {
+        if !utc_hours.is_finite() {
+            return None;
+        }
+        let adjusted = utc_hours + prayer.map_or(0.0, |p| settings.offset_minutes(p)) / 60.0;
+        // Round to the nearest minute as published timetables do
+        let minutes = (adjusted * 60.0).round() as i64;
+        Some((midnight_utc + Duration::minutes(minutes)).with_timezone(&offset))
+    };
+
+    PrayerTimes {
+        date,
+        fajr: to_time(Some(Prayer::Fajr), hours.fajr),
+        sunrise: to_time(Some(Prayer::Sunrise), hours.sunrise),
+        dhuhr: to_time(Some(Prayer::Dhuhr), hours.dhuhr),
+        asr: to_time(Some(Prayer::Asr), hours.asr),
+        maghrib: to_time(Some(Prayer::Maghrib), hours.maghrib),
+        isha: to_time(Some(Prayer::Isha), hours.isha),
+        midnight: to_time(None, hours.midnight),
+    }
+}
+
+/// Prayer times for every day of a month, with the time zone offset resolved per day.
+pub fn monthly_prayer_times(
+    location: &Location,
+    year: i32,
+    month: u32,
+    time_zone: &TimeZoneSpec,
+    settings: &PrayerSettings,
+) -> Result<Vec<PrayerTimes>> {
+    let first_day = NaiveDate::from_ymd_opt(year, month, 1)
+        .ok_or_else(|| anyhow!("Invalid month: {}-{:02}", year, month))?;
+    Ok(first_day
+        .iter_days()
+        .take_while(|date| date.month() == month)
+        .map(|date| prayer_times(location, date, time_zone.offset_on(date), settings))
+        .collect())
+}
+
+/// Times of one day in fractional UTC hours since midnight UTC of the date (negative for
+/// locations east of Greenwich whose morning falls on the previous UTC day); NaN when undefined.
+struct DayHours {
+    fajr: f64,
+    sunrise: f64,
+    dhuhr: f64,
+    asr: f64,
+    maghrib: f64,
+    isha: f64,
+    midnight: f64,
+}
+
+struct DayCalculator<'a> {
+    location: &'a Location,
+    date: NaiveDate,
+    settings: &'a PrayerSettings,
+    parameters: MethodParameters,
+}
+
+impl DayCalculator<'_> {
+    /// Solar declination and equation of time (hours) at `hours` UTC on the date.
+    fn sun(&self, hours: f64) -> (f64, f64) {
+        let midnight = Utc.from_utc_datetime(&self.date.and_hms_opt(0, 0, 0).expect("midnight is valid"));
+        let instant = midnight + Duration::milliseconds((hours * 3_600_000.0) as i64);
+        let (declination, equation_of_time) = declination_and_equation_of_time(julian_century(instant));
+        (declination, equation_of_time / 60.0)
+    }
+
+    /// Solar noon in UTC hours.
+    fn mid_day(&self, hours: f64) -> f64 {
+        let (_, equation_of_time) = self.sun(hours);
+        12.0 - equation_of_time - self.location.longitude / 15.0
+    }
+
+    /// Time at which the sun is `angle` degrees below the horizon, before noon if `morning`.
+    fn sun_angle_time(&self, angle: f64, hours: f64, morning: bool) -> f64 {
+        let (declination, _) = self.sun(hours);
+        let latitude = self.location.latitude.to_radians();
+        let delta = declination.to_radians();
+        let cos_hour_angle = (-angle.to_radians().sin() - latitude.sin() * delta.sin()) / (latitude.cos() * delta.cos());
+        if !(-1.0..=1.0).contains(&cos_hour_angle) {
+            return f64::NAN;
+        }
+        let hour_angle = cos_hour_angle.acos().to_degrees() / 15.0;
+        let noon = self.mid_day(hours);
+        if morning { noon - hour_angle } else { noon + hour_angle }
+    }
+
+    fn asr_time(&self, hours: f64) -> f64 {
+        let (declination, _) = self.sun(hours);
+        let factor = self.settings.asr.shadow_factor();
+        let altitude = (1.0 / (factor + (self.location.latitude - declination).to_radians().abs().tan())).atan();
+        self.sun_angle_time(-altitude.to_degrees(), hours, false)
+    }
+
+    fn compute(&self) -> DayHours {
+        let lon_shift = self.location.longitude / 15.0;
+        // Initial guesses in UTC hours, then one refinement pass at those instants
+        let mut fajr = 5.0 - lon_shift;
+        let mut sunrise = 6.0 - lon_shift;
+        let mut dhuhr = 12.0 - lon_shift;
+        let mut asr = 13.0 - lon_shift;
+        let mut sunset = 18.0 - lon_shift;
+        let mut maghrib = 18.0 - lon_shift;
+        let mut isha = 18.0 - lon_shift;
+
+        let guard = |value: f64, fallback: f64| if value.is_finite() { value } else { fallback };
+        for _ in 0..2 {
+            let next_fajr = self.sun_angle_time(self.parameters.fajr_angle, guard(fajr, 5.0 - lon_shift), true);
+            let next_sunrise = self.sun_angle_time(SUNRISE_ANGLE, guard(sunrise, 6.0 - lon_shift), true);
+            let next_dhuhr = self.mid_day(dhuhr);
+            let next_asr = self.asr_time(guard(asr, 13.0 - lon_shift));
+            let next_sunset = self.sun_angle_time(SUNRISE_ANGLE, guard(sunset, 18.0 - lon_shift), false);
+            let next_maghrib = match self.parameters.maghrib {
+                TwilightRule::Angle(angle) => self.sun_angle_time(angle, guard(maghrib, 18.0 - lon_shift), false),
+                TwilightRule::MinutesAfter(_) => next_sunset,
+            };
+            let next_isha = match self.parameters.isha {
+                TwilightRule::Angle(angle) => self.sun_angle_time(angle, guard(isha, 18.0 - lon_shift), false),
+                TwilightRule::MinutesAfter(_) => next_sunset,
+            };
+            (fajr, sunrise, dhuhr, asr, sunset, maghrib, isha) =
+                (next_fajr, next_sunrise, next_dhuhr, next_asr, next_sunset, next_maghrib, next_isha);
+        }
+
+        if let TwilightRule::MinutesAfter(minutes) = self.parameters.maghrib {
+            maghrib = sunset + minutes / 60.0;
+        }
+        if let TwilightRule::MinutesAfter(minutes) = self.parameters.isha {
+            isha = maghrib + minutes / 60.0;
+        }
+
+        self.adjust_high_latitudes(&mut fajr, &mut isha, &mut maghrib, sunrise, sunset);
+
+        let midnight = match self.parameters.midnight {
+            MidnightMode::Standard => sunset + time_difference(sunset, sunrise) / 2.0,
+            MidnightMode::Jafari => sunset + time_difference(sunset, fajr) / 2.0,
+        };
+
+        DayHours {
+            fajr,
+            sunrise,
+            dhuhr,
+            asr,
+            maghrib,
+            isha,
+            midnight,
+        }
+    }
+
+    fn adjust_high_latitudes(&self, fajr: &mut f64, isha: &mut f64, maghrib: &mut f64, sunrise: f64, sunset: f64) {
+        if self.settings.high_latitude == HighLatitudeRule::None || !sunrise.is_finite() || !sunset.is_finite() {
+            return;
+        }
+        let night = time_difference(sunset, sunrise);
+
+        let portion = |angle: f64| match self.settings.high_latitude {
+            HighLatitudeRule::None => f64::NAN,
+            HighLatitudeRule::MiddleOfNight => night / 2.0,
+            HighLatitudeRule::OneSeventh => night / 7.0,
+            HighLatitudeRule::AngleBased => angle / 60.0 * night,
+        };
+
+        let fajr_portion = portion(self.parameters.fajr_angle);
+        if !fajr.is_finite() || time_difference(*fajr, sunrise) > fajr_portion {
+            *fajr = sunrise - fajr_portion;
+        }
+        if let TwilightRule::Angle(angle) = self.parameters.isha {
+            let isha_portion = portion(angle);
+            if !isha.is_finite() || time_difference(sunset, *isha) > isha_portion {
+                *isha = sunset + isha_portion;
+            }
+        }
+        if let TwilightRule::Angle(angle) = self.parameters.maghrib {
+            let maghrib_portion = portion(angle);
+            if !maghrib.is_finite() || time_difference(sunset, *maghrib) > maghrib_portion {
+                *maghrib = sunset + maghrib_portion;
+            }
+        }
+    }
+}
+
+/// Hours from `from` forward to `to`, wrapping around midnight.
+fn time_difference(from: f64, to: f64) -> f64 {
+    (to - from).rem_euclid(24.0)
+}
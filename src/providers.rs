@@ -0,0 +1,424 @@
+use crate::error::{MecczError, Result};
+use crate::geocoding::{endpoint_source, HttpClient};
+use crate::interfaces::{BoundingBox, GeocodingCandidate, GeocodingService, Location, Place};
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Public Photon instance run by Komoot.
+pub const PHOTON_URL: &str = "https://photon.komoot.io";
+
+/// Service names used in error messages.
+const PHOTON: &str = "Photon";
+const PELIAS: &str = "Pelias";
+
+/// The GeoJSON answers of Photon and Pelias.
+#[derive(Debug, Deserialize)]
+struct FeatureCollection<P> {
+    #[serde(default)]
+    features: Vec<Feature<P>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Feature<P> {
+    geometry: Point,
+    properties: P,
+    bbox: Option<[f64; 4]>, // west, south, east, north
+}
+
+#[derive(Debug, Deserialize)]
+struct Point {
+    coordinates: [f64; 2], // longitude, latitude
+}
+
+impl Point {
+    fn location(&self) -> Location {
+        Location { latitude: self.coordinates[1], longitude: self.coordinates[0] }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PhotonProperties {
+    name: Option<String>,
+    housenumber: Option<String>,
+    street: Option<String>,
+    city: Option<String>,
+    state: Option<String>,
+    country: Option<String>,
+    countrycode: Option<String>,
+    osm_value: Option<String>,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    extent: Option<[f64; 4]>, // west, north, east, south
+}
+
+impl PhotonProperties {
+    /// Name, street, city, state and country, without repeating a part.
+    fn display_name(&self) -> String {
+        let street = match (&self.street, &self.housenumber) {
+            (Some(street), Some(number)) => Some(format!("{} {}", number, street)),
+            (street, _) => street.clone(),
+        };
+        let mut parts: Vec<String> = Vec::new();
+        for part in [self.name.clone(), street, self.city.clone(), self.state.clone(), self.country.clone()].into_iter().flatten() {
+            if !parts.contains(&part) {
+                parts.push(part);
+            }
+        }
+        parts.join(", ")
+    }
+}
+
+/// Geocoder backed by a [Photon](https://github.com/komoot/photon) server, the public
+/// instance unless told otherwise. Photon does not score its results, which come best first.
+pub struct PhotonGeocoder {
+    client: HttpClient,
+    base_url: String, // without a trailing slash
+    source: String, // "photon", with the server unless it is the public one
+}
+
+impl Default for PhotonGeocoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PhotonGeocoder {
+    pub fn new() -> Self {
+        Self {
+            client: HttpClient::new(),
+            base_url: PHOTON_URL.to_string(),
+            source: endpoint_source("photon", PHOTON_URL, Some(PHOTON_URL)),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self.source = endpoint_source("photon", &self.base_url, Some(PHOTON_URL));
+        self
+    }
+
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+#[async_trait]
+impl GeocodingService for PhotonGeocoder {
+    async fn geocode(&self, address: &str) -> Result<Location> {
+        let candidates = self.geocode_candidates(address, 1).await?;
+        Ok(candidates[0].location.clone())
+    }
+
+    async fn geocode_candidates(&self, address: &str, limit: usize) -> Result<Vec<GeocodingCandidate>> {
+        let url = format!("{}/api?q={}&limit={}", self.base_url, urlencoding::encode(address), limit.max(1));
+        let results: FeatureCollection<PhotonProperties> = self.client.get_json(PHOTON, &url).await?;
+        if results.features.is_empty() {
+            return Err(MecczError::LocationNotFound(address.to_string()));
+        }
+
+        Ok(results
+            .features
+            .into_iter()
+            .map(|feature| GeocodingCandidate {
+                location: feature.geometry.location(),
+                display_name: feature.properties.display_name(),
+                kind: feature.properties.osm_value.or(feature.properties.kind).unwrap_or_default(),
+                importance: None,
+                bounding_box: feature.properties.extent.map(|[west, north, east, south]| BoundingBox { south, north, west, east }),
+            })
+            .collect())
+    }
+
+    async fn reverse_geocode(&self, location: &Location) -> Result<Place> {
+        let url = format!("{}/reverse?lat={}&lon={}", self.base_url, location.latitude, location.longitude);
+        let results: FeatureCollection<PhotonProperties> = self.client.get_json(PHOTON, &url).await?;
+        let feature = results.features.into_iter().next().ok_or_else(|| not_found(location))?;
+        let properties = feature.properties;
+        Ok(Place {
+            display_name: properties.display_name(),
+            city: properties.city.or_else(|| properties.name.clone()),
+            country: properties.country,
+            country_code: properties.countrycode.map(|code| code.to_uppercase()),
+        })
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PeliasProperties {
+    label: Option<String>,
+    name: Option<String>,
+    layer: Option<String>,
+    confidence: Option<f64>,
+    locality: Option<String>,
+    localadmin: Option<String>,
+    country: Option<String>,
+    country_code: Option<String>,
+}
+
+/// Geocoder for servers implementing the [Pelias](https://github.com/pelias/documentation)
+/// API, such as a self-hosted Pelias or geocode.earth. Its confidence stands for importance.
+pub struct PeliasGeocoder {
+    client: HttpClient,
+    base_url: String, // without a trailing slash
+    source: String, // "pelias@" and the server
+    api_key: Option<String>,
+}
+
+impl PeliasGeocoder {
+    /// Pelias has no public instance, so the server must be given.
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: HttpClient::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            source: endpoint_source("pelias", base_url, None),
+            api_key: None,
+        }
+    }
+
+    /// Key sent as the `api_key` parameter, as hosted services require.
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn get(&self, path: &str) -> Result<FeatureCollection<PeliasProperties>> {
+        let mut url = format!("{}{}", self.base_url, path);
+        if let Some(api_key) = &self.api_key {
+            url.push_str(&format!("&api_key={}", urlencoding::encode(api_key)));
+        }
+        self.client.get_json(PELIAS, &url).await
+    }
+}
+
+#[async_trait]
+impl GeocodingService for PeliasGeocoder {
+    async fn geocode(&self, address: &str) -> Result<Location> {
+        let candidates = self.geocode_candidates(address, 1).await?;
+        Ok(candidates[0].location.clone())
+    }
+
+    async fn geocode_candidates(&self, address: &str, limit: usize) -> Result<Vec<GeocodingCandidate>> {
+        let results = self.get(&format!("/v1/search?text={}&size={}", urlencoding::encode(address), limit.max(1))).await?;
+        if results.features.is_empty() {
+            return Err(MecczError::LocationNotFound(address.to_string()));
+        }
+
+        Ok(results
+            .features
+            .into_iter()
+            .map(|feature| GeocodingCandidate {
+                location: feature.geometry.location(),
+                display_name: feature.properties.label.or(feature.properties.name).unwrap_or_default(),
+                kind: feature.properties.layer.unwrap_or_default(),
+                importance: feature.properties.confidence,
+                bounding_box: feature.bbox.map(|[west, south, east, north]| BoundingBox { south, north, west, east }),
+            })
+            .collect())
+    }
+
+    async fn reverse_geocode(&self, location: &Location) -> Result<Place> {
+        let path = format!("/v1/reverse?point.lat={}&point.lon={}&size=1", location.latitude, location.longitude);
+        let results = self.get(&path).await?;
+        let properties = results.features.into_iter().next().ok_or_else(|| not_found(location))?.properties;
+        Ok(Place {
+            display_name: properties.label.or_else(|| properties.name.clone()).unwrap_or_default(),
+            city: properties.locality.or(properties.localadmin),
+            country: properties.country,
+            country_code: properties.country_code.map(|code| code.to_uppercase()),
+        })
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+fn not_found(location: &Location) -> MecczError {
+    MecczError::LocationNotFound(format!("{:.5}, {:.5}", location.latitude, location.longitude))
+}
+
+/// Description of a JSON geocoding API for [`TemplateGeocoder`]. URLs may contain
+/// `{query}`, `{limit}`, `{lat}`, `{lon}` and `{api_key}`; fields are located with
+/// JSON pointers (RFC 6901), relative to each result.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateConfig {
+    pub name: String, // in error messages and, with the search URL, as the source of cached results
+    pub search_url: String,
+    #[serde(default)]
+    pub reverse_url: Option<String>, // reverse geocoding is unsupported without it
+    #[serde(default)]
+    pub results: String, // pointer to the array of results, or to the only result; "" is the whole body
+    pub latitude: String,
+    pub longitude: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub importance: Option<String>,
+    #[serde(default)]
+    pub city: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub country_code: Option<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>, // values may contain `{api_key}`
+}
+
+impl TemplateConfig {
+    pub fn from_path(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).with_context(|| format!("Cannot read geocoder config {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("Invalid geocoder config {}", path.display()))
+    }
+}
+
+/// Geocoder for any JSON API, described by a [`TemplateConfig`].
+pub struct TemplateGeocoder {
+    config: TemplateConfig,
+    client: HttpClient,
+    source: String, // the name and the search URL without its query
+    api_key: Option<String>,
+}
+
+impl TemplateGeocoder {
+    pub fn new(config: TemplateConfig) -> Self {
+        let endpoint = config.search_url.split('?').next().unwrap_or_default();
+        let source = endpoint_source(&config.name, endpoint, None);
+        Self { config, client: HttpClient::new(), source, api_key: None }
+    }
+
+    /// Value of `{api_key}` in the URLs and headers.
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// `template` with each `{name}` replaced by its URL-encoded value.
+    fn expand(&self, template: &str, values: &[(&str, String)]) -> String {
+        let mut text = template.replace("{api_key}", &urlencoding::encode(self.api_key.as_deref().unwrap_or_default()));
+        for (name, value) in values {
+            text = text.replace(&format!("{{{}}}", name), &urlencoding::encode(value));
+        }
+        text
+    }
+
+    /// The results of the request to `url`, as JSON values.
+    async fn results(&self, url: &str) -> Result<Vec<Value>> {
+        let mut client = self.client.clone();
+        for (name, value) in &self.config.headers {
+            client = client.with_header(name, &value.replace("{api_key}", self.api_key.as_deref().unwrap_or_default()))?;
+        }
+        let body: Value = client.get_json(&self.config.name, url).await?;
+        match body.pointer(&self.config.results) {
+            Some(Value::Array(results)) => Ok(results.clone()),
+            Some(Value::Null) | None => Ok(Vec::new()),
+            Some(result) => Ok(vec![result.clone()]),
+        }
+    }
+
+    fn number(&self, result: &Value, pointer: &str) -> Result<f64> {
+        let value = result.pointer(pointer);
+        value
+            .and_then(|value| match value {
+                Value::Number(number) => number.as_f64(),
+                Value::String(text) => text.trim().parse().ok(),
+                _ => None,
+            })
+            .ok_or_else(|| MecczError::malformed_response(&self.config.name, format!("no number at {}", pointer)))
+    }
+
+    fn text(result: &Value, pointer: &Option<String>) -> Option<String> {
+        match result.pointer(pointer.as_deref()?)? {
+            Value::String(text) => Some(text.clone()),
+            Value::Null => None,
+            value => Some(value.to_string()),
+        }
+    }
+
+    fn location(&self, result: &Value) -> Result<Location> {
+        Ok(Location {
+            latitude: self.number(result, &self.config.latitude)?,
+            longitude: self.number(result, &self.config.longitude)?,
+        })
+    }
+}
+
+#[async_trait]
+impl GeocodingService for TemplateGeocoder {
+    async fn geocode(&self, address: &str) -> Result<Location> {
+        let candidates = self.geocode_candidates(address, 1).await?;
+        Ok(candidates[0].location.clone())
+    }
+
+    async fn geocode_candidates(&self, address: &str, limit: usize) -> Result<Vec<GeocodingCandidate>> {
+        let limit = limit.max(1);
+        let url = self.expand(&self.config.search_url, &[("query", address.to_string()), ("limit", limit.to_string())]);
+        let results = self.results(&url).await?;
+        if results.is_empty() {
+            return Err(MecczError::LocationNotFound(address.to_string()));
+        }
+
+        results
+            .iter()
+            .take(limit)
+            .map(|result| {
+                Ok(GeocodingCandidate {
+                    location: self.location(result)?,
+                    display_name: Self::text(result, &self.config.display_name).unwrap_or_else(|| address.to_string()),
+                    kind: Self::text(result, &self.config.kind).unwrap_or_else(|| "place".to_string()),
+                    importance: match &self.config.importance {
+                        Some(pointer) => self.number(result, pointer).ok(),
+                        None => None,
+                    },
+                    bounding_box: None,
+                })
+            })
+            .collect()
+    }
+
+    async fn reverse_geocode(&self, location: &Location) -> Result<Place> {
+        let Some(template) = &self.config.reverse_url else {
+            return Err(MecczError::Unsupported {
+                service: self.config.name.clone(),
+                operation: "Reverse geocoding".to_string(),
+            });
+        };
+        let url = self.expand(template, &[("lat", location.latitude.to_string()), ("lon", location.longitude.to_string())]);
+        let results = self.results(&url).await?;
+        let result = results.first().ok_or_else(|| not_found(location))?;
+        Ok(Place {
+            display_name: Self::text(result, &self.config.display_name).ok_or_else(|| not_found(location))?,
+            city: Self::text(result, &self.config.city),
+            country: Self::text(result, &self.config.country),
+            country_code: Self::text(result, &self.config.country_code).map(|code| code.to_uppercase()),
+        })
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+}
@@ -1,14 +1,37 @@
-use crate::interfaces::{CompassEntry, CompassTable, Location, QiblaCalculator, QiblaDirection};
-
-const KAABA_LATITUDE: f64 = 21.4225;
-const KAABA_LONGITUDE: f64 = 39.8262;
+use crate::interfaces::{
+    CompassEntry, CompassTable, Location, NorthReference, QiblaCalculator, QiblaDirection, RhumbLine, Target,
+};
+use crate::compass::CompassRose;
+use crate::solar::bearing_difference;
+
+pub const KAABA_LATITUDE: f64 = 21.4225;
+pub const KAABA_LONGITUDE: f64 = 39.8262;
 const EARTH_RADIUS_KM: f64 = 6371.0;
 
-pub struct GreatCircleCalculator;
+/// Latitudes this close to ±90° (in degrees) are treated as the pole itself.
+const POLE_TOLERANCE_DEGREES: f64 = 1e-9;
+
+pub struct GreatCircleCalculator {
+    target: Target,
+}
+
+impl Default for GreatCircleCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl GreatCircleCalculator {
     pub fn new() -> Self {
-        Self
+        Self {
+            target: Target::kaaba(),
+        }
+    }
+
+    /// Computes bearings to `target` instead of the Kaaba.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
     }
 
     fn to_radians(degrees: f64) -> f64 {
@@ -19,7 +42,7 @@ impl GreatCircleCalculator {
         radians * 180.0 / std::f64::consts::PI
     }
 
-    fn normalize_bearing(bearing: f64) -> f64 {
+    pub(crate) fn normalize_bearing(bearing: f64) -> f64 {
         let mut normalized = bearing % 360.0;
         if normalized < 0.0 {
             normalized += 360.0;
@@ -27,21 +50,12 @@ impl GreatCircleCalculator {
         normalized
     }
 
-    fn bearing_to_direction(bearing: f64) -> String {
-        match bearing {
-            b if b >= 337.5 || b < 22.5 => "N".to_string(),
-            b if b >= 22.5 && b < 67.5 => "NE".to_string(),
-            b if b >= 67.5 && b < 112.5 => "E".to_string(),
-            b if b >= 112.5 && b < 157.5 => "SE".to_string(),
-            b if b >= 157.5 && b < 202.5 => "S".to_string(),
-            b if b >= 202.5 && b < 247.5 => "SW".to_string(),
-            b if b >= 247.5 && b < 292.5 => "W".to_string(),
-            b if b >= 292.5 && b < 337.5 => "NW".to_string(),
-            _ => "N".to_string(),
-        }
+    /// Name of `bearing` on the default rose, the one the compass table is laid out on.
+    pub(crate) fn bearing_to_direction(bearing: f64) -> String {
+        CompassRose::default().name(bearing)
     }
 
-    fn calculate_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    pub(crate) fn calculate_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
         let lat1_rad = Self::to_radians(lat1);
         let lon1_rad = Self::to_radians(lon1);
         let lat2_rad = Self::to_radians(lat2);
@@ -59,12 +73,45 @@ impl GreatCircleCalculator {
 
 }
 
+/// Constant-heading (loxodrome) route from `location` to `destination` on the sphere.
+///
+/// The great circle is the shortest path but its heading changes along the way; the
+/// rhumb line keeps one compass heading, so it is straight on a Mercator map and longer.
+/// Longitudes are crossed the shorter way round, across the antimeridian if needed.
+/// From or to a pole the only constant heading is along the meridian.
+pub fn rhumb_line(location: &Location, destination: &Location) -> RhumbLine {
+    let lat1 = location.latitude.to_radians();
+    let lat2 = destination.latitude.to_radians();
+    let delta_lat = lat2 - lat1;
+    let delta_lon = (destination.longitude - location.longitude + 180.0).rem_euclid(360.0).to_radians() - std::f64::consts::PI;
+
+    let at_pole = |latitude: f64| 90.0 - latitude.abs() < POLE_TOLERANCE_DEGREES;
+    let (bearing, distance) = if at_pole(location.latitude) || at_pole(destination.latitude) {
+        let bearing = if delta_lat >= 0.0 { 0.0 } else { 180.0 };
+        (bearing, delta_lat.abs() * EARTH_RADIUS_KM)
+    } else {
+        // Difference of Mercator latitudes; q is the stretch between map and sphere
+        let mercator = |latitude: f64| (std::f64::consts::FRAC_PI_4 + latitude / 2.0).tan().ln();
+        let delta_psi = mercator(lat2) - mercator(lat1);
+        let q = if delta_psi.abs() > 1e-12 { delta_lat / delta_psi } else { lat1.cos() };
+        let distance = (delta_lat.powi(2) + q.powi(2) * delta_lon.powi(2)).sqrt() * EARTH_RADIUS_KM;
+        (GreatCircleCalculator::to_degrees(delta_lon.atan2(delta_psi)), distance)
+    };
+
+    let bearing = GreatCircleCalculator::normalize_bearing(bearing);
+    RhumbLine {
+        bearing,
+        direction: GreatCircleCalculator::bearing_to_direction(bearing),
+        distance_km: distance,
+    }
+}
+
 impl QiblaCalculator for GreatCircleCalculator {
-    fn calculate_qibla(&self, location: &Location) -> QiblaDirection {
+    fn calculate_direction(&self, location: &Location, destination: &Location) -> QiblaDirection {
         let lat1 = Self::to_radians(location.latitude);
         let lon1 = Self::to_radians(location.longitude);
-        let lat2 = Self::to_radians(KAABA_LATITUDE);
-        let lon2 = Self::to_radians(KAABA_LONGITUDE);
+        let lat2 = Self::to_radians(destination.latitude);
+        let lon2 = Self::to_radians(destination.longitude);
 
         let delta_lon = lon2 - lon1;
 
@@ -77,100 +124,82 @@ impl QiblaCalculator for GreatCircleCalculator {
         let distance = Self::calculate_distance(
             location.latitude,
             location.longitude,
-            KAABA_LATITUDE,
-            KAABA_LONGITUDE,
+            destination.latitude,
+            destination.longitude,
         );
 
         QiblaDirection {
             bearing: normalized_bearing,
             direction: Self::bearing_to_direction(normalized_bearing),
             distance_km: distance,
+            magnetic: None,
+            rhumb_line: None,
         }
     }
 
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
     fn calculate_compass_table(&self, location: &Location) -> CompassTable {
-        let mut entries = Vec::new();
-        let compass_directions = [
-            ("N", 0.0),
-            ("NNE", 22.5),
-            ("NE", 45.0),
-            ("ENE", 67.5),
-            ("E", 90.0),
-            ("ESE", 112.5),
-            ("SE", 135.0),
-            ("SSE", 157.5),
-            ("S", 180.0),
-            ("SSW", 202.5),
-            ("SW", 225.0),
-            ("WSW", 247.5),
-            ("W", 270.0),
-            ("WNW", 292.5),
-            ("NW", 315.0),
-            ("NNW", 337.5),
-        ];
-
-        // Get the actual Qibla direction for this location
-        let qibla = self.calculate_qibla(location);
-
-        let mut min_angular_diff = f64::MAX;
-        let mut optimal_direction_name = String::new();
-
-        // First pass: find the optimal direction
-        for (direction, bearing) in compass_directions.iter() {
-            let mut angular_diff = (bearing - qibla.bearing).abs();
-            if angular_diff > 180.0 {
-                angular_diff = 360.0 - angular_diff;
-            }
-            if angular_diff < min_angular_diff {
-                min_angular_diff = angular_diff;
-                optimal_direction_name = direction.to_string();
-            }
-        }
+        build_compass_table(location, &self.calculate_qibla(location), &CompassRose::default())
+    }
+}
 
-        // Second pass: calculate all entries
-        for (direction, bearing) in compass_directions.iter() {
-            // Calculate the angular difference between this bearing and Qibla
-            let mut angular_diff = (bearing - qibla.bearing).abs();
-            if angular_diff > 180.0 {
-                angular_diff = 360.0 - angular_diff;
+/// Builds the compass table of `rose` around an already computed Qibla direction,
+/// so every `QiblaCalculator` shares the same table layout.
+///
+/// Each heading is followed along its great circle: the closest approach to the target
+/// is the cross-track distance, reached after the along-track distance. Only the
+/// distance and the bearing difference matter, so the geometry holds for magnetic
+/// bearings too; it is spherical even when `qibla` comes from the ellipsoid.
+pub(crate) fn build_compass_table(location: &Location, qibla: &QiblaDirection, rose: &CompassRose) -> CompassTable {
+
+    let circumference = EARTH_RADIUS_KM * 2.0 * std::f64::consts::PI;
+    let direct_arc = qibla.distance_km / EARTH_RADIUS_KM;
+
+    let mut entries: Vec<CompassEntry> = rose
+        .points()
+        .iter()
+        .map(|point| {
+            let bearing = point.bearing;
+            // Positive when the target lies to the right of the heading
+            let offset = bearing_difference(qibla.bearing, bearing).to_radians();
+            let cross_track = (direct_arc.sin() * offset.sin()).clamp(-1.0, 1.0).asin();
+            let along_track = (direct_arc.sin() * offset.cos())
+                .atan2(direct_arc.cos())
+                .rem_euclid(2.0 * std::f64::consts::PI);
+
+            let closest_approach_km = cross_track.abs() * EARTH_RADIUS_KM;
+            let along_track_km = along_track * EARTH_RADIUS_KM;
+            CompassEntry {
+                direction: point.name.clone(),
+                bearing,
+                angular_difference: offset.to_degrees().abs(),
+                short_path_distance_km: along_track_km + closest_approach_km,
+                long_path_distance_km: circumference - along_track_km + closest_approach_km,
+                is_optimal_direction: false,
+                closest_approach_km,
+                along_track_km,
+                cross_track_km: cross_track * EARTH_RADIUS_KM,
             }
+        })
+        .collect();
+
+    let optimal = entries
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.angular_difference.total_cmp(&b.angular_difference))
+        .map(|(index, _)| index);
+    if let Some(index) = optimal {
+        entries[index].is_optimal_direction = true;
+    }
 
-            // Calculate the actual distance if we travel in this direction
-            
-            // If we're going in exactly the right direction, distance = direct distance
-            // If we're going perpendicular, distance = infinite
-            // If we're going opposite, distance = circumference - direct distance
-            
-            let short_distance = if angular_diff < 90.0 {
-                // Going roughly towards Mecca - calculate actual distance via this route
-                qibla.distance_km / (angular_diff * std::f64::consts::PI / 180.0).cos().max(0.001)
-            } else if angular_diff > 90.0 {
-                // Going away from Mecca - would need to go the long way around
-                EARTH_RADIUS_KM * 2.0 * std::f64::consts::PI - qibla.distance_km
-            } else {
-                // Perpendicular - theoretically infinite, but let's say it's the full circumference
-                EARTH_RADIUS_KM * 2.0 * std::f64::consts::PI
-            };
-            
-            let long_distance = EARTH_RADIUS_KM * 2.0 * std::f64::consts::PI - qibla.distance_km;
-
-            let is_optimal = direction == &optimal_direction_name;
-
-            entries.push(CompassEntry {
-                direction: direction.to_string(),
-                bearing: *bearing,
-                angular_difference: angular_diff,
-                short_path_distance_km: short_distance,
-                long_path_distance_km: long_distance,
-                is_optimal_direction: is_optimal,
-            });
-        }
-
-        CompassTable {
-            location: location.clone(),
-            qibla_bearing: qibla.bearing,
-            direct_distance_km: qibla.distance_km,
-            entries,
-        }
+    CompassTable {
+        location: location.clone(),
+        qibla_bearing: qibla.bearing,
+        direct_distance_km: qibla.distance_km,
+        north_reference: NorthReference::True,
+        entries,
     }
-}
\ No newline at end of file
+}
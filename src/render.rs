@@ -0,0 +1,310 @@
+use crate::i18n::Localizer;
+use crate::interfaces::{CompassTable, QiblaDirection, Target};
+use crate::route::xml_escape;
+use std::fmt::Write;
+
+/// Default width of a card, in pixels.
+pub const DEFAULT_CARD_SIZE: u32 = 600;
+
+// Layout in SVG user units; the card is scaled to the requested pixel width
+const WIDTH: f64 = 400.0;
+const HEIGHT: f64 = 520.0;
+const CENTER_X: f64 = 200.0;
+const CENTER_Y: f64 = 250.0;
+const RADIUS: f64 = 150.0;
+const MARGIN: f64 = 12.0;
+
+const QIBLA_COLOR: &str = "#1b7a3d";
+const NORTH_COLOR: &str = "#c62828";
+const MAGNETIC_COLOR: &str = "#1f5fbf";
+
+/// A printable Qibla card: a compass rose with North, the Qibla arrow, the bearing
+/// and the location, optionally with the points of a compass table and magnetic North.
+pub struct QiblaCard {
+    qibla: QiblaDirection,
+    target: Target,
+    table: Option<CompassTable>,
+    label: Option<String>,
+    size: u32,
+    i18n: Localizer,
+}
+
+impl QiblaCard {
+    pub fn new(qibla: QiblaDirection, target: Target) -> Self {
+        Self {
+            qibla,
+            target,
+            table: None,
+            label: None,
+            size: DEFAULT_CARD_SIZE,
+            i18n: Localizer::default(),
+        }
+    }
+
+    /// Draws a spoke for every entry of `table` and highlights the optimal one.
+    pub fn with_table(mut self, table: CompassTable) -> Self {
+        self.table = Some(table);
+        self
+    }
+
+    /// Caption naming the location, shown above the rose.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Width of the card in pixels.
+    pub fn with_size(mut self, size: u32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Language of the captions.
+    pub fn with_localizer(mut self, i18n: Localizer) -> Self {
+        self.i18n = i18n;
+        self
+    }
+
+    /// Height of the card in pixels, for its width.
+    pub fn height(&self) -> u32 {
+        (self.size as f64 * HEIGHT / WIDTH).round() as u32
+    }
+
+    pub fn to_svg(&self) -> String {
+        let text = self.i18n.text();
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"DejaVu Sans, Arial, sans-serif\">",
+            self.size,
+            self.height(),
+            WIDTH,
+            HEIGHT
+        );
+        let _ = writeln!(svg, "  <rect width=\"{}\" height=\"{}\" fill=\"white\"/>", WIDTH, HEIGHT);
+
+        if let Some(label) = &self.label {
+            let _ = writeln!(svg, "  {}", caption(CENTER_X, 42.0, 18.0, "bold", label));
+        }
+
+        self.draw_rose(&mut svg);
+        if let Some(table) = &self.table {
+            self.draw_table(&mut svg, table);
+        }
+        if let Some(magnetic) = &self.qibla.magnetic {
+            // Magnetic North lies `declination` degrees east of true North
+            let _ = writeln!(
+                svg,
+                "  <g transform=\"rotate({:.3} {} {})\" stroke=\"{}\"><line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke-width=\"2\" stroke-dasharray=\"6 4\"/></g>",
+                magnetic.declination,
+                CENTER_X,
+                CENTER_Y,
+                MAGNETIC_COLOR,
+                CENTER_X,
+                CENTER_Y,
+                CENTER_X,
+                CENTER_Y - RADIUS
+            );
+            let (x, y) = polar(magnetic.declination, RADIUS - 36.0);
+            let _ = writeln!(
+                svg,
+                "  <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"11\" fill=\"{}\">MN</text>",
+                x + 5.0,
+                y,
+                MAGNETIC_COLOR
+            );
+        }
+        self.draw_needles(&mut svg);
+
+        let bearing = self.i18n.number(self.qibla.bearing, 1);
+        let heading = if self.target.is_kaaba() {
+            self.i18n.format(text.qibla_direction, &[&bearing])
+        } else {
+            self.i18n.format(text.target_direction, &[&self.target.name, &bearing])
+        };
+        let _ = writeln!(svg, "  {}", caption(CENTER_X, 450.0, 22.0, "bold", &heading));
+        let details = format!(
+            "{} · {}",
+            self.i18n.format(text.direction, &[&self.i18n.direction(&self.qibla.direction)]),
+            self.i18n.format(text.distance, &[&self.i18n.number(self.qibla.distance_km, 0)])
+        );
+        let _ = writeln!(svg, "  {}", caption(CENTER_X, 476.0, 14.0, "normal", &details));
+        if let Some(magnetic) = &self.qibla.magnetic {
+            let side = self.i18n.direction(if magnetic.declination >= 0.0 { "E" } else { "W" });
+            let line = self.i18n.format(
+                text.magnetic_bearing,
+                &[
+                    &self.i18n.number(magnetic.bearing, 1),
+                    &self.i18n.direction(&magnetic.direction),
+                    &self.i18n.number(magnetic.declination.abs(), 1),
+                    &side,
+                    &magnetic.model,
+                    &self.i18n.ungrouped(magnetic.valid_from, 1),
+                    &self.i18n.ungrouped(magnetic.valid_until, 1),
+                ],
+            );
+            let _ = writeln!(svg, "  {}", caption(CENTER_X, 500.0, 11.0, "normal", &line));
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Outer ring, degree ticks every 5°, numbers every 30° and the cardinal points.
+    fn draw_rose(&self, svg: &mut String) {
+        let _ = writeln!(
+            svg,
+            "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"#f7f5ef\" stroke=\"#333\" stroke-width=\"2\"/>",
+            CENTER_X, CENTER_Y, RADIUS
+        );
+        for degrees in (0..360).step_by(5) {
+            let length = match degrees {
+                d if d % 90 == 0 => 16.0,
+                d if d % 30 == 0 => 12.0,
+                d if d % 10 == 0 => 8.0,
+                _ => 4.0,
+            };
+            let (x1, y1) = polar(degrees as f64, RADIUS);
+            let (x2, y2) = polar(degrees as f64, RADIUS - length);
+            let _ = writeln!(
+                svg,
+                "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#333\" stroke-width=\"1\"/>",
+                x1, y1, x2, y2
+            );
+            if degrees % 30 == 0 && degrees % 90 != 0 {
+                let (x, y) = polar(degrees as f64, RADIUS - 24.0);
+                let _ = writeln!(
+                    svg,
+                    "  <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\" fill=\"#555\" text-anchor=\"middle\">{}</text>",
+                    x,
+                    y + 3.5,
+                    self.i18n.number(degrees as f64, 0)
+                );
+            }
+        }
+        // East and West are anchored on the ring so that long names grow outwards
+        for (degrees, point, anchor) in [(0.0, "N", "middle"), (90.0, "E", "start"), (180.0, "S", "middle"), (270.0, "W", "end")] {
+            let (x, y) = polar(degrees, RADIUS + if anchor == "middle" { 16.0 } else { 6.0 });
+            let color = if point == "N" { NORTH_COLOR } else { "#333" };
+            let _ = writeln!(
+                svg,
+                "  <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"18\" font-weight=\"bold\" fill=\"{}\" text-anchor=\"{}\">{}</text>",
+                x,
+                y + 6.0,
+                color,
+                anchor,
+                xml_escape(&self.i18n.direction(point))
+            );
+        }
+    }
+
+    /// One spoke per table entry, the optimal one in the Qibla color.
+    fn draw_table(&self, svg: &mut String, table: &CompassTable) {
+        for entry in &table.entries {
+            let (x, y) = polar(entry.bearing, RADIUS - 30.0);
+            let (color, width) = if entry.is_optimal_direction { (QIBLA_COLOR, 2.0) } else { ("#bbb", 1.0) };
+            let _ = writeln!(
+                svg,
+                "  <line x1=\"{}\" y1=\"{}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"{}\"/>",
+                CENTER_X, CENTER_Y, x, y, color, width
+            );
+            let (x, y) = polar(entry.bearing, RADIUS - 40.0);
+            let _ = writeln!(
+                svg,
+                "  <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"8\" fill=\"{}\" text-anchor=\"middle\">{}</text>",
+                x,
+                y + 3.0,
+                color,
+                xml_escape(&self.i18n.direction(&entry.direction))
+            );
+        }
+    }
+
+    /// The north needle and the Qibla arrow with the Kaaba at its tip.
+    fn draw_needles(&self, svg: &mut String) {
+        let _ = writeln!(
+            svg,
+            "  <polygon points=\"{},{} {},{} {},{}\" fill=\"{}\"/>",
+            CENTER_X - 6.0,
+            CENTER_Y,
+            CENTER_X,
+            CENTER_Y - 90.0,
+            CENTER_X + 6.0,
+            CENTER_Y,
+            NORTH_COLOR
+        );
+        let _ = writeln!(
+            svg,
+            "  <polygon points=\"{},{} {},{} {},{}\" fill=\"#999\"/>",
+            CENTER_X - 6.0,
+            CENTER_Y,
+            CENTER_X,
+            CENTER_Y + 90.0,
+            CENTER_X + 6.0,
+            CENTER_Y
+        );
+
+        let tip = CENTER_Y - RADIUS + 40.0;
+        let _ = writeln!(svg, "  <g transform=\"rotate({:.3} {} {})\" fill=\"{}\" stroke=\"{}\">", self.qibla.bearing, CENTER_X, CENTER_Y, QIBLA_COLOR, QIBLA_COLOR);
+        let _ = writeln!(svg, "    <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke-width=\"5\" stroke-linecap=\"round\"/>", CENTER_X, CENTER_Y, CENTER_X, tip + 14.0);
+        let _ = writeln!(
+            svg,
+            "    <polygon points=\"{},{} {},{} {},{}\" stroke=\"none\"/>",
+            CENTER_X - 11.0,
+            tip + 18.0,
+            CENTER_X,
+            tip,
+            CENTER_X + 11.0,
+            tip + 18.0
+        );
+        let _ = writeln!(
+            svg,
+            "    <rect x=\"{}\" y=\"{}\" width=\"12\" height=\"12\" fill=\"#111\" stroke=\"#d4af37\" stroke-width=\"1.5\"/>",
+            CENTER_X - 6.0,
+            tip - 16.0
+        );
+        svg.push_str("  </g>\n");
+        let _ = writeln!(svg, "  <circle cx=\"{}\" cy=\"{}\" r=\"5\" fill=\"#333\"/>", CENTER_X, CENTER_Y);
+    }
+
+    /// The card rasterized with resvg, as PNG bytes. Text needs the fonts of the system.
+    #[cfg(feature = "png")]
+    pub fn to_png(&self) -> anyhow::Result<Vec<u8>> {
+        use resvg::{tiny_skia, usvg};
+
+        let mut options = usvg::Options::default();
+        options.fontdb_mut().load_system_fonts();
+        let tree = usvg::Tree::from_str(&self.to_svg(), &options)?;
+        let mut pixmap = tiny_skia::Pixmap::new(self.size, self.height())
+            .ok_or_else(|| anyhow::anyhow!("Cannot allocate a {}x{} image", self.size, self.height()))?;
+        let scale = self.size as f32 / tree.size().width();
+        resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+        Ok(pixmap.encode_png()?)
+    }
+}
+
+/// Point at `radius` from the center in the direction of `bearing`, in SVG coordinates.
+fn polar(bearing: f64, radius: f64) -> (f64, f64) {
+    let angle = bearing.to_radians();
+    (CENTER_X + radius * angle.sin(), CENTER_Y - radius * angle.cos())
+}
+
+/// Centered text, squeezed to the width of the card when it would not fit.
+fn caption(x: f64, y: f64, size: f64, weight: &str, text: &str) -> String {
+    // Rough width of a sans-serif glyph, enough to tell when a line overflows
+    let estimated_width = text.chars().count() as f64 * size * 0.55;
+    let fit = if estimated_width > WIDTH - 2.0 * MARGIN {
+        format!(" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\"", WIDTH - 2.0 * MARGIN)
+    } else {
+        String::new()
+    };
+    format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-weight=\"{}\" fill=\"#222\" text-anchor=\"middle\"{}>{}</text>",
+        x,
+        y,
+        size,
+        weight,
+        fit,
+        xml_escape(text)
+    )
+}
@@ -0,0 +1,257 @@
+use crate::error::MecczError;
+use crate::interfaces::Location;
+use crate::qibla::GreatCircleCalculator;
+use serde::Serialize;
+use std::str::FromStr;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Default distance between waypoints when neither a count nor a spacing is given.
+pub const DEFAULT_SPACING_KM: f64 = 100.0;
+
+/// Points closer than this (in radians of arc) to each other or to antipodes are
+/// joined along the initial bearing, where the intermediate-point formula breaks down.
+const DEGENERATE_ARC: f64 = 1e-9;
+
+/// How densely a great-circle path is sampled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Density {
+    Waypoints(usize), // total number of points, both ends included (at least 2)
+    SpacingKm(f64),   // largest distance between consecutive points
+}
+
+impl Default for Density {
+    fn default() -> Self {
+        Density::SpacingKm(DEFAULT_SPACING_KM)
+    }
+}
+
+impl Density {
+    /// Number of equal segments a path of `distance_km` is divided into.
+    fn segments(self, distance_km: f64) -> usize {
+        match self {
+            Density::Waypoints(count) => count.max(2) - 1,
+            Density::SpacingKm(spacing) if spacing > 0.0 => (distance_km / spacing).ceil().max(1.0) as usize,
+            Density::SpacingKm(_) => 1,
+        }
+    }
+}
+
+/// The great-circle path between two locations, sampled at evenly spaced waypoints.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GreatCirclePath {
+    pub distance_km: f64,
+    pub waypoints: Vec<Location>, // from the start to the destination, longitudes in [-180, 180]
+}
+
+impl GreatCirclePath {
+    /// Samples the shortest path from `from` to `to` with the intermediate-point formula.
+    pub fn new(from: &Location, to: &Location, density: Density) -> Self {
+        let distance_km = GreatCircleCalculator::calculate_distance(from.latitude, from.longitude, to.latitude, to.longitude);
+        let segments = density.segments(distance_km);
+        // The ends are copied rather than computed, so they match the input exactly
+        let waypoints = (0..=segments)
+            .map(|step| match step {
+                0 => from.clone(),
+                step if step == segments => to.clone(),
+                step => intermediate_point(from, to, step as f64 / segments as f64),
+            })
+            .collect();
+        Self { distance_km, waypoints }
+    }
+
+    /// The waypoints split where the path crosses the antimeridian, each part ending
+    /// (or starting) exactly on ±180° so that maps do not draw a line across the world.
+    pub fn segments(&self) -> Vec<Vec<Location>> {
+        let mut parts = vec![Vec::new()];
+        for (index, point) in self.waypoints.iter().enumerate() {
+            if let Some(previous) = index.checked_sub(1).map(|i| &self.waypoints[i])
+                && (point.longitude - previous.longitude).abs() > 180.0
+            {
+                let edge = if previous.longitude > 0.0 { 180.0 } else { -180.0 };
+                let latitude = antimeridian_latitude(previous, point);
+                if let Some(part) = parts.last_mut() {
+                    part.push(Location { latitude, longitude: edge });
+                }
+                parts.push(vec![Location { latitude, longitude: -edge }]);
+            }
+            if let Some(part) = parts.last_mut() {
+                part.push(point.clone());
+            }
+        }
+        parts
+    }
+}
+
+/// The point a `fraction` of the way along the great circle from `from` to `to`.
+pub fn intermediate_point(from: &Location, to: &Location, fraction: f64) -> Location {
+    let (lat1, lon1) = (from.latitude.to_radians(), from.longitude.to_radians());
+    let (lat2, lon2) = (to.latitude.to_radians(), to.longitude.to_radians());
+    let arc = GreatCircleCalculator::calculate_distance(from.latitude, from.longitude, to.latitude, to.longitude)
+        / EARTH_RADIUS_KM;
+
+    if arc.sin().abs() < DEGENERATE_ARC {
+        return destination_point(from, initial_bearing(from, to), fraction * arc);
+    }
+
+    let a = ((1.0 - fraction) * arc).sin() / arc.sin();
+    let b = (fraction * arc).sin() / arc.sin();
+    let x = a * lat1.cos() * lon1.cos() + b * lat2.cos() * lon2.cos();
+    let y = a * lat1.cos() * lon1.sin() + b * lat2.cos() * lon2.sin();
+    let z = a * lat1.sin() + b * lat2.sin();
+
+    Location {
+        latitude: z.atan2(x.hypot(y)).to_degrees(),
+        longitude: normalize_longitude(y.atan2(x).to_degrees()),
+    }
+}
+
+/// The point reached after `arc` radians along `bearing` (degrees) from `from`.
+fn destination_point(from: &Location, bearing: f64, arc: f64) -> Location {
+    let lat1 = from.latitude.to_radians();
+    let bearing = bearing.to_radians();
+    let lat2 = (lat1.sin() * arc.cos() + lat1.cos() * arc.sin() * bearing.cos()).asin();
+    let delta_lon = (bearing.sin() * arc.sin() * lat1.cos()).atan2(arc.cos() - lat1.sin() * lat2.sin());
+    Location {
+        latitude: lat2.to_degrees(),
+        longitude: normalize_longitude(from.longitude + delta_lon.to_degrees()),
+    }
+}
+
+fn initial_bearing(from: &Location, to: &Location) -> f64 {
+    let (lat1, lat2) = (from.latitude.to_radians(), to.latitude.to_radians());
+    let delta_lon = (to.longitude - from.longitude).to_radians();
+    let y = delta_lon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * delta_lon.cos();
+    y.atan2(x).to_degrees()
+}
+
+/// Latitude where the great circle through `a` and `b` crosses the antimeridian.
+fn antimeridian_latitude(a: &Location, b: &Location) -> f64 {
+    let (lat1, lon1) = (a.latitude.to_radians(), a.longitude.to_radians());
+    let (lat2, lon2) = (b.latitude.to_radians(), b.longitude.to_radians());
+    let crossing = std::f64::consts::PI;
+    let denominator = lat1.cos() * lat2.cos() * (lon1 - lon2).sin();
+    if denominator.abs() < DEGENERATE_ARC {
+        // Over a pole the path follows a meridian; split halfway between the points
+        return (a.latitude + b.latitude) / 2.0;
+    }
+    let numerator = lat1.sin() * lat2.cos() * (crossing - lon2).sin() - lat2.sin() * lat1.cos() * (crossing - lon1).sin();
+    (numerator / denominator).atan().to_degrees()
+}
+
+fn normalize_longitude(longitude: f64) -> f64 {
+    let normalized = (longitude + 180.0).rem_euclid(360.0) - 180.0;
+    if normalized == -180.0 && longitude > 0.0 { 180.0 } else { normalized }
+}
+
+/// File formats a path can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathFormat {
+    GeoJson,
+    Kml,
+    Gpx,
+}
+
+impl PathFormat {
+    /// Renders `path` as a document named `name`.
+    pub fn render(self, path: &GreatCirclePath, name: &str) -> String {
+        match self {
+            PathFormat::GeoJson => to_geojson(path, name),
+            PathFormat::Kml => to_kml(path, name),
+            PathFormat::Gpx => to_gpx(path, name),
+        }
+    }
+}
+
+impl FromStr for PathFormat {
+    type Err = MecczError;
+
+    fn from_str(input: &str) -> Result<Self, MecczError> {
+        match input.trim().to_lowercase().as_str() {
+            "geojson" | "json" => Ok(PathFormat::GeoJson),
+            "kml" => Ok(PathFormat::Kml),
+            "gpx" => Ok(PathFormat::Gpx),
+            _ => Err(MecczError::parse("export format", format!("'{}' (expected geojson, kml or gpx)", input.trim()))),
+        }
+    }
+}
+
+/// A GeoJSON `Feature` holding a `LineString`, or a `MultiLineString` when the path
+/// crosses the antimeridian (RFC 7946, section 3.1.9).
+pub fn to_geojson(path: &GreatCirclePath, name: &str) -> String {
+    let line = |points: &[Location]| -> Vec<[f64; 2]> {
+        points.iter().map(|point| [round(point.longitude), round(point.latitude)]).collect()
+    };
+    let segments = path.segments();
+    let geometry = match segments.as_slice() {
+        [single] => serde_json::json!({ "type": "LineString", "coordinates": line(single) }),
+        parts => serde_json::json!({
+            "type": "MultiLineString",
+            "coordinates": parts.iter().map(|part| line(part)).collect::<Vec<_>>(),
+        }),
+    };
+    let feature = serde_json::json!({
+        "type": "Feature",
+        "properties": { "name": name, "distance_km": path.distance_km },
+        "geometry": geometry,
+    });
+    format!("{}\n", serde_json::to_string_pretty(&feature).unwrap_or_default())
+}
+
+/// A KML document with one placemark; a path crossing the antimeridian becomes a
+/// `MultiGeometry` of line strings.
+pub fn to_kml(path: &GreatCirclePath, name: &str) -> String {
+    let line_string = |points: &[Location], indent: &str| {
+        let coordinates: Vec<String> = points
+            .iter()
+            .map(|point| format!("{},{}", round(point.longitude), round(point.latitude)))
+            .collect();
+        format!(
+            "{indent}<LineString>\n{indent}  <tessellate>1</tessellate>\n{indent}  <coordinates>{}</coordinates>\n{indent}</LineString>\n",
+            coordinates.join(" ")
+        )
+    };
+    let segments = path.segments();
+    let geometry = match segments.as_slice() {
+        [single] => line_string(single, "      "),
+        parts => {
+            let lines: String = parts.iter().map(|part| line_string(part, "        ")).collect();
+            format!("      <MultiGeometry>\n{}      </MultiGeometry>\n", lines)
+        }
+    };
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n  <Document>\n    <name>{name}</name>\n    <Placemark>\n      <name>{name}</name>\n{geometry}    </Placemark>\n  </Document>\n</kml>\n",
+        name = xml_escape(name),
+    )
+}
+
+/// A GPX 1.1 route through every waypoint. Routes are point lists, so they need no
+/// splitting at the antimeridian.
+pub fn to_gpx(path: &GreatCirclePath, name: &str) -> String {
+    let points: String = path
+        .waypoints
+        .iter()
+        .map(|point| format!("    <rtept lat=\"{}\" lon=\"{}\"/>\n", round(point.latitude), round(point.longitude)))
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <gpx version=\"1.1\" creator=\"meccz\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n  <rte>\n    <name>{}</name>\n{}  </rte>\n</gpx>\n",
+        xml_escape(name),
+        points
+    )
+}
+
+/// Coordinates are written with 6 decimals, about 0.1 m.
+fn round(degrees: f64) -> f64 {
+    (degrees * 1e6).round() / 1e6
+}
+
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
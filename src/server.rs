@@ -0,0 +1,200 @@
+use crate::core::MeccaApp;
+use crate::error::MecczError;
+use crate::interfaces::{CompassTable, GeocodingCandidate, GeocodingService, Location, QiblaCalculator, QiblaDirection};
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+use std::sync::Arc;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// OpenAPI description of the endpoints, served at `/openapi.json`.
+const OPENAPI_JSON: &str = include_str!("../data/openapi.json");
+
+/// Places returned by `/geocode` unless `limit` says otherwise, and the most it may ask.
+const DEFAULT_GEOCODE_LIMIT: usize = 5;
+const MAX_GEOCODE_LIMIT: usize = 50;
+
+/// Browser origins allowed to call the API.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum CorsOrigins {
+    #[default]
+    Any,
+    List(Vec<String>),
+}
+
+/// The HTTP API: `/qibla`, `/compass-table`, `/geocode`, `/health` and `/openapi.json`.
+pub fn router<G, Q>(app: Arc<MeccaApp<G, Q>>, origins: &CorsOrigins) -> Router
+where
+    G: GeocodingService + 'static,
+    Q: QiblaCalculator + Send + Sync + 'static,
+{
+    let allow_origin = match origins {
+        CorsOrigins::Any => AllowOrigin::any(),
+        CorsOrigins::List(list) => AllowOrigin::list(list.iter().filter_map(|origin| HeaderValue::from_str(origin).ok())),
+    };
+    let cors = CorsLayer::new().allow_origin(allow_origin).allow_methods([axum::http::Method::GET]);
+
+    Router::new()
+        .route("/qibla", get(qibla::<G, Q>))
+        .route("/compass-table", get(compass_table::<G, Q>))
+        .route("/geocode", get(geocode::<G, Q>))
+        .route("/health", get(health))
+        .route("/openapi.json", get(openapi))
+        .fallback(not_found)
+        .layer(cors)
+        .with_state(app)
+}
+
+/// Where a request is about: `lat` and `lon`, or an address or coordinates in `q`.
+/// Parameters are read as text so that bad values get a structured error.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LocationQuery {
+    pub lat: Option<String>,
+    pub lon: Option<String>,
+    pub q: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GeocodeQuery {
+    pub q: Option<String>,
+    pub limit: Option<String>,
+}
+
+async fn qibla<G, Q>(
+    State(app): State<Arc<MeccaApp<G, Q>>>,
+    query: Result<Query<LocationQuery>, QueryRejection>,
+) -> Result<Json<QiblaDirection>, ApiError>
+where
+    G: GeocodingService,
+    Q: QiblaCalculator,
+{
+    let location = resolve(&app, &parameters(query)?).await?;
+    Ok(Json(app.get_qibla(&location)))
+}
+
+async fn compass_table<G, Q>(
+    State(app): State<Arc<MeccaApp<G, Q>>>,
+    query: Result<Query<LocationQuery>, QueryRejection>,
+) -> Result<Json<CompassTable>, ApiError>
+where
+    G: GeocodingService,
+    Q: QiblaCalculator,
+{
+    let location = resolve(&app, &parameters(query)?).await?;
+    Ok(Json(app.get_compass_table(&location)))
+}
+
+async fn geocode<G, Q>(
+    State(app): State<Arc<MeccaApp<G, Q>>>,
+    query: Result<Query<GeocodeQuery>, QueryRejection>,
+) -> Result<Json<Vec<GeocodingCandidate>>, ApiError>
+where
+    G: GeocodingService,
+    Q: QiblaCalculator,
+{
+    let query = parameters(query)?;
+    let address = required(query.q.as_deref(), "q")?;
+    let limit = match query.limit.as_deref() {
+        Some(limit) => match limit.trim().parse::<usize>() {
+            Ok(limit) if (1..=MAX_GEOCODE_LIMIT).contains(&limit) => limit,
+            _ => return Err(MecczError::parse("limit", format!("'{}' (expected 1 to {})", limit, MAX_GEOCODE_LIMIT)).into()),
+        },
+        None => DEFAULT_GEOCODE_LIMIT,
+    };
+    Ok(Json(app.get_location_candidates(address, limit).await?))
+}
+
+async fn health() -> Response {
+    Json(serde_json::json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") })).into_response()
+}
+
+async fn openapi() -> Response {
+    ([(header::CONTENT_TYPE, "application/json")], OPENAPI_JSON).into_response()
+}
+
+async fn not_found() -> Response {
+    let body = serde_json::json!({ "error": "No such endpoint; see /openapi.json", "code": "not_found" });
+    (StatusCode::NOT_FOUND, Json(body)).into_response()
+}
+
+/// The location of a request, from `lat` and `lon` or from `q`, but not both.
+pub async fn resolve<G, Q>(app: &MeccaApp<G, Q>, query: &LocationQuery) -> Result<Location, MecczError>
+where
+    G: GeocodingService,
+    Q: QiblaCalculator,
+{
+    match (&query.lat, &query.lon, &query.q) {
+        (None, None, Some(q)) => app.get_location(required(Some(q), "q")?).await,
+        (Some(lat), Some(lon), None) => {
+            let latitude = number(lat, "latitude")?;
+            let longitude = number(lon, "longitude")?;
+            // The coordinate parser checks the ranges
+            app.get_location(&format!("{},{}", latitude, longitude)).await
+        }
+        (_, _, Some(_)) => Err(MecczError::parse("query", "give either q or lat and lon, not both")),
+        (Some(_), None, None) => Err(MecczError::parse("lon", "missing")),
+        (None, Some(_), None) => Err(MecczError::parse("lat", "missing")),
+        (None, None, None) => Err(MecczError::parse("query", "expected lat and lon, or q")),
+    }
+}
+
+/// Query parameters, or a structured error when they cannot be decoded.
+fn parameters<T>(query: Result<Query<T>, QueryRejection>) -> Result<T, MecczError> {
+    query.map(|Query(query)| query).map_err(|rejection| MecczError::parse("query", rejection.body_text()))
+}
+
+fn required<'a>(value: Option<&'a str>, field: &str) -> Result<&'a str, MecczError> {
+    match value.map(str::trim) {
+        Some(value) if !value.is_empty() => Ok(value),
+        _ => Err(MecczError::parse(field, "missing")),
+    }
+}
+
+fn number(value: &str, field: &str) -> Result<f64, MecczError> {
+    match value.trim().parse::<f64>() {
+        Ok(number) if number.is_finite() => Ok(number),
+        _ => Err(MecczError::parse(field, format!("'{}' is not a number", value.trim()))),
+    }
+}
+
+/// A [`MecczError`] as a response: the JSON error object of `meccz --json` with the
+/// matching HTTP status.
+pub struct ApiError(pub MecczError);
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match &self.0 {
+            MecczError::Parse { .. } | MecczError::OutOfRange { .. } => StatusCode::BAD_REQUEST,
+            MecczError::LocationNotFound(_) => StatusCode::NOT_FOUND,
+            MecczError::Unsupported { .. } => StatusCode::NOT_IMPLEMENTED,
+            MecczError::Transport { .. } | MecczError::MalformedResponse { .. } => StatusCode::BAD_GATEWAY,
+            MecczError::RateLimited { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+impl From<MecczError> for ApiError {
+    fn from(error: MecczError) -> Self {
+        ApiError(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut body = serde_json::json!({ "error": self.0.to_string(), "code": self.0.code() });
+        if let Some(field) = self.0.field() {
+            body["field"] = field.into();
+        }
+        let mut response = (self.status(), Json(body)).into_response();
+        if let MecczError::RateLimited { retry_after: Some(delay), .. } = &self.0
+            && let Ok(value) = HeaderValue::from_str(&delay.as_secs().max(1).to_string())
+        {
+            response.headers_mut().insert(header::RETRY_AFTER, value);
+        }
+        response
+    }
+}
@@ -0,0 +1,213 @@
+use crate::interfaces::{Location, QiblaCalculator};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Sun altitude below which it is considered set (refraction and semi-diameter).
+pub const SUNRISE_ALTITUDE: f64 = -0.833;
+
+/// Step used to bracket azimuth crossings before refining them by bisection.
+const SEARCH_STEP_MINUTES: i64 = 2;
+
+/// Position of the sun for an observer, following the NOAA solar calculator
+/// (Meeus' low-precision algorithms, accurate to about 0.01° for 1800-2100).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SolarPosition {
+    pub azimuth: f64, // degrees from North (0-360)
+    pub altitude: f64, // apparent elevation above the horizon, refraction corrected
+    pub declination: f64, // degrees
+    pub equation_of_time: f64, // minutes
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SunAlignment {
+    /// The sun stands in the Qibla direction: face the sun.
+    TowardsQibla,
+    /// The sun stands opposite the Qibla: turn your back to it, your shadow points to the Qibla.
+    AwayFromQibla,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SunQiblaEvent {
+    pub alignment: SunAlignment,
+    pub time: DateTime<FixedOffset>,
+    pub azimuth: f64,
+    pub altitude: f64,
+    pub visible: bool, // true if the sun is above the horizon at that moment
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SunQiblaTimes {
+    pub location: Location,
+    pub date: NaiveDate,
+    pub qibla_bearing: f64,
+    pub events: Vec<SunQiblaEvent>,
+}
+
+/// Julian centuries since J2000.0 for a UTC instant.
+pub(crate) fn julian_century(instant: DateTime<Utc>) -> f64 {
+    let julian_day = instant.timestamp() as f64 / 86400.0
+        + instant.timestamp_subsec_nanos() as f64 / 86400.0e9
+        + 2440587.5;
+    (julian_day - 2451545.0) / 36525.0
+}
+
+/// Solar declination and equation of time (minutes) at a given Julian century.
+pub(crate) fn declination_and_equation_of_time(t: f64) -> (f64, f64) {
+    let mean_longitude = (280.46646 + t * (36000.76983 + 0.0003032 * t)).rem_euclid(360.0);
+    let mean_anomaly = 357.52911 + t * (35999.05029 - 0.0001537 * t);
+    let eccentricity = 0.016708634 - t * (0.000042037 + 0.0000001267 * t);
+
+    let m = mean_anomaly.to_radians();
+    let equation_of_center = m.sin() * (1.914602 - t * (0.004817 + 0.000014 * t))
+        + (2.0 * m).sin() * (0.019993 - 0.000101 * t)
+        + (3.0 * m).sin() * 0.000289;
+    let true_longitude = mean_longitude + equation_of_center;
+
+    let omega = (125.04 - 1934.136 * t).to_radians();
+    let apparent_longitude = (true_longitude - 0.00569 - 0.00478 * omega.sin()).to_radians();
+
+    let mean_obliquity = 23.0 + (26.0 + (21.448 - t * (46.815 + t * (0.00059 - t * 0.001813))) / 60.0) / 60.0;
+    let obliquity = (mean_obliquity + 0.00256 * omega.cos()).to_radians();
+
+    let declination = (obliquity.sin() * apparent_longitude.sin()).asin().to_degrees();
+
+    let y = (obliquity / 2.0).tan().powi(2);
+    let l0 = mean_longitude.to_radians();
+    let equation_of_time = 4.0
+        * (y * (2.0 * l0).sin() - 2.0 * eccentricity * m.sin()
+            + 4.0 * eccentricity * y * m.sin() * (2.0 * l0).cos()
+            - 0.5 * y * y * (4.0 * l0).sin()
+            - 1.25 * eccentricity * eccentricity * (2.0 * m).sin())
+        .to_degrees();
+
+    (declination, equation_of_time)
+}
+
+/// Approximate atmospheric refraction in degrees for a geometric elevation (NOAA).
+fn refraction(elevation: f64) -> f64 {
+    if elevation > 85.0 {
+        return 0.0;
+    }
+    let te = elevation.to_radians().tan();
+    let arcseconds = if elevation > 5.0 {
+        58.1 / te - 0.07 / te.powi(3) + 0.000086 / te.powi(5)
+    } else if elevation > -0.575 {
+        1735.0 + elevation * (-518.2 + elevation * (103.4 + elevation * (-12.79 + elevation * 0.711)))
+    } else {
+        -20.772 / te
+    };
+    arcseconds / 3600.0
+}
+
+/// Computes the sun's azimuth and apparent altitude seen from `location` at `instant`.
+pub fn solar_position(location: &Location, instant: DateTime<Utc>) -> SolarPosition {
+    let (declination, equation_of_time) = declination_and_equation_of_time(julian_century(instant));
+
+    let minutes = instant.num_seconds_from_midnight() as f64 / 60.0
+        + instant.timestamp_subsec_nanos() as f64 / 60.0e9;
+    let true_solar_time = (minutes + equation_of_time + 4.0 * location.longitude).rem_euclid(1440.0);
+    let hour_angle = (true_solar_time / 4.0 - 180.0).to_radians();
+
+    let latitude = location.latitude.to_radians();
+    let delta = declination.to_radians();
+
+    let cos_zenith = (latitude.sin() * delta.sin() + latitude.cos() * delta.cos() * hour_angle.cos()).clamp(-1.0, 1.0);
+    let elevation = 90.0 - cos_zenith.acos().to_degrees();
+
+    let azimuth = hour_angle
+        .sin()
+        .atan2(hour_angle.cos() * latitude.sin() - delta.tan() * latitude.cos())
+        .to_degrees()
+        + 180.0;
+
+    SolarPosition {
+        azimuth: azimuth.rem_euclid(360.0),
+        altitude: elevation + refraction(elevation),
+        declination,
+        equation_of_time,
+    }
+}
+
+/// Signed difference `a - b` between two bearings, in (-180, 180].
+pub fn bearing_difference(a: f64, b: f64) -> f64 {
+    let difference = (a - b).rem_euclid(360.0);
+    if difference > 180.0 { difference - 360.0 } else { difference }
+}
+
+/// Finds the instants within the local day `date` when the sun's azimuth equals
+/// the Qibla bearing returned by `calculator`, or its reciprocal.
+pub fn sun_qibla_times<Q: QiblaCalculator + ?Sized>(
+    calculator: &Q,
+    location: &Location,
+    date: NaiveDate,
+    offset: FixedOffset,
+) -> SunQiblaTimes {
+    let qibla = calculator.calculate_qibla(location);
+    let start = offset
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0).expect("midnight is valid"))
+        .single()
+        .expect("fixed offsets are unambiguous")
+        .with_timezone(&Utc);
+
+    let mut events = Vec::new();
+    for (alignment, target) in [
+        (SunAlignment::TowardsQibla, qibla.bearing),
+        (SunAlignment::AwayFromQibla, (qibla.bearing + 180.0).rem_euclid(360.0)),
+    ] {
+        for instant in azimuth_crossings(location, start, target) {
+            let position = solar_position(location, instant);
+            events.push(SunQiblaEvent {
+                alignment,
+                time: instant.with_timezone(&offset),
+                azimuth: position.azimuth,
+                altitude: position.altitude,
+                visible: position.altitude > SUNRISE_ALTITUDE,
+            });
+        }
+    }
+    events.sort_by_key(|event| event.time);
+
+    SunQiblaTimes {
+        location: location.clone(),
+        date,
+        qibla_bearing: qibla.bearing,
+        events,
+    }
+}
+
+/// Instants during the 24 hours after `start` when the solar azimuth crosses `target`.
+fn azimuth_crossings(location: &Location, start: DateTime<Utc>, target: f64) -> Vec<DateTime<Utc>> {
+    let offset_at = |instant: DateTime<Utc>| bearing_difference(solar_position(location, instant).azimuth, target);
+
+    let mut crossings = Vec::new();
+    let mut previous = start;
+    let mut previous_offset = offset_at(previous);
+    for step in 1..=(24 * 60 / SEARCH_STEP_MINUTES) {
+        let current = start + Duration::minutes(step * SEARCH_STEP_MINUTES);
+        let current_offset = offset_at(current);
+
+        // A sign change across ±180° is the reciprocal bearing, not a crossing
+        if previous_offset.signum() != current_offset.signum()
+            && (previous_offset - current_offset).abs() < 180.0
+        {
+            let (mut low, mut high) = (previous, current);
+            let low_sign = previous_offset.signum();
+            while high - low > Duration::milliseconds(500) {
+                let middle = low + (high - low) / 2;
+                if offset_at(middle).signum() == low_sign {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            let middle = low + (high - low) / 2;
+            let rounded = DateTime::from_timestamp((middle.timestamp_millis() as f64 / 1000.0).round() as i64, 0);
+            crossings.push(rounded.unwrap_or(middle));
+        }
+
+        previous = current;
+        previous_offset = current_offset;
+    }
+    crossings
+}
@@ -0,0 +1,112 @@
+use crate::coordinates::CoordinateFormats;
+use crate::error::{MecczError, Result};
+use crate::interfaces::{Location, Target};
+use crate::qibla::{KAABA_LATITUDE, KAABA_LONGITUDE};
+
+/// Identifier of the default target.
+pub const KAABA_TARGET_ID: &str = "kaaba";
+
+/// Identifier of targets given as coordinates rather than by name.
+pub const CUSTOM_TARGET_ID: &str = "custom";
+
+impl Target {
+    pub fn new(id: &str, name: &str, latitude: f64, longitude: f64) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            location: Location { latitude, longitude },
+        }
+    }
+
+    /// The Kaaba in Mecca, the default target.
+    pub fn kaaba() -> Self {
+        Self::new(KAABA_TARGET_ID, "Kaaba, Mecca", KAABA_LATITUDE, KAABA_LONGITUDE)
+    }
+
+    pub fn is_kaaba(&self) -> bool {
+        self.id == KAABA_TARGET_ID
+    }
+
+    /// Short destination name for headings: "Mecca" for the Kaaba, the full name otherwise.
+    pub fn label(&self) -> &str {
+        if self.is_kaaba() { "Mecca" } else { &self.name }
+    }
+}
+
+/// Named targets, looked up by identifier or alias, case-insensitively.
+pub struct Targets {
+    targets: Vec<Target>,
+    aliases: Vec<(String, String)>, // alias -> target identifier
+}
+
+impl Targets {
+    /// An empty registry, to be filled with [`Targets::register`].
+    pub fn empty() -> Self {
+        Self {
+            targets: Vec::new(),
+            aliases: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, target: Target) {
+        self.targets.retain(|existing| existing.id != target.id);
+        self.targets.push(target);
+    }
+
+    /// Makes `alias` another name for the target `id`.
+    pub fn alias(&mut self, alias: &str, id: &str) {
+        self.aliases.push((alias.to_lowercase(), id.to_string()));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Target> {
+        let name = name.trim().to_lowercase();
+        let id = self
+            .aliases
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map_or(name.as_str(), |(_, id)| id.as_str());
+        self.targets.iter().find(|target| target.id.eq_ignore_ascii_case(id))
+    }
+
+    pub fn all(&self) -> &[Target] {
+        &self.targets
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.targets.iter().map(|target| target.id.as_str()).collect()
+    }
+
+    /// A registered target, or a custom one when `input` is written in one of `formats`.
+    pub fn resolve(&self, input: &str, formats: &CoordinateFormats) -> Result<Target> {
+        if let Some(target) = self.get(input) {
+            return Ok(target.clone());
+        }
+        match formats.parse(input) {
+            Some(location) => Ok(Target {
+                id: CUSTOM_TARGET_ID.to_string(),
+                name: input.trim().to_string(),
+                location: location?,
+            }),
+            None => Err(MecczError::parse(
+                "target",
+                format!("'{}' is neither coordinates nor one of {}", input.trim(), self.names().join(", ")),
+            )),
+        }
+    }
+}
+
+impl Default for Targets {
+    /// The Kaaba, the Prophet's Mosque, Al-Aqsa (the first qibla) and the Temple Mount.
+    fn default() -> Self {
+        let mut targets = Self::empty();
+        targets.register(Target::kaaba());
+        targets.register(Target::new("medina", "Prophet's Mosque, Medina", 24.4672, 39.6112));
+        targets.register(Target::new("aqsa", "Al-Aqsa Mosque, Jerusalem", 31.7761, 35.2358));
+        targets.register(Target::new("jerusalem", "Temple Mount, Jerusalem", 31.7780, 35.2354));
+        targets.alias("mecca", "kaaba");
+        targets.alias("makkah", "kaaba");
+        targets.alias("madinah", "medina");
+        targets.alias("al-aqsa", "aqsa");
+        targets
+    }
+}
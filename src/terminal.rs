@@ -0,0 +1,289 @@
+use crate::i18n::Localizer;
+use crate::interfaces::QiblaDirection;
+
+/// Narrowest and widest dial, in terminal columns.
+const MIN_DIAL_COLUMNS: usize = 16;
+const MAX_DIAL_COLUMNS: usize = 40;
+
+/// Number of columns `text` takes on screen: directional marks and control
+/// characters take none.
+pub fn display_width(text: &str) -> usize {
+    text.chars().filter(|c| !c.is_control() && !is_bidi_mark(*c)).count()
+}
+
+fn is_bidi_mark(c: char) -> bool {
+    matches!(c, '\u{200E}' | '\u{200F}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Alignment of a [`TextTable`] column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// A table whose columns are as wide as their widest cell, measured in screen columns
+/// so that translated headers, degree signs and localized digits stay aligned.
+#[derive(Debug, Clone)]
+pub struct TextTable {
+    columns: Vec<(String, Align)>,
+    rows: Vec<Vec<String>>,
+}
+
+impl TextTable {
+    pub fn new<S: Into<String>>(columns: impl IntoIterator<Item = (S, Align)>) -> Self {
+        Self {
+            columns: columns.into_iter().map(|(header, align)| (header.into(), align)).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Adds a row; missing cells are left empty and extra cells ignored.
+    pub fn push_row(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    /// The header, a rule as wide as the table, then the rows. Trailing spaces are trimmed.
+    pub fn lines(&self) -> Vec<String> {
+        let widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, (header, _))| {
+                self.rows
+                    .iter()
+                    .filter_map(|row| row.get(index))
+                    .map(|cell| display_width(cell))
+                    .chain([display_width(header)])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let format_row = |cells: Vec<&str>| {
+            let mut line = String::new();
+            for (index, ((_, align), width)) in self.columns.iter().zip(&widths).enumerate() {
+                let cell = cells.get(index).copied().unwrap_or_default();
+                let padding = " ".repeat(width.saturating_sub(display_width(cell)));
+                if index > 0 {
+                    line.push_str("  ");
+                }
+                match align {
+                    Align::Left => {
+                        line.push_str(cell);
+                        line.push_str(&padding);
+                    }
+                    Align::Right => {
+                        line.push_str(&padding);
+                        line.push_str(cell);
+                    }
+                }
+            }
+            line.trim_end().to_string()
+        };
+
+        let header = format_row(self.columns.iter().map(|(header, _)| header.as_str()).collect());
+        let rule_width = widths.iter().sum::<usize>() + 2 * widths.len().saturating_sub(1);
+        let mut lines = vec![header, "-".repeat(rule_width)];
+        lines.extend(self.rows.iter().map(|row| format_row(row.iter().map(String::as_str).collect())));
+        lines
+    }
+}
+
+/// What a dot of the dial belongs to; later kinds are drawn over earlier ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Ink {
+    Rim,
+    Magnetic,
+    North,
+    Qibla,
+}
+
+impl Ink {
+    fn ansi(self) -> &'static str {
+        match self {
+            Ink::Rim => "\x1b[2m",
+            Ink::Magnetic => "\x1b[34m",
+            Ink::North => "\x1b[1;31m",
+            Ink::Qibla => "\x1b[1;32m",
+        }
+    }
+}
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// A grid of braille characters, each holding 2x4 dots. Terminal cells are about
+/// twice as tall as wide, so the dots are evenly spaced in both directions.
+struct BrailleCanvas {
+    columns: usize,
+    rows: usize,
+    cells: Vec<(u8, Option<Ink>)>,
+}
+
+impl BrailleCanvas {
+    fn new(columns: usize, rows: usize) -> Self {
+        Self { columns, rows, cells: vec![(0, None); columns * rows] }
+    }
+
+    /// Sets the dot nearest to (`x`, `y`), in dots from the top left corner.
+    fn dot(&mut self, x: f64, y: f64, ink: Ink) {
+        let (x, y) = (x.round(), y.round());
+        if x < 0.0 || y < 0.0 {
+            return;
+        }
+        let (x, y) = (x as usize, y as usize);
+        if x >= self.columns * 2 || y >= self.rows * 4 {
+            return;
+        }
+        // Unicode braille numbers the dots down the left column, then the right one,
+        // with the bottom row added last
+        const BITS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+        let cell = &mut self.cells[(y / 4) * self.columns + x / 2];
+        cell.0 |= BITS[x % 2][y % 4];
+        cell.1 = cell.1.max(Some(ink));
+    }
+
+    /// A straight line, drawn every `step` dots.
+    fn line(&mut self, from: (f64, f64), to: (f64, f64), step: usize, ink: Ink) {
+        let samples = (to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil().max(1.0) as usize;
+        for sample in (0..=samples).step_by(step.max(1)) {
+            let t = sample as f64 / samples as f64;
+            self.dot(from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t, ink);
+        }
+    }
+
+    /// Row `row` as (character, ink) pairs.
+    fn row(&self, row: usize) -> impl Iterator<Item = (char, Option<Ink>)> + '_ {
+        self.cells[row * self.columns..(row + 1) * self.columns].iter().map(|&(bits, ink)| match bits {
+            0 => (' ', None),
+            bits => (char::from_u32(0x2800 + bits as u32).unwrap_or(' '), ink),
+        })
+    }
+}
+
+/// A compass dial drawn with braille characters: the rim with a tick every 30°, a red
+/// North needle, the Qibla arrow and, when known, a dotted magnetic North line.
+pub struct TerminalCompass {
+    bearing: f64,
+    direction: String,
+    declination: Option<f64>,
+    width: usize,
+    color: bool,
+    i18n: Localizer,
+}
+
+impl TerminalCompass {
+    pub fn new(qibla: &QiblaDirection) -> Self {
+        Self {
+            bearing: qibla.bearing,
+            direction: qibla.direction.clone(),
+            declination: qibla.magnetic.as_ref().map(|magnetic| magnetic.declination),
+            width: 80,
+            color: false,
+            i18n: Localizer::default(),
+        }
+    }
+
+    /// Columns available on the terminal; the dial is sized and centered to fit.
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Colors the needles with ANSI escape sequences.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Language of the cardinal points and the caption.
+    pub fn with_localizer(mut self, i18n: Localizer) -> Self {
+        self.i18n = i18n;
+        self
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let [north, east, south, west] = ["N", "E", "S", "W"].map(|point| self.i18n.direction(point));
+        let (west_width, east_width) = (display_width(&west) + 1, display_width(&east) + 1);
+
+        // An even number of columns keeps the dial as tall as it is wide
+        let room = self.width.saturating_sub(west_width + east_width);
+        let columns = room.clamp(MIN_DIAL_COLUMNS, MAX_DIAL_COLUMNS) & !1;
+        let rows = columns / 2;
+        let canvas = self.draw(columns, rows);
+
+        let total_width = west_width + columns + east_width;
+        let indent = " ".repeat(self.width.saturating_sub(total_width) / 2);
+        let centered = |text: &str, ink: Option<Ink>| {
+            let offset = (total_width.saturating_sub(display_width(text))) / 2;
+            format!("{}{}{}", indent, " ".repeat(offset), self.paint(text, ink))
+        };
+        let middle_row = (rows * 2 - 1) / 4; // the row holding the center of the dial
+
+        let mut lines = vec![centered(&north, Some(Ink::North))];
+        for row in 0..rows {
+            let mut line = indent.clone();
+            if row == middle_row {
+                line.push_str(&west);
+                line.push(' ');
+            } else {
+                line.push_str(&" ".repeat(west_width));
+            }
+            let cells: Vec<(char, Option<Ink>)> = canvas.row(row).collect();
+            for run in cells.chunk_by(|a, b| a.1 == b.1) {
+                let text: String = run.iter().map(|(c, _)| c).collect();
+                line.push_str(&self.paint(&text, run[0].1));
+            }
+            if row == middle_row {
+                line.push(' ');
+                line.push_str(&east);
+            }
+            lines.push(line.trim_end().to_string());
+        }
+        lines.push(centered(&south, None));
+
+        let caption = format!("{}° {}", self.i18n.number(self.bearing, 1), self.i18n.direction(&self.direction));
+        lines.push(centered(&caption, Some(Ink::Qibla)));
+        lines
+    }
+
+    fn draw(&self, columns: usize, rows: usize) -> BrailleCanvas {
+        let mut canvas = BrailleCanvas::new(columns, rows);
+        // The dial spans dots 0 to 2 * (columns - 1) both ways, in a grid of 2 * columns
+        let radius = columns as f64 - 1.0;
+        let center = (radius, radius);
+        let point = |bearing: f64, fraction: f64| {
+            let angle = bearing.to_radians();
+            (center.0 + radius * fraction * angle.sin(), center.1 - radius * fraction * angle.cos())
+        };
+
+        let steps = (radius * 8.0) as usize;
+        for step in 0..steps {
+            let (x, y) = point(step as f64 * 360.0 / steps as f64, 1.0);
+            canvas.dot(x, y, Ink::Rim);
+        }
+        for degrees in (0..360).step_by(30) {
+            let inner = if degrees % 90 == 0 { 0.8 } else { 0.9 };
+            canvas.line(point(degrees as f64, inner), point(degrees as f64, 1.0), 1, Ink::Rim);
+        }
+        if let Some(declination) = self.declination {
+            canvas.line(center, point(declination, 0.75), 2, Ink::Magnetic);
+        }
+        canvas.line(center, point(0.0, 0.55), 1, Ink::North);
+
+        let tip = point(self.bearing, 0.9);
+        canvas.line(center, tip, 1, Ink::Qibla);
+        for side in [-1.0, 1.0] {
+            canvas.line(tip, point(self.bearing + side * 10.0, 0.72), 1, Ink::Qibla);
+        }
+        canvas.dot(center.0, center.1, Ink::Qibla);
+        canvas
+    }
+
+    fn paint(&self, text: &str, ink: Option<Ink>) -> String {
+        match ink {
+            Some(ink) if self.color => format!("{}{}{}", ink.ansi(), text, ANSI_RESET),
+            _ => text.to_string(),
+        }
+    }
+}
@@ -33,6 +33,189 @@ mod geocoding_tests {
         assert!(parse_coordinates("0.0, 181.0").is_err()); // longitude > 180
         assert!(parse_coordinates("0.0, -181.0").is_err()); // longitude < -180
     }
+
+    fn assert_location(input: &str, latitude: f64, longitude: f64) {
+        let result = parse_coordinates(input).unwrap_or_else(|e| panic!("{}: {}", input, e));
+        assert!((result.latitude - latitude).abs() < 0.0001, "{}: latitude {}", input, result.latitude);
+        assert!((result.longitude - longitude).abs() < 0.0001, "{}: longitude {}", input, result.longitude);
+    }
+
+    #[test]
+    fn test_parse_coordinates_dms() {
+        let latitude = 48.0 + 51.0 / 60.0 + 24.0 / 3600.0;
+        let longitude = 2.0 + 21.0 / 60.0 + 8.0 / 3600.0;
+        assert_location("48°51'24\"N 2°21'08\"E", latitude, longitude);
+        assert_location("48°51′24″N, 2°21′08″E", latitude, longitude);
+        assert_location("48 51 24 N 2 21 8 E", latitude, longitude);
+        assert_location("48:51:24N 2:21:08E", latitude, longitude);
+        assert_location("33°52'S 151°12'E", -(33.0 + 52.0 / 60.0), 151.2);
+    }
+
+    #[test]
+    fn test_parse_coordinates_decimal_minutes() {
+        assert_location("N 48° 51.4' E 2° 21.1'", 48.0 + 51.4 / 60.0, 2.0 + 21.1 / 60.0);
+        assert_location("40 42.768 N 74 0.36 W", 40.7128, -74.006);
+    }
+
+    #[test]
+    fn test_parse_coordinates_hemisphere_letters() {
+        assert_location("48.8566N 2.3522E", 48.8566, 2.3522);
+        assert_location("S33.8688 E151.2093", -33.8688, 151.2093);
+        assert_location("40.7128 n, 74.0060 w", 40.7128, -74.006);
+        // Hemisphere letters decide the order
+        assert_location("2.3522E 48.8566N", 48.8566, 2.3522);
+        assert_location("W 74.0060 N 40.7128", 40.7128, -74.006);
+    }
+
+    #[test]
+    fn test_parse_coordinates_separators_and_decimal_commas() {
+        assert_location("48.8566 2.3522", 48.8566, 2.3522);
+        assert_location("48.8566; 2.3522", 48.8566, 2.3522);
+        assert_location("48,8566 2,3522", 48.8566, 2.3522);
+        assert_location("48,8566; 2,3522", 48.8566, 2.3522);
+        assert_location("48,8566, 2,3522", 48.8566, 2.3522);
+        assert_location("-33.8688 −151.2093", -33.8688, -151.2093);
+    }
+
+    #[test]
+    fn test_parse_coordinates_component_errors() {
+        let error = parse_coordinates("48°61'N 2°21'E").unwrap_err().to_string();
+        assert!(error.contains("latitude") && error.contains("minutes"), "{}", error);
+
+        let error = parse_coordinates("48°51'N 2°21'75\"E").unwrap_err().to_string();
+        assert!(error.contains("longitude") && error.contains("seconds"), "{}", error);
+
+        let error = parse_coordinates("48.8566N 2.3522S").unwrap_err().to_string();
+        assert!(error.contains("latitude"), "{}", error);
+
+        let error = parse_coordinates("-48.8566N 2.3522E").unwrap_err().to_string();
+        assert!(error.contains("minus sign"), "{}", error);
+
+        let error = parse_coordinates("95°N 2°E").unwrap_err().to_string();
+        assert!(error.contains("Latitude must be between"), "{}", error);
+    }
+
+    #[test]
+    fn test_nominatim_reverse_response() {
+        use crate::geocoding::NominatimReverseResponse;
+        let location = crate::Location { latitude: 48.8566, longitude: 2.3522 };
+
+        let response: NominatimReverseResponse = serde_json::from_str(r#"{
+            "display_name": "Paris, Île-de-France, France métropolitaine, France",
+            "address": {"city": "Paris", "state": "Île-de-France", "country": "France", "country_code": "fr"}
+        }"#).unwrap();
+        let place = response.into_place(&location).unwrap();
+        assert_eq!(place.city.as_deref(), Some("Paris"));
+        assert_eq!(place.country.as_deref(), Some("France"));
+        assert_eq!(place.country_code.as_deref(), Some("FR"));
+
+        let village: NominatimReverseResponse = serde_json::from_str(
+            r#"{"display_name": "Giverny, Eure", "address": {"village": "Giverny"}}"#,
+        ).unwrap();
+        assert_eq!(village.into_place(&location).unwrap().city.as_deref(), Some("Giverny"));
+
+        let ocean: NominatimReverseResponse = serde_json::from_str(r#"{"error": "Unable to geocode"}"#).unwrap();
+        assert!(ocean.into_place(&location).is_err());
+    }
+
+    #[test]
+    fn test_nominatim_candidates() {
+        use crate::geocoding::{candidates_are_ambiguous, NominatimResponse};
+
+        let responses: Vec<NominatimResponse> = serde_json::from_str(r#"[
+            {"lat": "48.8588897", "lon": "2.3200410", "display_name": "Paris, Île-de-France, France", "type": "administrative",
+             "importance": 0.88, "boundingbox": ["48.8155755", "48.9021560", "2.2241220", "2.4697602"]},
+            {"lat": "33.6617962", "lon": "-95.5555130", "display_name": "Paris, Lamar County, Texas, United States", "type": "city",
+             "importance": 0.56}
+        ]"#).unwrap();
+        let candidates: Vec<_> = responses.into_iter().map(|response| response.into_candidate().unwrap()).collect();
+        assert_eq!(candidates[0].kind, "administrative");
+        let bounds = candidates[0].bounding_box.as_ref().unwrap();
+        assert!((bounds.south - 48.8155755).abs() < 1e-9 && (bounds.east - 2.4697602).abs() < 1e-9);
+        assert!(candidates[1].bounding_box.is_none());
+        assert!(!candidates_are_ambiguous(&candidates));
+        assert!(!candidates_are_ambiguous(&candidates[..1]));
+
+        let mut close = candidates.clone();
+        close[1].importance = Some(0.7);
+        assert!(candidates_are_ambiguous(&close));
+        close[1].importance = None;
+        assert!(candidates_are_ambiguous(&close));
+    }
+
+    #[test]
+    fn test_parse_coordinates_rejects_place_names() {
+        assert!(parse_coordinates("Paris, France").is_err());
+        assert!(parse_coordinates("10 Downing Street, London").is_err());
+        assert!(parse_coordinates("New York").is_err());
+        assert!(parse_coordinates("Station 48 2").is_err());
+    }
+}
+
+#[cfg(test)]
+mod error_tests {
+    use crate::geocoding::{parse_coordinates, NominatimResponse};
+    use crate::MecczError;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_errors_name_the_field() {
+        let error = parse_coordinates("48°61'N 2°21'E").unwrap_err();
+        assert_eq!(error.code(), "parse_error");
+        assert_eq!(error.field(), Some("latitude"));
+        assert!(matches!(&error, MecczError::Parse { reason, .. } if reason.contains("minutes")));
+
+        let error = parse_coordinates("Paris, France").unwrap_err();
+        assert_eq!(error.field(), Some("coordinates"));
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let error = parse_coordinates("45.0, 190.0").unwrap_err();
+        assert_eq!(
+            error,
+            MecczError::OutOfRange { field: "longitude".to_string(), value: 190.0, min: -180.0, max: 180.0 }
+        );
+        assert_eq!(error.to_string(), "Longitude must be between -180 and 180 degrees (got 190)");
+        assert_eq!(error.code(), "out_of_range");
+    }
+
+    #[test]
+    fn test_malformed_response() {
+        let response: NominatimResponse =
+            serde_json::from_str(r#"{"lat": "north", "lon": "2.35", "display_name": "Paris"}"#).unwrap();
+        let error = response.into_candidate().unwrap_err();
+        assert_eq!(error.code(), "malformed_response");
+        assert!(error.to_string().contains("'north'"), "{}", error);
+    }
+
+    #[test]
+    fn test_codes_and_exit_codes_are_distinct() {
+        let errors = [
+            MecczError::parse("latitude", "missing value"),
+            MecczError::OutOfRange { field: "latitude".to_string(), value: 91.0, min: -90.0, max: 90.0 },
+            MecczError::LocationNotFound("Atlantis".to_string()),
+            MecczError::Unsupported { service: "test".to_string(), operation: "Reverse geocoding".to_string() },
+            MecczError::Transport { service: "Nominatim".to_string(), status: Some(503), reason: "HTTP 503".to_string() },
+            MecczError::RateLimited { service: "Nominatim".to_string(), retry_after: Some(Duration::from_secs(30)) },
+            MecczError::malformed_response("Nominatim", "expected value"),
+        ];
+        let mut codes: Vec<&str> = errors.iter().map(MecczError::code).collect();
+        let mut exit_codes: Vec<i32> = errors.iter().map(MecczError::exit_code).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        exit_codes.sort_unstable();
+        exit_codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+        assert_eq!(exit_codes.len(), errors.len());
+        assert!(exit_codes.iter().all(|&code| code > 2));
+
+        assert_eq!(errors[5].to_string(), "Nominatim is rate limiting requests, retry after 30 s");
+        assert!(errors[4].is_transient() && errors[5].is_transient() && !errors[2].is_transient());
+        let not_found = MecczError::Transport { service: "Nominatim".to_string(), status: Some(404), reason: "HTTP 404".to_string() };
+        let timeout = MecczError::Transport { service: "Nominatim".to_string(), status: None, reason: "no response".to_string() };
+        assert!(!not_found.is_transient() && timeout.is_transient());
+    }
 }
 
 #[cfg(test)]
@@ -64,7 +247,7 @@ mod qibla_tests {
         
         // Expected values for Paris (approximate)
         assert!((result.bearing - 119.0).abs() < 5.0); // Should be around 119°
-        assert!(result.direction == "SE");
+        assert!(result.direction == "ESE");
         assert!((result.distance_km - 4500.0).abs() < 500.0); // Should be around 4500 km
     }
 
@@ -79,7 +262,7 @@ mod qibla_tests {
         
         // Expected values for New York (approximate)
         assert!((result.bearing - 58.0).abs() < 5.0); // Should be around 58°
-        assert!(result.direction == "NE");
+        assert!(result.direction == "ENE");
         assert!((result.distance_km - 10300.0).abs() < 500.0); // Should be around 10300 km
     }
 
@@ -94,7 +277,7 @@ mod qibla_tests {
         
         // Expected values for Guam (approximate)
         assert!((result.bearing - 294.0).abs() < 5.0); // Should be around 294°
-        assert!(result.direction == "NW");
+        assert!(result.direction == "WNW");
         assert!((result.distance_km - 11000.0).abs() < 500.0); // Should be around 11000 km
     }
 
@@ -128,6 +311,59 @@ mod qibla_tests {
         }
     }
 
+    #[test]
+    fn test_rhumb_line_across_antimeridian() {
+        use crate::qibla::rhumb_line;
+
+        let fiji = Location { latitude: -17.0, longitude: 178.0 };
+        let samoa = Location { latitude: -17.0, longitude: -172.0 };
+        let route = rhumb_line(&fiji, &samoa);
+        assert!((route.bearing - 90.0).abs() < 1e-9);
+        let expected = 10.0_f64.to_radians() * 17.0_f64.to_radians().cos() * 6371.0;
+        assert!((route.distance_km - expected).abs() < 1e-6);
+        assert!((rhumb_line(&samoa, &fiji).bearing - 270.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rhumb_line_at_poles() {
+        use crate::qibla::rhumb_line;
+
+        let kaaba = Location { latitude: 21.4225, longitude: 39.8262 };
+        let north_pole = Location { latitude: 90.0, longitude: 0.0 };
+        let from_pole = rhumb_line(&north_pole, &kaaba);
+        assert_eq!(from_pole.bearing, 180.0);
+        assert!((from_pole.distance_km - (90.0 - 21.4225_f64).to_radians() * 6371.0).abs() < 1e-6);
+
+        let to_pole = rhumb_line(&kaaba, &Location { latitude: -90.0, longitude: 120.0 });
+        assert_eq!(to_pole.bearing, 180.0);
+        assert!(to_pole.distance_km.is_finite());
+
+        let near_pole = rhumb_line(&Location { latitude: 89.999, longitude: -100.0 }, &kaaba);
+        assert!(near_pole.bearing.is_finite() && near_pole.distance_km.is_finite());
+    }
+
+    #[test]
+    fn test_rhumb_line_is_never_shorter() {
+        use crate::qibla::rhumb_line;
+
+        let calculator = GreatCircleCalculator::new();
+        let kaaba = Location { latitude: 21.4225, longitude: 39.8262 };
+        for location in [
+            Location { latitude: 48.8566, longitude: 2.3522 },
+            Location { latitude: 40.7128, longitude: -74.0060 },
+            Location { latitude: -33.87, longitude: 151.21 },
+            Location { latitude: 64.1, longitude: -21.9 },
+        ] {
+            let great_circle = calculator.calculate_qibla(&location);
+            let rhumb = rhumb_line(&location, &kaaba);
+            assert!(rhumb.distance_km >= great_circle.distance_km - 1e-6);
+        }
+
+        // Due south the two coincide
+        let due_south = rhumb_line(&Location { latitude: 50.0, longitude: 39.8262 }, &kaaba);
+        assert_eq!(due_south.bearing, 180.0);
+    }
+
     #[test]
     fn test_compass_table() {
         let calculator = GreatCircleCalculator::new();
@@ -171,16 +407,16 @@ mod integration_tests {
     use crate::qibla::GreatCircleCalculator;
 
     // Mock geocoding service for testing
-    struct MockGeocoder;
+    pub(super) struct MockGeocoder;
     
     #[async_trait::async_trait]
     impl GeocodingService for MockGeocoder {
-        async fn geocode(&self, address: &str) -> anyhow::Result<Location> {
+        async fn geocode(&self, address: &str) -> Result<Location, MecczError> {
             match address.to_lowercase().as_str() {
                 "paris" => Ok(Location { latitude: 48.8566, longitude: 2.3522 }),
                 "new york" => Ok(Location { latitude: 40.7128, longitude: -74.0060 }),
                 "tokyo" => Ok(Location { latitude: 35.6762, longitude: 139.6503 }),
-                _ => Err(anyhow::anyhow!("Location not found: {}", address)),
+                _ => Err(MecczError::LocationNotFound(address.to_string())),
             }
         }
     }
@@ -193,7 +429,7 @@ mod integration_tests {
 
         let result = app.run("48.8566,2.3522").await.unwrap();
         assert!((result.bearing - 119.0).abs() < 5.0);
-        assert!(result.direction == "SE");
+        assert!(result.direction == "ESE");
         assert!((result.distance_km - 4500.0).abs() < 500.0);
     }
 
@@ -205,7 +441,7 @@ mod integration_tests {
 
         let result = app.run("paris").await.unwrap();
         assert!((result.bearing - 119.0).abs() < 5.0);
-        assert!(result.direction == "SE");
+        assert!(result.direction == "ESE");
     }
 
     #[tokio::test]
@@ -218,6 +454,36 @@ mod integration_tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_reverse_geocoding_defaults_to_unsupported() {
+        let app = MeccaApp::new(MockGeocoder, GreatCircleCalculator::new());
+        let error = app.describe_location(&Location { latitude: 48.8566, longitude: 2.3522 }).await.unwrap_err();
+        assert!(error.to_string().contains("not supported"));
+    }
+
+    #[tokio::test]
+    async fn test_get_location_reports_malformed_coordinates() {
+        let app = MeccaApp::new(MockGeocoder, GreatCircleCalculator::new());
+
+        let error = app.get_location("48°75'N 2°21'E").await.unwrap_err().to_string();
+        assert!(error.contains("latitude") && error.contains("minutes"), "{}", error);
+        assert!(!error.contains("Location not found"));
+    }
+
+    #[tokio::test]
+    async fn test_get_location_candidates() {
+        let app = MeccaApp::new(MockGeocoder, GreatCircleCalculator::new());
+
+        let candidates = app.get_location_candidates("paris", 5).await.unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert!((candidates[0].location.latitude - 48.8566).abs() < 1e-9);
+
+        let coordinates = app.get_location_candidates(" 21.4225, 39.8262 ", 5).await.unwrap();
+        assert_eq!(coordinates.len(), 1);
+        assert_eq!(coordinates[0].kind, "coordinates");
+        assert_eq!(coordinates[0].display_name, "21.4225, 39.8262");
+    }
+
     #[test]
     fn test_get_location_coordinates() {
         let geocoder = MockGeocoder;
@@ -241,7 +507,7 @@ mod integration_tests {
         let result = app.get_qibla(&location);
         
         assert!((result.bearing - 58.0).abs() < 5.0);
-        assert!(result.direction == "NE");
+        assert!(result.direction == "ENE");
     }
 
     #[test]
@@ -265,7 +531,7 @@ mod integration_tests {
 #[cfg(test)]
 mod mathematical_tests {
     use super::*;
-    use crate::qibla::GreatCircleCalculator;
+    use crate::qibla::{build_compass_table, GreatCircleCalculator};
 
     #[test]
     fn test_distance_calculation_accuracy() {
@@ -326,11 +592,1997 @@ mod mathematical_tests {
         let min_diff = table.entries.iter().map(|e| e.angular_difference).fold(f64::INFINITY, f64::min);
         assert!((optimal_entry.angular_difference - min_diff).abs() < 0.01);
         
-        // Long path distances should be consistent (all approximately circumference - direct distance)
-        let expected_long_distance = 2.0 * std::f64::consts::PI * 6371.0 - table.direct_distance_km;
+        // Each heading's great circle, followed both ways, adds up to a full turn plus two misses
+        let circumference = 2.0 * std::f64::consts::PI * 6371.0;
+        for entry in &table.entries {
+            let total = entry.short_path_distance_km + entry.long_path_distance_km;
+            assert!((total - circumference - 2.0 * entry.closest_approach_km).abs() < 1e-6);
+            assert!(entry.short_path_distance_km >= table.direct_distance_km - 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_compass_table_geometry() {
+        let calculator = GreatCircleCalculator::new();
+        let paris = Location { latitude: 48.8566, longitude: 2.3522 };
+        let qibla = calculator.calculate_qibla(&paris);
+        let table = calculator.calculate_compass_table(&paris);
+
         for entry in &table.entries {
-            // Allow some variation due to calculation method
-            assert!((entry.long_path_distance_km - expected_long_distance).abs() < 1000.0);
+            // Follow the heading to its closest approach and measure the miss directly
+            let arc = entry.along_track_km / 6371.0;
+            let (lat1, heading) = (paris.latitude.to_radians(), entry.bearing.to_radians());
+            let lat2 = (lat1.sin() * arc.cos() + lat1.cos() * arc.sin() * heading.cos()).asin();
+            let delta_lon = (heading.sin() * arc.sin() * lat1.cos()).atan2(arc.cos() - lat1.sin() * lat2.sin());
+            let point = Location { latitude: lat2.to_degrees(), longitude: paris.longitude + delta_lon.to_degrees() };
+            let miss = calculator.calculate_qibla(&point).distance_km;
+            assert!((miss - entry.closest_approach_km).abs() < 1e-3, "{}: {} vs {}", entry.direction, miss, entry.closest_approach_km);
+            assert!(entry.closest_approach_km <= qibla.distance_km + 1e-6);
+        }
+
+        // ESE passes north of Mecca, which is then on the right; SE passes south of it
+        let ese = table.entries.iter().find(|e| e.direction == "ESE").unwrap();
+        let se = table.entries.iter().find(|e| e.direction == "SE").unwrap();
+        assert!(ese.cross_track_km > 0.0 && se.cross_track_km < 0.0);
+        assert!(ese.along_track_km < qibla.distance_km);
+
+        // Heading away, the closest approach comes after most of a turn around the Earth
+        let wnw = table.entries.iter().find(|e| e.direction == "WNW").unwrap();
+        assert!((wnw.closest_approach_km - ese.closest_approach_km).abs() < 1e-6);
+        assert!(wnw.along_track_km > 30000.0);
+
+        // A heading straight at the target hits it
+        let aligned = build_compass_table(&paris, &QiblaDirection { bearing: 135.0, ..qibla.clone() }, &crate::compass::CompassRose::default());
+        let se = aligned.entries.iter().find(|e| e.direction == "SE").unwrap();
+        assert!(se.closest_approach_km < 1e-9);
+        assert!((se.short_path_distance_km - qibla.distance_km).abs() < 1e-6);
+    }
+}
+#[cfg(test)]
+mod geodesic_tests {
+    use super::*;
+    use crate::geodesic::EllipsoidalCalculator;
+
+    #[test]
+    fn test_quarter_meridian_distance() {
+        // Equator-to-pole meridian arc on WGS-84 is 10001.965729 km
+        let calculator = EllipsoidalCalculator::new();
+        let (distance, _) = calculator_inverse(&calculator, 0.0, 0.0, 90.0, 0.0);
+        assert!((distance - 10001.965729).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_quarter_equator_distance() {
+        // Along the equator the geodesic is a circle of radius a = 6378.137 km
+        let calculator = EllipsoidalCalculator::new();
+        let (distance, bearing) = calculator_inverse(&calculator, 0.0, 0.0, 0.0, 90.0);
+        assert!((distance - 6378.137 * std::f64::consts::FRAC_PI_2).abs() < 0.001);
+        assert!((bearing - 90.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ellipsoid_close_to_sphere() {
+        let sphere = GreatCircleCalculator::new();
+        let ellipsoid = EllipsoidalCalculator::new();
+        let paris = Location { latitude: 48.8566, longitude: 2.3522 };
+
+        let spherical = sphere.calculate_qibla(&paris);
+        let ellipsoidal = ellipsoid.calculate_qibla(&paris);
+
+        assert!((spherical.bearing - ellipsoidal.bearing).abs() < 0.5);
+        assert!((spherical.distance_km - ellipsoidal.distance_km).abs() / spherical.distance_km < 0.005);
+        assert_eq!(ellipsoidal.direction, "ESE");
+    }
+
+    #[test]
+    fn test_near_antipodal_location() {
+        // Vincenty's method does not converge here; Karney's must still return a geodesic
+        let calculator = EllipsoidalCalculator::new();
+        let antipode = Location { latitude: -21.4, longitude: -140.2 };
+        let result = calculator.calculate_qibla(&antipode);
+
+        assert!(result.bearing.is_finite());
+        assert!((0.0..360.0).contains(&result.bearing));
+        assert!(result.distance_km > 19900.0 && result.distance_km < 20010.0);
+    }
+
+    #[test]
+    fn test_ellipsoidal_compass_table() {
+        let calculator = EllipsoidalCalculator::new();
+        let tokyo = Location { latitude: 35.6762, longitude: 139.6503 };
+        let table = calculator.calculate_compass_table(&tokyo);
+
+        assert_eq!(table.entries.len(), 16);
+        assert_eq!(table.qibla_bearing, calculator.calculate_qibla(&tokyo).bearing);
+        assert_eq!(table.entries.iter().filter(|e| e.is_optimal_direction).count(), 1);
+    }
+
+    fn calculator_inverse(calculator: &EllipsoidalCalculator, lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> (f64, f64) {
+        calculator.inverse(lat1, lon1, lat2, lon2)
+    }
+}
+
+#[cfg(test)]
+mod geomagnetic_tests {
+    use super::*;
+    use crate::geomagnetic::{decimal_year, MagneticModel};
+    use chrono::NaiveDate;
+
+    fn date(year: i32, ordinal: u32) -> NaiveDate {
+        NaiveDate::from_yo_opt(year, ordinal).unwrap()
+    }
+
+    #[test]
+    fn test_embedded_model_metadata() {
+        let model = MagneticModel::wmm();
+        assert_eq!(model.name(), "WMM-2025");
+        assert_eq!(model.validity(), (2025.0, 2030.0));
+    }
+
+    #[test]
+    fn test_declination_reference_values() {
+        let model = MagneticModel::wmm();
+        // (altitude km, latitude, longitude, date, declination, inclination)
+        let cases = [
+            (0.1, 37.03, -7.91, date(2029, 15), -0.1737, 50.4231),
+            (0.0, 40.7128, -74.006, date(2025, 1), -12.5341, 65.7073),
+            (0.0, -33.87, 151.21, date(2027, 100), 12.8381, -64.3962),
+            (0.0, 80.0, 0.0, date(2025, 1), 1.2815, 83.2106),
+            (5.0, -80.0, 120.0, date(2027, 182), -145.4990, -77.3347),
+        ];
+
+        for (altitude, latitude, longitude, date, declination, inclination) in cases {
+            let field = model.field(&Location { latitude, longitude }, altitude, date).unwrap();
+            assert!((field.declination - declination).abs() < 0.01,
+                    "declination {} != {} at {}, {}", field.declination, declination, latitude, longitude);
+            assert!((field.inclination - inclination).abs() < 0.01,
+                    "inclination {} != {} at {}, {}", field.inclination, inclination, latitude, longitude);
+        }
+    }
+
+    #[test]
+    fn test_date_outside_validity_window() {
+        let model = MagneticModel::wmm();
+        let paris = Location { latitude: 48.8566, longitude: 2.3522 };
+        assert!(model.declination(&paris, 0.0, date(2024, 200)).is_err());
+        assert_eq!(model.declination(&paris, 0.0, date(2030, 1)).unwrap_err().field(), Some("date"));
+    }
+
+    #[test]
+    fn test_malformed_coefficient_files() {
+        for text in ["", "2025.0", "2025.0 WMM-2025\n1 0 x 0.0 0.0 0.0", "2025.0 WMM-2025\n1 0 -29351.8"] {
+            let error = MagneticModel::from_cof(text).unwrap_err();
+            assert_eq!((error.code(), error.field()), ("parse_error", Some("coefficient file")), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_magnetic_qibla_bearing() {
+        let model = MagneticModel::wmm();
+        let calculator = GreatCircleCalculator::new();
+        let new_york = Location { latitude: 40.7128, longitude: -74.0060 };
+        let qibla = calculator.calculate_qibla(&new_york);
+
+        let result = model.magnetic_qibla(&qibla, &new_york, 0.0, date(2025, 1)).unwrap();
+        let magnetic = result.magnetic.unwrap();
+
+        // Westerly declination means the compass bearing is larger than the true one
+        assert!(magnetic.declination < -12.0 && magnetic.declination > -13.0);
+        assert!((magnetic.bearing - (qibla.bearing - magnetic.declination)).abs() < 1e-9);
+        assert_eq!(result.bearing, qibla.bearing);
+        assert_eq!(magnetic.valid_until, 2030.0);
+    }
+
+    #[test]
+    fn test_magnetic_compass_table() {
+        let model = MagneticModel::wmm();
+        let calculator = GreatCircleCalculator::new();
+        let new_york = Location { latitude: 40.7128, longitude: -74.0060 };
+        let table = calculator.calculate_compass_table(&new_york);
+
+        let magnetic = model.magnetic_compass_table(&table, 0.0, date(2025, 1)).unwrap();
+        assert_eq!(magnetic.north_reference, NorthReference::Magnetic);
+        assert_eq!(magnetic.entries.len(), table.entries.len());
+        assert!((magnetic.qibla_bearing - table.qibla_bearing - 12.5341).abs() < 0.01);
+        assert_eq!(magnetic.entries.iter().filter(|e| e.is_optimal_direction).count(), 1);
+    }
+
+    #[test]
+    fn test_decimal_year() {
+        assert_eq!(decimal_year(date(2025, 1)), 2025.0);
+        assert!((decimal_year(date(2024, 184)) - 2024.5).abs() < 1e-9);
+    }
+}
+
+#[cfg(test)]
+mod solar_tests {
+    use super::*;
+    use crate::solar::{solar_position, sun_qibla_times, SunAlignment};
+    use crate::timezone::TimeZoneSpec;
+    use chrono::{FixedOffset, NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn test_solstice_declination() {
+        let paris = Location { latitude: 48.8566, longitude: 2.3522 };
+        let position = solar_position(&paris, Utc.with_ymd_and_hms(2026, 6, 21, 12, 0, 0).unwrap());
+        assert!((position.declination - 23.44).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_equation_of_time_extremes() {
+        let origin = Location { latitude: 0.0, longitude: 0.0 };
+        let february = solar_position(&origin, Utc.with_ymd_and_hms(2026, 2, 11, 12, 0, 0).unwrap());
+        let november = solar_position(&origin, Utc.with_ymd_and_hms(2026, 11, 3, 12, 0, 0).unwrap());
+        assert!((february.equation_of_time + 14.2).abs() < 0.2);
+        assert!((november.equation_of_time - 16.4).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_solar_azimuth_and_altitude() {
+        let paris = Location { latitude: 48.8566, longitude: 2.3522 };
+        let position = solar_position(&paris, Utc.with_ymd_and_hms(2026, 6, 21, 9, 29, 25).unwrap());
+        assert!((position.azimuth - 119.2).abs() < 0.1);
+        assert!((position.altitude - 52.2).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_sun_qibla_times_paris() {
+        let calculator = GreatCircleCalculator::new();
+        let paris = Location { latitude: 48.8566, longitude: 2.3522 };
+        let date = NaiveDate::from_ymd_opt(2026, 6, 21).unwrap();
+        let times = sun_qibla_times(&calculator, &paris, date, FixedOffset::east_opt(7200).unwrap());
+
+        assert_eq!(times.events.len(), 2);
+        let towards = &times.events[0];
+        assert_eq!(towards.alignment, SunAlignment::TowardsQibla);
+        assert_eq!(towards.time.format("%H:%M").to_string(), "11:29");
+        assert!((towards.azimuth - times.qibla_bearing).abs() < 0.01);
+        assert!(towards.visible);
+
+        let away = &times.events[1];
+        assert_eq!(away.alignment, SunAlignment::AwayFromQibla);
+        assert!((away.azimuth - (times.qibla_bearing + 180.0)).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_time_zone_spec_parsing() {
+        let date = NaiveDate::from_ymd_opt(2026, 7, 1).unwrap();
+        assert_eq!("UTC".parse::<TimeZoneSpec>().unwrap().offset_on(date).local_minus_utc(), 0);
+        assert_eq!("+03:00".parse::<TimeZoneSpec>().unwrap().offset_on(date).local_minus_utc(), 10800);
+        assert_eq!("UTC-0530".parse::<TimeZoneSpec>().unwrap().offset_on(date).local_minus_utc(), -19800);
+        assert_eq!("Europe/Paris".parse::<TimeZoneSpec>().unwrap().offset_on(date).local_minus_utc(), 7200);
+        assert!("Mars/Olympus".parse::<TimeZoneSpec>().is_err());
+        assert!("+25:00".parse::<TimeZoneSpec>().is_err());
+    }
+}
+
+#[cfg(test)]
+mod istiwa_tests {
+    use super::*;
+    use crate::istiwa::{kaaba_zenith_transits, observe_zenith_transit};
+    use chrono::{Datelike, Timelike};
+
+    #[test]
+    fn test_two_transits_per_year() {
+        let transits = kaaba_zenith_transits(2026);
+        assert_eq!(transits.len(), 2);
+
+        // 28 May 12:17 and 15 July 12:26 Mecca time (UTC+3), within a minute of the published times
+        let (may, july) = (&transits[0], &transits[1]);
+        assert_eq!((may.instant.month(), may.instant.day()), (5, 28));
+        assert_eq!((may.instant.hour(), may.instant.minute()), (9, 17));
+        assert_eq!((july.instant.month(), july.instant.day()), (7, 15));
+        assert_eq!((july.instant.hour(), july.instant.minute()), (9, 26));
+    }
+
+    #[test]
+    fn test_sun_nearly_overhead() {
+        for transit in kaaba_zenith_transits(2025) {
+            assert!(transit.zenith_distance < 0.3);
+            assert!((transit.declination - 21.4225).abs() < 0.3);
+        }
+    }
+
+    #[test]
+    fn test_sun_points_to_qibla_in_daylight() {
+        let calculator = GreatCircleCalculator::new();
+        let paris = Location { latitude: 48.8566, longitude: 2.3522 };
+
+        for transit in kaaba_zenith_transits(2026) {
+            let observation = observe_zenith_transit(&calculator, &transit, &paris);
+            assert!(observation.sun_visible);
+            assert!(observation.deviation.abs() < 0.5);
+            assert!((observation.shadow_direction - (observation.sun_azimuth + 180.0)).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_sun_below_horizon_on_far_side() {
+        let calculator = GreatCircleCalculator::new();
+        let sydney = Location { latitude: -33.87, longitude: 151.21 };
+
+        for transit in kaaba_zenith_transits(2026) {
+            let observation = observe_zenith_transit(&calculator, &transit, &sydney);
+            assert!(!observation.sun_visible);
+        }
+    }
+}
+
+#[cfg(test)]
+mod targets_tests {
+    use super::*;
+    use crate::coordinates::CoordinateFormats;
+    use crate::geodesic::EllipsoidalCalculator;
+    use crate::istiwa::{kaaba_zenith_transits, observe_zenith_transit};
+    use crate::targets::{Targets, CUSTOM_TARGET_ID};
+
+    #[test]
+    fn test_registry_lookup() {
+        let targets = Targets::default();
+        assert!(targets.get("kaaba").unwrap().is_kaaba());
+        assert!(targets.get(" Makkah ").unwrap().is_kaaba());
+        assert_eq!(targets.get("AL-AQSA").unwrap().id, "aqsa");
+        assert_eq!(targets.get("madinah").unwrap().name, "Prophet's Mosque, Medina");
+        assert!(targets.get("atlantis").is_none());
+        assert_eq!(targets.names(), ["kaaba", "medina", "aqsa", "jerusalem"]);
+    }
+
+    #[test]
+    fn test_resolve_custom_coordinates() {
+        let targets = Targets::default();
+        let formats = CoordinateFormats::default();
+
+        let custom = targets.resolve("31°46'34\"N 35°14'9\"E", &formats).unwrap();
+        assert_eq!(custom.id, CUSTOM_TARGET_ID);
+        assert!((custom.location.latitude - 31.7761).abs() < 1e-3);
+
+        let error = targets.resolve("atlantis", &formats).unwrap_err();
+        assert_eq!(error.field(), Some("target"));
+        assert!(targets.resolve("95, 0", &formats).is_err());
+    }
+
+    #[test]
+    fn test_direction_to_first_qibla() {
+        let aqsa = Targets::default().get("aqsa").unwrap().clone();
+        let calculator = GreatCircleCalculator::new().with_target(aqsa.clone());
+        assert_eq!(calculator.target(), &aqsa);
+
+        // From the Kaaba, Al-Aqsa lies about 1230 km to the north-north-west
+        let kaaba = Target::kaaba().location;
+        let result = calculator.calculate_qibla(&kaaba);
+        assert!((result.bearing - 339.4).abs() < 1.0, "{}", result.bearing);
+        assert!((result.distance_km - 1230.0).abs() < 20.0, "{}", result.distance_km);
+
+        let ellipsoidal = EllipsoidalCalculator::new().with_target(aqsa).calculate_qibla(&kaaba);
+        assert!((ellipsoidal.bearing - result.bearing).abs() < 0.5);
+        assert!((ellipsoidal.distance_km - result.distance_km).abs() < 10.0);
+
+        let table = calculator.calculate_compass_table(&kaaba);
+        assert!((table.qibla_bearing - result.bearing).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_default_target_is_kaaba() {
+        let paris = Location { latitude: 48.8566, longitude: 2.3522 };
+        let default = GreatCircleCalculator::new().calculate_qibla(&paris);
+        let explicit = GreatCircleCalculator::new().with_target(Target::kaaba()).calculate_qibla(&paris);
+        assert_eq!(default, explicit);
+        assert!(GreatCircleCalculator::new().target().is_kaaba());
+    }
+
+    #[test]
+    fn test_zenith_transit_still_refers_to_kaaba() {
+        let medina = Targets::default().get("medina").unwrap().clone();
+        let calculator = GreatCircleCalculator::new().with_target(medina);
+        let paris = Location { latitude: 48.8566, longitude: 2.3522 };
+
+        for transit in kaaba_zenith_transits(2026) {
+            let observation = observe_zenith_transit(&calculator, &transit, &paris);
+            assert!(observation.deviation.abs() < 0.5);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod conventions_tests {
+    use super::*;
+    use crate::conventions::*;
+    use crate::targets::Targets;
+
+    const PARIS: Location = Location { latitude: 48.8566, longitude: 2.3522 };
+
+    #[test]
+    fn test_rhumb_line() {
+        let result = RhumbLineCalculator::new().calculate_qibla(&PARIS);
+        assert!((result.bearing - 132.4).abs() < 0.1, "{}", result.bearing);
+        assert!(result.distance_km > GreatCircleCalculator::new().calculate_qibla(&PARIS).distance_km);
+
+        // Along a parallel the rhumb line heads due east
+        let east = RhumbLineCalculator::new().calculate_direction(
+            &Location { latitude: 30.0, longitude: 10.0 },
+            &Location { latitude: 30.0, longitude: 20.0 },
+        );
+        assert!((east.bearing - 90.0).abs() < 1e-9);
+        assert!((east.distance_km - 10.0_f64.to_radians() * 30.0_f64.to_radians().cos() * 6371.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_cardinal_and_medieval() {
+        assert_eq!(CardinalCalculator::new().calculate_qibla(&PARIS).bearing, 90.0);
+        let cordoba = Location { latitude: 37.8847, longitude: -4.7792 };
+        assert_eq!(CardinalCalculator::new().calculate_qibla(&cordoba).direction, "E");
+
+        let medieval = MedievalApproximationCalculator::new().calculate_qibla(&PARIS);
+        assert!((medieval.bearing - 127.1).abs() < 0.1, "{}", medieval.bearing);
+
+        // On the Kaaba's meridian the approximation agrees with the great circle
+        let north = Location { latitude: 40.0, longitude: 39.8262 };
+        assert!((MedievalApproximationCalculator::new().calculate_qibla(&north).bearing - 180.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_winter_sunrise() {
+        let equator = WinterSunriseCalculator::new().calculate_qibla(&Location { latitude: 0.0, longitude: 0.0 });
+        assert!((equator.bearing - 113.4).abs() < 0.1, "{}", equator.bearing);
+
+        let cairo = WinterSunriseCalculator::new().calculate_qibla(&Location { latitude: 30.0444, longitude: 31.2357 });
+        assert!((cairo.bearing - 116.8).abs() < 0.1, "{}", cairo.bearing);
+
+        // No sunrise at the December solstice beyond the Arctic circle
+        let arctic = WinterSunriseCalculator::new().calculate_qibla(&Location { latitude: 78.2, longitude: 15.6 });
+        assert_eq!(arctic.bearing, 180.0);
+    }
+
+    #[test]
+    fn test_compare_conventions() {
+        let comparisons = compare_conventions(&GreatCircleCalculator::new(), &PARIS);
+        assert_eq!(comparisons.len(), QiblaConvention::ALL.len());
+        assert_eq!(comparisons[0].convention, QiblaConvention::GreatCircle);
+        assert_eq!(comparisons[0].deviation, 0.0);
+        let cardinal = comparisons.iter().find(|c| c.convention == QiblaConvention::Cardinal).unwrap();
+        assert!((cardinal.deviation + 29.16).abs() < 0.01);
+
+        // Conventions follow the reference calculator's target
+        let aqsa = Targets::default().get("aqsa").unwrap().clone();
+        let towards_aqsa = compare_conventions(&GreatCircleCalculator::new().with_target(aqsa), &PARIS);
+        assert!((towards_aqsa[1].direction.distance_km - comparisons[1].direction.distance_km).abs() > 500.0);
+
+        // The winter sunrise only stands for the Kaaba
+        assert_eq!(towards_aqsa.len(), QiblaConvention::ALL.len() - 1);
+        assert!(towards_aqsa.iter().all(|c| c.convention != QiblaConvention::WinterSunrise));
+    }
+}
+
+#[cfg(test)]
+mod prayer_tests {
+    use super::*;
+    use crate::prayer::{
+        monthly_prayer_times, prayer_times, AsrMethod, CalculationMethod, HighLatitudeRule, Prayer, PrayerOffset,
+        PrayerSettings,
+    };
+    use crate::timezone::TimeZoneSpec;
+    use chrono::{DateTime, FixedOffset, NaiveDate, Timelike};
+
+    fn minutes_of_day(time: Option<DateTime<FixedOffset>>) -> i64 {
+        let time = time.expect("time should be defined");
+        (time.hour() * 60 + time.minute()) as i64
+    }
+
+    #[test]
+    fn test_isna_hanafi_reference_day() {
+        // Raleigh, NC on 2015-07-12 (EDT): 04:42, 06:08, 13:21, 18:22, 20:32, 21:57
+        let settings = PrayerSettings {
+            method: CalculationMethod::Isna,
+            asr: AsrMethod::Hanafi,
+            ..Default::default()
+        };
+        let raleigh = Location { latitude: 35.7750, longitude: -78.6336 };
+        let times = prayer_times(
+            &raleigh,
+            NaiveDate::from_ymd_opt(2015, 7, 12).unwrap(),
+            FixedOffset::west_opt(4 * 3600).unwrap(),
+            &settings,
+        );
+
+        let expected = [(times.fajr, 4 * 60 + 42), (times.sunrise, 6 * 60 + 8), (times.dhuhr, 13 * 60 + 21),
+                        (times.asr, 18 * 60 + 22), (times.maghrib, 20 * 60 + 32), (times.isha, 21 * 60 + 57)];
+        for (time, minutes) in expected {
+            assert!((minutes_of_day(time) - minutes).abs() <= 1, "{:?} != {}", time, minutes);
+        }
+    }
+
+    #[test]
+    fn test_umm_al_qura_isha_interval() {
+        let settings = PrayerSettings {
+            method: CalculationMethod::UmmAlQura,
+            ..Default::default()
+        };
+        let mecca = Location { latitude: 21.4225, longitude: 39.8262 };
+        let times = prayer_times(&mecca, NaiveDate::from_ymd_opt(2026, 1, 10).unwrap(), FixedOffset::east_opt(3 * 3600).unwrap(), &settings);
+        assert_eq!(minutes_of_day(times.isha) - minutes_of_day(times.maghrib), 90);
+    }
+
+    #[test]
+    fn test_hanafi_asr_is_later() {
+        let paris = Location { latitude: 48.8566, longitude: 2.3522 };
+        let date = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        let offset = FixedOffset::east_opt(7200).unwrap();
+        let shafi = prayer_times(&paris, date, offset, &PrayerSettings::default());
+        let hanafi = prayer_times(&paris, date, offset, &PrayerSettings { asr: AsrMethod::Hanafi, ..Default::default() });
+        assert!(hanafi.asr.unwrap() > shafi.asr.unwrap());
+    }
+
+    #[test]
+    fn test_high_latitude_rules() {
+        let stockholm = Location { latitude: 59.33, longitude: 18.07 };
+        let date = NaiveDate::from_ymd_opt(2026, 6, 21).unwrap();
+        let offset = FixedOffset::east_opt(7200).unwrap();
+
+        let unadjusted = prayer_times(&stockholm, date, offset, &PrayerSettings {
+            high_latitude: HighLatitudeRule::None,
+            ..Default::default()
+        });
+        assert!(unadjusted.fajr.is_none());
+        assert!(unadjusted.isha.is_none());
+
+        for rule in [HighLatitudeRule::MiddleOfNight, HighLatitudeRule::OneSeventh, HighLatitudeRule::AngleBased] {
+            let adjusted = prayer_times(&stockholm, date, offset, &PrayerSettings { high_latitude: rule, ..Default::default() });
+            assert!(adjusted.fajr.unwrap() < adjusted.sunrise.unwrap());
+            assert!(adjusted.isha.unwrap() > adjusted.maghrib.unwrap());
+        }
+    }
+
+    #[test]
+    fn test_prayer_offsets() {
+        let paris = Location { latitude: 48.8566, longitude: 2.3522 };
+        let date = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        let offset = FixedOffset::east_opt(7200).unwrap();
+        let settings = PrayerSettings {
+            offsets: vec!["fajr=2".parse().unwrap(), "isha=-3".parse().unwrap()],
+            ..Default::default()
+        };
+
+        let base = prayer_times(&paris, date, offset, &PrayerSettings::default());
+        let adjusted = prayer_times(&paris, date, offset, &settings);
+        assert_eq!(minutes_of_day(adjusted.fajr) - minutes_of_day(base.fajr), 2);
+        assert_eq!(minutes_of_day(adjusted.isha) - minutes_of_day(base.isha), -3);
+        assert_eq!(adjusted.dhuhr, base.dhuhr);
+    }
+
+    #[test]
+    fn test_settings_parsing() {
+        assert_eq!("umm-al-qura".parse::<CalculationMethod>().unwrap(), CalculationMethod::UmmAlQura);
+        assert_eq!("ISNA".parse::<CalculationMethod>().unwrap(), CalculationMethod::Isna);
+        assert!("unknown".parse::<CalculationMethod>().is_err());
+        assert_eq!("angle-based".parse::<HighLatitudeRule>().unwrap(), HighLatitudeRule::AngleBased);
+        assert_eq!(
+            "maghrib=1.5".parse::<PrayerOffset>().unwrap(),
+            PrayerOffset { prayer: Prayer::Maghrib, minutes: 1.5 }
+        );
+        assert!("maghrib".parse::<PrayerOffset>().is_err());
+    }
+
+    #[test]
+    fn test_monthly_calendar() {
+        let mecca = Location { latitude: 21.4225, longitude: 39.8262 };
+        let tz: TimeZoneSpec = "Asia/Riyadh".parse().unwrap();
+        let days = monthly_prayer_times(&mecca, 2026, 2, &tz, &PrayerSettings::default()).unwrap();
+        assert_eq!(days.len(), 28);
+        assert!(days.iter().all(|day| day.fajr.is_some() && day.isha.is_some()));
+        assert!(monthly_prayer_times(&mecca, 2026, 13, &tz, &PrayerSettings::default()).is_err());
+    }
+}
+
+#[cfg(test)]
+mod coordinates_tests {
+    use crate::coordinates::*;
+    use crate::Location;
+
+    fn parse(input: &str) -> Location {
+        let formats = CoordinateFormats::default();
+        formats
+            .parse(input)
+            .unwrap_or_else(|| panic!("{} not detected", input))
+            .unwrap_or_else(|e| panic!("{}: {}", input, e))
+    }
+
+    fn assert_near(location: &Location, latitude: f64, longitude: f64, tolerance: f64) {
+        assert!((location.latitude - latitude).abs() < tolerance, "latitude {} vs {}", location.latitude, latitude);
+        assert!((location.longitude - longitude).abs() < tolerance, "longitude {} vs {}", location.longitude, longitude);
+    }
+
+    #[test]
+    fn test_detects_each_format() {
+        let formats = CoordinateFormats::default();
+        let detected = |input: &str| formats.detect(input).map(|format| format.name());
+        assert_eq!(detected("31U 448251 5411932"), Some("utm"));
+        assert_eq!(detected("31U DQ 48251 11932"), Some("mgrs"));
+        assert_eq!(detected("8FW4V75V+8Q"), Some("olc"));
+        assert_eq!(detected("u09tunquc"), Some("geohash"));
+        assert_eq!(detected("48°51'24\"N 2°21'08\"E"), Some("dms"));
+        assert_eq!(detected("48.8566, 2.3522"), Some("decimal"));
+        assert_eq!(detected("Paris, France"), None);
+        assert_eq!(detected("nyc"), None);
+        assert_eq!(detected("75001"), None);
+    }
+
+    #[tokio::test]
+    async fn test_street_addresses_are_geocoded() {
+        let formats = CoordinateFormats::default();
+        for address in ["12 Rue", "7 Elm", "10 Oak", "1 Way", "12 Rue de Rivoli", "h3z2y7", "1012jk", "ec1v9hx", "sw1a2aa"] {
+            assert!(formats.detect(address).is_none(), "{} taken as coordinates", address);
+        }
+        // Compact and spaced references with digits are still grid references
+        assert!(formats.detect("12RUE").is_some());
+        assert!(formats.detect("31U DQ 48").is_some());
+        assert!(formats.detect("geohash:h3z2y7").is_some());
+
+        let app = crate::core::MeccaApp::new(super::integration_tests::MockGeocoder, crate::qibla::GreatCircleCalculator::new());
+        let error = app.get_location("12 Rue").await.unwrap_err();
+        assert_eq!(error.code(), "location_not_found");
+    }
+
+    #[test]
+    fn test_utm_round_trip() {
+        // Eiffel Tower
+        let eiffel = parse("31U 448251 5411932");
+        assert_near(&eiffel, 48.8583, 2.2945, 0.0005);
+        assert_eq!(UtmFormat.format(&eiffel), "31U 448251 5411932");
+
+        let sydney = parse("56H 334873 6252266");
+        assert_near(&sydney, -33.8568, 151.2153, 0.0005);
+    }
+
+    #[test]
+    fn test_utm_band_s_and_hemisphere_letter() {
+        // Band S (32°N to 40°N): Los Angeles
+        assert_near(&parse("11S 385000 3768000"), 34.05, -118.25, 0.05);
+        // Outside band S the letter means the southern hemisphere: Rio de Janeiro
+        assert_near(&parse("23 S 686000 7466000"), -22.9, -43.2, 0.05);
+    }
+
+    #[test]
+    fn test_mgrs_round_trip() {
+        let eiffel = parse("31UDQ4825111932");
+        assert_near(&eiffel, 48.8583, 2.2945, 0.0005);
+        assert_eq!(MgrsFormat.format(&eiffel), "31U DQ 48251 11932");
+
+        let sydney = Location { latitude: -33.8568, longitude: 151.2153 };
+        let mgrs = MgrsFormat.format(&sydney);
+        assert_near(&parse(&mgrs), sydney.latitude, sydney.longitude, 0.0001);
+    }
+
+    #[test]
+    fn test_geohash() {
+        assert_near(&parse("u4pruydqqvj"), 57.64911, 10.40744, 0.0001);
+        assert_near(&parse("geohash:gcpvj"), 51.5, -0.13, 0.05);
+        let location = Location { latitude: 57.64911, longitude: 10.40744 };
+        assert_eq!(GeohashFormat.format(&location), "u4pruydqq");
+    }
+
+    #[test]
+    fn test_open_location_code() {
+        assert_near(&parse("8FW4V75V+8Q"), 48.8583, 2.2944, 0.0002);
+        assert_eq!(OpenLocationCodeFormat.format(&Location { latitude: 48.858_312_5, longitude: 2.294_437_5 }), "8FW4V75V+8Q");
+        assert!(CoordinateFormats::default().parse("V75V+8Q").unwrap().is_err());
+    }
+
+    #[test]
+    fn test_output_formats() {
+        let formats = CoordinateFormats::default();
+        let kaaba = Location { latitude: 21.4225, longitude: 39.8262 };
+        assert_eq!(formats.format("decimal", &kaaba).unwrap(), "21.422500, 39.826200");
+        assert_eq!(formats.format("dms", &kaaba).unwrap(), "21°25'21.0\"N 39°49'34.3\"E");
+        assert_eq!(formats.format("what3words", &kaaba).unwrap_err().field(), Some("coordinate format"));
+        for name in formats.names() {
+            let text = formats.format(name, &kaaba).unwrap();
+            assert_near(&parse(&text), kaaba.latitude, kaaba.longitude, 0.001);
+        }
+    }
+}
+
+#[cfg(test)]
+mod gazetteer_tests {
+    use crate::gazetteer::{fold, OfflineGeocoder};
+    use crate::GeocodingService;
+
+    // Excerpt in the GeoNames cities format (19 tab separated columns)
+    const CITIES: &str = "\
+2988507\tParis\tParis\tLutece,Paris,Parigi,Париж\t48.85341\t2.3488\tP\tPPLC\tFR\t\t11\t75\t751\t75056\t2138551\t\t42\tEurope/Paris\t2024-01-01
+4717560\tParis\tParis\t\t33.66094\t-95.55551\tP\tPPLA2\tUS\t\tTX\t277\t\t\t24171\t\t183\tAmerica/Chicago\t2024-01-01
+2980291\tSaint-Étienne\tSaint-Etienne\tSaint-Etienne,Sant-Etiève\t45.43389\t4.39\tP\tPPLA2\tFR\t\t84\t42\t422\t42218\t171057\t\t478\tEurope/Paris\t2024-01-01
+2643743\tLondon\tLondon\tLondres,Londra,Лондон\t51.50853\t-0.12574\tP\tPPLC\tGB\t\tENG\tGLA\t\t\t8961989\t\t25\tEurope/London\t2024-01-01
+6058560\tLondon\tLondon\t\t42.98339\t-81.23304\tP\tPPL\tCA\t\t08\t\t\t\t346765\t\t252\tAmerica/Toronto\t2024-01-01
+104515\tMecca\tMecca\tMakkah,Makkah al Mukarramah,مكة المكرمة\t21.42664\t39.82563\tP\tPPLA\tSA\t\t14\t\t\t\t1323624\t\t277\tAsia/Riyadh\t2024-01-01
+";
+
+    const ADMIN1: &str = "CA.08\tOntario\tOntario\t6093943\nUS.TX\tTexas\tTexas\t4736286\n";
+
+    fn geocoder() -> OfflineGeocoder {
+        OfflineGeocoder::from_geonames(CITIES).unwrap().with_admin1_names(ADMIN1)
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("Saint-Étienne"), "saint etienne");
+        assert_eq!(fold("  Straße  "), "strasse");
+        assert_eq!(fold("Côte d'Ivoire"), "cote d ivoire");
+    }
+
+    #[test]
+    fn test_ranks_by_population() {
+        let geocoder = geocoder();
+        assert_eq!(geocoder.len(), 6);
+        let matches = geocoder.search("paris", 5);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].entry.country_code, "FR");
+        assert_eq!(matches[1].entry.country_code, "US");
+    }
+
+    #[test]
+    fn test_accents_alternate_names_and_typos() {
+        let geocoder = geocoder();
+        assert_eq!(geocoder.search("saint etienne", 1)[0].entry.geoname_id, 2980291);
+        assert_eq!(geocoder.search("Makkah", 1)[0].entry.geoname_id, 104515);
+        assert_eq!(geocoder.search("Лондон", 1)[0].entry.geoname_id, 2643743);
+
+        let typo = geocoder.search("Londno", 1);
+        assert_eq!(typo[0].entry.geoname_id, 2643743);
+        assert_eq!(typo[0].distance, 1);
+        assert!(geocoder.search("Xyzzy", 1).is_empty());
+    }
+
+    #[test]
+    fn test_country_and_admin_filters() {
+        let geocoder = geocoder();
+        assert_eq!(geocoder.search("Paris, TX", 1)[0].entry.country_code, "US");
+        assert_eq!(geocoder.search("Paris, Texas", 1)[0].entry.country_code, "US");
+        assert_eq!(geocoder.search("Paris, United States", 1)[0].entry.country_code, "US");
+        assert_eq!(geocoder.search("London, Ontario", 1)[0].entry.country_code, "CA");
+        assert_eq!(geocoder.search("London, UK", 1)[0].entry.country_code, "GB");
+        assert_eq!(geocoder.search("London Canada", 1)[0].entry.country_code, "CA");
+        assert!(geocoder.search("Paris, Germany", 1).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_geocode() {
+        let geocoder = geocoder();
+        let location = geocoder.geocode("Mecca, Saudi Arabia").await.unwrap();
+        assert!((location.latitude - 21.42664).abs() < 1e-6);
+        let error = geocoder.geocode("Atlantis").await.unwrap_err().to_string();
+        assert!(error.contains("Location not found"));
+    }
+
+    #[tokio::test]
+    async fn test_reverse_geocode() {
+        let geocoder = geocoder();
+        let place = geocoder.reverse_geocode(&crate::Location { latitude: 33.6, longitude: -95.5 }).await.unwrap();
+        assert_eq!(place.display_name, "Paris, Texas, United States");
+        assert_eq!(place.city.as_deref(), Some("Paris"));
+        assert_eq!(place.country_code.as_deref(), Some("US"));
+
+        let ocean = geocoder.reverse_geocode(&crate::Location { latitude: 0.0, longitude: 0.0 }).await;
+        assert!(matches!(ocean, Err(crate::MecczError::LocationNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_geocode_candidates() {
+        let geocoder = geocoder();
+        let candidates = geocoder.geocode_candidates("London", 5).await.unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].display_name, "London, United Kingdom");
+        assert_eq!(candidates[1].display_name, "London, Ontario, Canada");
+        assert_eq!(candidates[1].kind, "PPL");
+        assert!(candidates[0].importance.unwrap() > candidates[1].importance.unwrap());
+
+        assert_eq!(geocoder.geocode_candidates("London", 1).await.unwrap().len(), 1);
+        assert!(geocoder.geocode_candidates("Atlantis", 5).await.is_err());
+    }
+
+    #[test]
+    fn test_rejects_malformed_dump() {
+        assert!(OfflineGeocoder::from_geonames("").is_err());
+        assert!(OfflineGeocoder::from_geonames("1\tParis\t48.8\t2.3").is_err());
+    }
+}
+
+#[cfg(test)]
+mod cache_tests {
+    use crate::cache::{write_csv, CacheEntry, CachedGeocoder, GeocodingCache, PurgeFilter, CSV_COLUMNS};
+    use crate::{GeocodingService, Location, MecczError, Place};
+    use chrono::{Duration, Utc};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingGeocoder {
+        calls: AtomicUsize,
+        name: &'static str,
+    }
+
+    #[async_trait::async_trait]
+    impl GeocodingService for CountingGeocoder {
+        async fn geocode(&self, address: &str) -> Result<Location, MecczError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match address {
+                "Paris, France" => Ok(Location { latitude: 48.8566, longitude: 2.3522 }),
+                "offline" => Err(MecczError::Transport {
+                    service: "counting".to_string(),
+                    status: None,
+                    reason: "connection refused".to_string(),
+                }),
+                _ => Err(MecczError::LocationNotFound(address.to_string())),
+            }
+        }
+
+        async fn reverse_geocode(&self, location: &Location) -> Result<Place, MecczError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match location.latitude > 0.0 {
+                true => Ok(Place { display_name: "Paris, France".to_string(), city: Some("Paris".to_string()), country: None, country_code: None }),
+                false => Err(MecczError::LocationNotFound(format!("{}", location.latitude))),
+            }
+        }
+
+        fn source(&self) -> &str {
+            self.name
+        }
+    }
+
+    fn temporary_cache_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("meccz-cache-test-{}-{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn cached(path: &PathBuf) -> CachedGeocoder<CountingGeocoder> {
+        cached_as(path, "counting")
+    }
+
+    fn cached_as(path: &PathBuf, name: &'static str) -> CachedGeocoder<CountingGeocoder> {
+        CachedGeocoder::new(CountingGeocoder { calls: AtomicUsize::new(0), name }, GeocodingCache::open(path).unwrap())
+    }
+
+    fn entry(query: &str, age_days: i64) -> CacheEntry {
+        CacheEntry {
+            query: query.to_string(),
+            location: Some(Location { latitude: 0.0, longitude: 0.0 }),
+            source: "test".to_string(),
+            cached_at: Utc::now() - Duration::days(age_days),
+            candidates: Vec::new(),
+            candidate_limit: 0,
+            reverse: false,
+            place: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hits_are_served_from_disk() {
+        let path = temporary_cache_path("hits");
+        let geocoder = cached(&path);
+        geocoder.geocode("Paris, France").await.unwrap();
+        geocoder.geocode("  paris FRANCE ").await.unwrap();
+        assert_eq!(geocoder.inner().calls.load(Ordering::SeqCst), 1);
+
+        // Answers are written when persisted, not on every lookup
+        assert!(!path.exists());
+        geocoder.persist().unwrap();
+        assert!(path.exists());
+
+        // A new process reads the same file
+        let reopened = cached(&path);
+        let location = reopened.geocode("Paris, France").await.unwrap();
+        assert!((location.latitude - 48.8566).abs() < 1e-9);
+        assert_eq!(reopened.inner().calls.load(Ordering::SeqCst), 0);
+
+        let cache = GeocodingCache::open(&path).unwrap();
+        assert_eq!(cache.entries()[0].source, "counting");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_persist_reports_failures() {
+        let path = temporary_cache_path("persist");
+        let geocoder = cached(&path);
+        geocoder.geocode("Paris, France").await.unwrap();
+        drop(geocoder);
+        assert!(!path.exists());
+
+        // A cache directory that is a file cannot be written, and the answers are kept for another try
+        let geocoder = cached(&path.join("geocoding.json"));
+        geocoder.geocode("Paris, France").await.unwrap();
+        std::fs::write(&path, "").unwrap();
+        assert!(geocoder.persist().is_err());
+        std::fs::remove_file(&path).unwrap();
+        geocoder.persist().unwrap();
+        assert!(path.join("geocoding.json").exists());
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[tokio::test]
+    async fn test_negative_caching_and_errors() {
+        let path = temporary_cache_path("negative");
+        let geocoder = cached(&path);
+
+        for _ in 0..2 {
+            let error = geocoder.geocode("Atlantis").await.unwrap_err();
+            assert!(matches!(error, MecczError::LocationNotFound(_)));
+        }
+        assert_eq!(geocoder.inner().calls.load(Ordering::SeqCst), 1);
+
+        // Transient failures are retried every time
+        assert!(geocoder.geocode("offline").await.is_err());
+        assert!(geocoder.geocode("offline").await.is_err());
+        assert_eq!(geocoder.inner().calls.load(Ordering::SeqCst), 3);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_candidate_lists_are_cached() {
+        let path = temporary_cache_path("candidates");
+        let geocoder = cached(&path);
+
+        assert_eq!(geocoder.geocode_candidates("Paris, France", 5).await.unwrap().len(), 1);
+        // A one-item list for a limit of 5 is already complete
+        assert_eq!(geocoder.geocode_candidates("paris, france", 10).await.unwrap().len(), 1);
+        assert!(geocoder.geocode("Paris, France").await.is_ok());
+        assert_eq!(geocoder.inner().calls.load(Ordering::SeqCst), 1);
+
+        assert!(geocoder.geocode_candidates("Atlantis", 5).await.is_err());
+        assert!(geocoder.geocode_candidates("Atlantis", 5).await.is_err());
+        assert_eq!(geocoder.inner().calls.load(Ordering::SeqCst), 2);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_sources_do_not_share_entries() {
+        let path = temporary_cache_path("sources");
+        let first = cached_as(&path, "first");
+        first.geocode("Paris, France").await.unwrap();
+        assert!(first.geocode("Atlantis").await.is_err());
+
+        first.persist().unwrap();
+
+        let second = cached_as(&path, "second");
+        second.geocode("Paris, France").await.unwrap();
+        assert!(second.geocode("Atlantis").await.is_err());
+        assert_eq!(second.inner().calls.load(Ordering::SeqCst), 2);
+        second.persist().unwrap();
+
+        let cache = GeocodingCache::open(&path).unwrap();
+        assert_eq!(cache.len(), 4);
+        assert!(cache.get("first", "paris france", Utc::now()).is_some());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_reverse_lookups_are_cached() {
+        let path = temporary_cache_path("reverse");
+        let geocoder = cached(&path);
+        let paris = Location { latitude: 48.85661, longitude: 2.35222 };
+        let nearby = Location { latitude: 48.85659, longitude: 2.35218 };
+        let ocean = Location { latitude: -40.0, longitude: -120.0 };
+
+        assert_eq!(geocoder.reverse_geocode(&paris).await.unwrap().display_name, "Paris, France");
+        assert_eq!(geocoder.reverse_geocode(&nearby).await.unwrap().city.as_deref(), Some("Paris"));
+        for _ in 0..2 {
+            assert!(matches!(geocoder.reverse_geocode(&ocean).await, Err(MecczError::LocationNotFound(_))));
+        }
+        assert_eq!(geocoder.inner().calls.load(Ordering::SeqCst), 2);
+        geocoder.persist().unwrap();
+
+        // Reverse entries neither answer address queries nor other geocoders
+        let cache = GeocodingCache::open(&path).unwrap();
+        assert!(cache.get_place("counting", &nearby, Utc::now()).is_some());
+        assert!(cache.get_place("other", &nearby, Utc::now()).is_none());
+        assert!(cache.get("counting", "48.8566, 2.3522", Utc::now()).is_none());
+        assert_eq!(cache.entries().iter().filter(|entry| entry.is_found()).count(), 1);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let mut address = entry("Paris, \"la Ville Lumière\"", 0);
+        address.source = "pelias@http://localhost:4000/a,b".to_string();
+        let mut reverse = entry("48.8566, 2.3522", 0);
+        reverse.reverse = true;
+        reverse.place = Some(Place {
+            display_name: "Paris, France".to_string(),
+            city: Some("Paris".to_string()),
+            country: Some("France".to_string()),
+            country_code: Some("FR".to_string()),
+        });
+        let mut out = Vec::new();
+        write_csv(&[&address, &reverse], &mut out).unwrap();
+
+        let mut reader = csv::Reader::from_reader(out.as_slice());
+        assert_eq!(reader.headers().unwrap(), CSV_COLUMNS.as_slice());
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][0], "Paris, \"la Ville Lumière\"");
+        assert_eq!(&rows[0][3], "pelias@http://localhost:4000/a,b");
+        assert_eq!((&rows[0][5], &rows[0][6]), ("false", ""));
+        assert_eq!((&rows[1][5], &rows[1][6], &rows[1][9]), ("true", "Paris, France", "FR"));
+    }
+
+    #[test]
+    fn test_ttl_and_size_limit() {
+        let path = temporary_cache_path("ttl");
+        let mut cache = GeocodingCache::open(&path).unwrap().with_ttl(Duration::days(7)).with_max_entries(2);
+        cache.insert(entry("old", 10));
+        cache.insert(entry("recent", 1));
+        assert!(cache.get("test", "old", Utc::now()).is_none());
+        assert!(cache.get("test", "recent", Utc::now()).is_some());
+        assert!(cache.get("other", "recent", Utc::now()).is_none());
+
+        cache.insert(entry("newest", 0));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.entries().iter().all(|entry| entry.query != "old"));
+    }
+
+    #[test]
+    fn test_purge() {
+        let path = temporary_cache_path("purge");
+        let mut cache = GeocodingCache::open(&path).unwrap().with_negative_ttl(Duration::days(1));
+        cache.insert(entry("expired", 60));
+        cache.insert(entry("fresh", 0));
+        cache.insert(CacheEntry { location: None, ..entry("missing", 0) });
+
+        assert_eq!(cache.purge(PurgeFilter::Negative, Utc::now()), 1);
+        assert_eq!(cache.purge(PurgeFilter::Expired, Utc::now()), 1);
+        assert_eq!(cache.entries()[0].query, "fresh");
+        cache.save().unwrap();
+        assert!(!cache.is_dirty());
+        assert_eq!(GeocodingCache::open(&path).unwrap().len(), 1);
+
+        assert_eq!(cache.purge(PurgeFilter::Expired, Utc::now()), 0);
+        assert!(!cache.is_dirty());
+        assert_eq!(cache.purge(PurgeFilter::All, Utc::now()), 1);
+        assert!(cache.is_empty() && cache.is_dirty());
+        let _ = std::fs::remove_file(&path);
+    }
+}
+
+#[cfg(test)]
+mod route_tests {
+    use super::*;
+    use crate::route::*;
+
+    const PARIS: Location = Location { latitude: 48.8566, longitude: 2.3522 };
+    const KAABA: Location = Location { latitude: 21.4225, longitude: 39.8262 };
+    const APIA: Location = Location { latitude: -13.83, longitude: -171.76 };
+
+    #[test]
+    fn test_waypoints_follow_the_great_circle() {
+        let path = GreatCirclePath::new(&PARIS, &KAABA, Density::Waypoints(11));
+        assert_eq!(path.waypoints.len(), 11);
+        assert_eq!(path.waypoints[0], PARIS);
+        assert_eq!(path.waypoints[10], KAABA);
+
+        // Evenly spaced, and the legs add up to the direct distance
+        let legs: Vec<f64> = path
+            .waypoints
+            .windows(2)
+            .map(|pair| GreatCircleCalculator::calculate_distance(pair[0].latitude, pair[0].longitude, pair[1].latitude, pair[1].longitude))
+            .collect();
+        assert!(legs.iter().all(|leg| (leg - path.distance_km / 10.0).abs() < 1e-6));
+        assert!((legs.iter().sum::<f64>() - path.distance_km).abs() < 1e-6);
+
+        // The second point lies on the initial Qibla bearing
+        let qibla = GreatCircleCalculator::new().calculate_qibla(&PARIS);
+        let heading = GreatCircleCalculator::new().calculate_direction(&PARIS, &path.waypoints[1]);
+        assert!((heading.bearing - qibla.bearing).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_spacing() {
+        let path = GreatCirclePath::new(&PARIS, &KAABA, Density::SpacingKm(500.0));
+        assert_eq!(path.waypoints.len(), 10); // 4496 km in 9 legs of at most 500 km
+        assert_eq!(GreatCirclePath::new(&PARIS, &PARIS, Density::default()).waypoints, vec![PARIS, PARIS]);
+
+        // Antipodes have no single great circle; the path still reaches the far side
+        let antipode = Location { latitude: -KAABA.latitude, longitude: KAABA.longitude - 180.0 };
+        let path = GreatCirclePath::new(&antipode, &KAABA, Density::Waypoints(3));
+        assert!(path.waypoints.iter().all(|point| point.latitude.is_finite() && point.longitude.is_finite()));
+        assert!((path.distance_km - std::f64::consts::PI * 6371.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_antimeridian_split() {
+        let path = GreatCirclePath::new(&APIA, &KAABA, Density::SpacingKm(1000.0));
+        let segments = path.segments();
+        assert_eq!(segments.len(), 2);
+        let (before, after) = (segments[0].last().unwrap(), &segments[1][0]);
+        assert_eq!((before.longitude, after.longitude), (-180.0, 180.0));
+        assert_eq!(before.latitude, after.latitude);
+        assert!(before.latitude > APIA.latitude && before.latitude < 0.0);
+        assert_eq!(segments.iter().map(Vec::len).sum::<usize>(), path.waypoints.len() + 2);
+
+        assert_eq!(GreatCirclePath::new(&PARIS, &KAABA, Density::default()).segments().len(), 1);
+    }
+
+    #[test]
+    fn test_exports() {
+        let path = GreatCirclePath::new(&PARIS, &KAABA, Density::Waypoints(3));
+        let geojson: serde_json::Value = serde_json::from_str(&to_geojson(&path, "Paris")).unwrap();
+        assert_eq!(geojson["geometry"]["type"], "LineString");
+        assert_eq!(geojson["geometry"]["coordinates"][0], serde_json::json!([2.3522, 48.8566]));
+        assert_eq!(geojson["properties"]["name"], "Paris");
+
+        let crossing = GreatCirclePath::new(&APIA, &KAABA, Density::Waypoints(5));
+        let geojson: serde_json::Value = serde_json::from_str(&to_geojson(&crossing, "Apia")).unwrap();
+        assert_eq!(geojson["geometry"]["type"], "MultiLineString");
+        assert!(to_kml(&crossing, "Apia").contains("<MultiGeometry>"));
+
+        let kml = to_kml(&path, "Paris & <Mecca>");
+        assert!(kml.contains("<name>Paris &amp; &lt;Mecca&gt;</name>"));
+        assert!(kml.contains("<coordinates>2.3522,48.8566 "));
+
+        let gpx = to_gpx(&path, "Paris");
+        assert_eq!(gpx.matches("<rtept ").count(), 3);
+        assert!(gpx.contains("<rtept lat=\"21.4225\" lon=\"39.8262\"/>"));
+
+        assert_eq!("GeoJSON".parse::<PathFormat>(), Ok(PathFormat::GeoJson));
+        assert_eq!("gpx".parse::<PathFormat>(), Ok(PathFormat::Gpx));
+        assert_eq!("shp".parse::<PathFormat>().unwrap_err().code(), "parse_error");
+    }
+}
+
+#[cfg(test)]
+mod compass_tests {
+    use super::*;
+    use crate::compass::*;
+    use super::integration_tests::MockGeocoder;
+    use crate::geomagnetic::MagneticModel;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_named_roses() {
+        let names = |count| -> Vec<String> { CompassRose::named(count).unwrap().points().iter().map(|p| p.name.clone()).collect() };
+        assert_eq!(names(4), ["N", "E", "S", "W"]);
+        assert_eq!(names(8), ["N", "NE", "E", "SE", "S", "SW", "W", "NW"]);
+        assert_eq!(names(16).len(), 16);
+        let rose = CompassRose::named(32).unwrap();
+        assert_eq!(rose.points()[1], CompassPoint { name: "NbE".to_string(), bearing: 11.25 });
+        assert_eq!(rose.name(119.16), "SEbE");
+        assert_eq!(rose.name(355.0), "N");
+        assert!(CompassRose::named(12).is_err());
+    }
+
+    #[test]
+    fn test_eight_point_names_are_unchanged() {
+        // Same boundaries as before the rose: halfway bearings go clockwise, except at North
+        let rose = CompassRose::named(8).unwrap();
+        for (bearing, name) in [(0.0, "N"), (22.4, "N"), (22.5, "NE"), (67.5, "E"), (337.4, "NW"), (337.5, "N"), (359.9, "N")] {
+            assert_eq!(rose.name(bearing), name, "{}", bearing);
+        }
+    }
+
+    #[test]
+    fn test_step_roses() {
+        let rose = CompassRose::with_step(10.0).unwrap();
+        assert_eq!(rose.points().len(), 36);
+        assert_eq!(rose.name(124.0), "120°");
+        assert_eq!(CompassRose::with_step(100.0).unwrap().points().len(), 4); // 0, 100, 200, 300
+        assert!(CompassRose::with_step(0.0).is_err());
+
+        assert_eq!("32".parse::<CompassRose>().unwrap(), CompassRose::named(32).unwrap());
+        assert_eq!("10deg".parse::<CompassRose>().unwrap(), rose);
+        assert_eq!("10°".parse::<CompassRose>().unwrap(), rose);
+        assert_eq!("36".parse::<CompassRose>().unwrap(), rose);
+        assert_eq!("12".parse::<CompassRose>().unwrap().name(31.0), "30°");
+        assert_eq!("north".parse::<CompassRose>().unwrap_err().code(), "parse_error");
+    }
+
+    #[test]
+    fn test_app_uses_one_rose() {
+        let location = Location { latitude: 48.8566, longitude: 2.3522 };
+        let default_app = MeccaApp::new(MockGeocoder, GreatCircleCalculator::new());
+        let qibla = default_app.get_qibla(&location);
+        let table = default_app.get_compass_table(&location);
+        assert_eq!(qibla.direction, "ESE");
+        assert_eq!(table.entries.len(), 16);
+        assert_eq!(table.entries.iter().find(|e| e.is_optimal_direction).unwrap().direction, qibla.direction);
+        assert_eq!(GreatCircleCalculator::new().calculate_qibla(&location).direction, qibla.direction);
+
+        // The magnetic bearing is named on the same rose
+        let date = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let eight = MeccaApp::new(MockGeocoder, GreatCircleCalculator::new()).with_compass_rose(CompassRose::named(8).unwrap());
+        let magnetic = eight.get_magnetic_qibla(&MagneticModel::wmm(), &location, 0.0, date).unwrap();
+        let magnetic = magnetic.magnetic.unwrap();
+        assert_eq!(magnetic.direction, CompassRose::named(8).unwrap().name(magnetic.bearing));
+
+        for count in NAMED_POINT_COUNTS {
+            let app = MeccaApp::new(MockGeocoder, GreatCircleCalculator::new()).with_compass_rose(CompassRose::named(count).unwrap());
+            let qibla = app.get_qibla(&location);
+            let table = app.get_compass_table(&location);
+            assert_eq!(table.entries.len(), count);
+            let optimal = table.entries.iter().find(|e| e.is_optimal_direction).unwrap();
+            assert_eq!(optimal.direction, qibla.direction, "{} points", count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod i18n_tests {
+    use super::*;
+    use crate::conventions::QiblaConvention;
+    use crate::i18n::*;
+    use crate::targets::Targets;
+
+    #[test]
+    fn test_language_codes() {
+        assert_eq!("fr".parse::<Language>(), Ok(Language::French));
+        assert_eq!("ar_SA.UTF-8".parse::<Language>(), Ok(Language::Arabic));
+        assert_eq!("ms-MY".parse::<Language>(), Ok(Language::Malay));
+        assert_eq!("de".parse::<Language>().unwrap_err().field(), Some("language"));
+        for language in Language::ALL {
+            assert_eq!(language.code().parse::<Language>(), Ok(language));
+        }
+        assert!(Language::Urdu.is_right_to_left() && !Language::Turkish.is_right_to_left());
+    }
+
+    #[test]
+    fn test_numbers() {
+        let english = Localizer::default();
+        assert_eq!(english.number(4496.17, 0), "4496");
+        assert_eq!(english.signed(13.234, 2), "+13.23");
+        assert_eq!(english.signed(-0.5, 1), "-0.5");
+
+        assert_eq!(Localizer::new(Language::French).number(4496.17, 1), "4\u{202F}496,2");
+        assert_eq!(Localizer::new(Language::Turkish).number(1234567.0, 0), "1.234.567");
+        assert_eq!(Localizer::new(Language::Turkish).ungrouped(2025.0, 1), "2025,0");
+        assert_eq!(Localizer::new(Language::Arabic).number(119.16, 2), "١١٩٫١٦");
+        assert_eq!(Localizer::new(Language::Urdu).number(-12.5, 1), "-۱۲٫۵");
+        assert_eq!(
+            Localizer::new(Language::Indonesian).coordinates(&Location { latitude: -6.2, longitude: 106.8167 }),
+            "-6,2000; 106,8167"
+        );
+    }
+
+    #[test]
+    fn test_direction_names() {
+        let french = Localizer::new(Language::French);
+        assert_eq!(french.direction("WSW"), "OSO");
+        assert_eq!(french.direction("NbW"), "NqO");
+        assert_eq!(Localizer::new(Language::Turkish).direction("SE"), "GD");
+        assert_eq!(Localizer::new(Language::Malay).direction("NE"), "UT");
+        assert_eq!(Localizer::new(Language::Arabic).direction("NE"), "شمال شرق");
+        assert_eq!(Localizer::new(Language::Urdu).direction("W"), "مغرب");
+        assert_eq!(Localizer::new(Language::Arabic).direction("22.5°"), "٢٢٫٥°");
+        assert_eq!(Localizer::default().direction("NNE"), "NNE");
+    }
+
+    #[test]
+    fn test_error_messages() {
+        use crate::error::MecczError;
+        use std::time::Duration;
+
+        // English messages are those of the errors themselves
+        let errors = [
+            MecczError::parse("latitude", "minutes must be below 60"),
+            MecczError::OutOfRange { field: "latitude".to_string(), value: 91.5, min: -90.0, max: 90.0 },
+            MecczError::LocationNotFound("Atlantis".to_string()),
+            MecczError::Unsupported { service: "offline".to_string(), operation: "Reverse geocoding".to_string() },
+            MecczError::Transport { service: "nominatim".to_string(), status: Some(503), reason: "unavailable".to_string() },
+            MecczError::RateLimited { service: "nominatim".to_string(), retry_after: None },
+            MecczError::RateLimited { service: "nominatim".to_string(), retry_after: Some(Duration::from_secs(30)) },
+            MecczError::malformed_response("photon", "missing features"),
+        ];
+        let english = Localizer::default();
+        for error in &errors {
+            assert_eq!(english.error(error), error.to_string());
+        }
+
+        let french = Localizer::new(Language::French);
+        assert_eq!(french.error(&errors[1]), "Latitude : valeur attendue entre -90 et 90 degrés (reçu 91,5)");
+        assert_eq!(french.error(&errors[2]), "Lieu introuvable : Atlantis");
+        let arabic = Localizer::new(Language::Arabic);
+        assert!(arabic.error(&errors[6]).contains("٣٠"));
+    }
+
+    #[test]
+    fn test_messages() {
+        let english = Localizer::default();
+        let kaaba = Targets::default().get("kaaba").unwrap().clone();
+        assert_eq!(english.format(english.text().direction_to, &[&english.target_label(&kaaba)]), "Direction to Mecca:");
+        assert_eq!(english.convention_name(QiblaConvention::Cardinal), QiblaConvention::Cardinal.name());
+        assert_eq!(english.convention_description(QiblaConvention::RhumbLine), QiblaConvention::RhumbLine.description());
+
+        let french = Localizer::new(Language::French);
+        assert_eq!(french.format(french.text().distance, &[&french.number(4496.0, 0)]), "Distance : 4\u{202F}496 km");
+        assert_eq!(french.target_label(&kaaba), "La Mecque");
+
+        // Right-to-left lines are marked and their values isolated
+        let arabic = Localizer::new(Language::Arabic);
+        let line = arabic.format(arabic.text().place, &["Paris"]);
+        assert_eq!(line, "\u{200F}المكان: \u{2068}Paris\u{2069}");
+        let explanation = arabic.format(arabic.text().rhumb_explanation, &[]);
+        assert!(explanation.lines().all(|line| line.starts_with('\u{200F}')));
+    }
+}
+
+#[cfg(test)]
+mod render_tests {
+    use super::*;
+    use crate::i18n::{Language, Localizer};
+    use crate::qibla::GreatCircleCalculator;
+    use crate::render::QiblaCard;
+
+    fn paris_card() -> QiblaCard {
+        let calculator = GreatCircleCalculator::new();
+        let qibla = calculator.calculate_qibla(&Location { latitude: 48.8566, longitude: 2.3522 });
+        QiblaCard::new(qibla, calculator.target().clone())
+    }
+
+    #[test]
+    fn test_svg_card() {
+        let svg = paris_card().with_label("Paris").to_svg();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"600\" height=\"780\""));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains("rotate(119.1"), "the Qibla arrow points along the bearing");
+        assert!(svg.contains("Qibla Direction: 119.2°"));
+        assert!(svg.contains(">Paris</text>"));
+        assert!(!svg.contains("MN</text>"), "no magnetic North without a magnetic bearing");
+    }
+
+    #[test]
+    fn test_card_options() {
+        let calculator = GreatCircleCalculator::new();
+        let location = Location { latitude: 48.8566, longitude: 2.3522 };
+        let table = calculator.calculate_compass_table(&location);
+        let svg = paris_card()
+            .with_table(table)
+            .with_label("Caf\u{e9} <Ch\u{e2}telet> & co")
+            .with_size(1000)
+            .with_localizer(Localizer::new(Language::French))
+            .to_svg();
+        assert!(svg.contains("width=\"1000\" height=\"1300\""));
+        assert!(svg.contains("Café &lt;Châtelet&gt; &amp; co"));
+        assert!(svg.contains(">OSO</text>"), "table directions use the language of the card");
+        assert!(svg.contains("Direction de la Qibla : 119,2°"));
+        assert_eq!(svg.matches("stroke=\"#1b7a3d\" stroke-width=\"2\"").count(), 1, "one optimal direction");
+    }
+
+    #[test]
+    fn test_magnetic_north_on_card() {
+        let calculator = GreatCircleCalculator::new();
+        let location = Location { latitude: 48.8566, longitude: 2.3522 };
+        let date = chrono::NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let qibla = crate::geomagnetic::MagneticModel::wmm()
+            .magnetic_qibla(&calculator.calculate_qibla(&location), &location, 0.0, date)
+            .unwrap();
+        let declination = qibla.magnetic.as_ref().unwrap().declination;
+        let svg = QiblaCard::new(qibla, calculator.target().clone()).to_svg();
+        assert!(svg.contains("MN</text>"));
+        assert!(svg.contains(&format!("rotate({:.3} 200 250)", declination)));
+        assert!(svg.contains("Magnetic bearing: "));
+    }
+}
+
+#[cfg(test)]
+mod terminal_tests {
+    use super::*;
+    use crate::i18n::{Language, Localizer};
+    use crate::terminal::*;
+
+    fn heading(bearing: f64) -> QiblaDirection {
+        QiblaDirection {
+            bearing,
+            direction: crate::compass::CompassRose::named(8).unwrap().name(bearing),
+            distance_km: 1000.0,
+            magnetic: None,
+            rhumb_line: None,
+        }
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("119.2°"), 6);
+        assert_eq!(display_width("\u{200F}المكان: \u{2068}Paris\u{2069}"), 13);
+        assert_eq!(display_width("4\u{202F}496 km"), 8);
+    }
+
+    #[test]
+    fn test_text_table_layout() {
+        let mut table = TextTable::new([("Direction", Align::Left), ("Bearing", Align::Right), ("Optimal", Align::Left)]);
+        table.push_row(vec!["ESE".to_string(), "112.5°".to_string(), "*".to_string()]);
+        table.push_row(vec!["شرق جنوب شرق".to_string(), "١١٢٫٥°".to_string()]);
+        let lines = table.lines();
+        assert_eq!(lines[0], "Direction     Bearing  Optimal");
+        assert_eq!(lines[1], "-".repeat(30));
+        assert_eq!(lines[2], "ESE            112.5°  *");
+        assert_eq!(lines[3], "شرق جنوب شرق   ١١٢٫٥°", "short rows are padded and trimmed");
+    }
+
+    #[test]
+    fn test_compass_fits_terminal() {
+        for width in [20, 60, 80, 200] {
+            let lines = TerminalCompass::new(&heading(119.16)).with_width(width).lines();
+            let widest = lines.iter().map(|line| display_width(line)).max().unwrap();
+            assert!(widest <= width.max(20), "{} columns on a {} column terminal", widest, width);
+            // The dial is half as many rows as columns, plus North, South and the caption
+            let dial = lines.len() - 3;
+            assert!((8..=20).contains(&dial));
+            assert_eq!(lines[0].trim(), "N");
+            assert_eq!(lines[lines.len() - 1].trim(), "119.2° SE");
+        }
+        let french = TerminalCompass::new(&heading(270.0)).with_localizer(Localizer::new(Language::French)).lines();
+        assert!(french.iter().any(|line| line.trim_start().starts_with("O ")));
+        assert_eq!(french[french.len() - 1].trim(), "270,0° O");
+    }
+
+    #[test]
+    fn test_compass_needle_and_colors() {
+        // The arrow fills the row through the center on the side it points to
+        let row_through_center = |bearing: f64| {
+            let lines = TerminalCompass::new(&heading(bearing)).lines();
+            lines.into_iter().find(|line| line.trim_start().starts_with("W ")).unwrap()
+        };
+        let east = row_through_center(90.0);
+        let west = row_through_center(270.0);
+        let dots_right_of_center = |line: &str| line.chars().skip(40).filter(|c| ('\u{2801}'..='\u{28FF}').contains(c)).count();
+        assert!(dots_right_of_center(&east) > dots_right_of_center(&west) + 10);
+        assert!(!east.contains('\x1b'));
+
+        let colored = TerminalCompass::new(&heading(90.0)).with_color(true).lines().join("\n");
+        assert!(colored.contains("\x1b[1;32m") && colored.contains("\x1b[1;31m"));
+        assert!(colored.contains("\x1b[0m"));
+    }
+}
+
+#[cfg(test)]
+mod batch_tests {
+    use super::*;
+    use super::integration_tests::MockGeocoder;
+    use crate::batch::*;
+    use crate::qibla::GreatCircleCalculator;
+    use crate::throttle::{RateLimitedGeocoder, RateLimiter};
+    use futures::StreamExt;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_csv_input() {
+        let text = "ID,Name,Address\n1,Grande Mosquée,\"48.8420, 2.3550\"\n2,Empty,\n3,Unknown,Atlantis\n";
+        let batch = BatchInput::parse(text, BatchFormat::Csv, &BatchColumns::default()).unwrap();
+        assert_eq!(batch.id_columns, vec!["id"]);
+        assert_eq!(batch.records.len(), 3);
+        assert_eq!(batch.records[0].line, 2);
+        assert_eq!(batch.records[0].ids, vec!["1"]);
+        assert_eq!(batch.records[0].input, Ok("48.8420, 2.3550".to_string()));
+        assert_eq!(batch.records[1].input.as_ref().unwrap_err().code(), "parse_error");
+
+        // Latitude and longitude columns, named identifier columns
+        let text = "code,name,lat,lon\nA,Kaaba,21.4225,39.8262\n";
+        let columns = BatchColumns { ids: vec!["code".to_string(), "name".to_string()], location: None };
+        let batch = BatchInput::parse(text, BatchFormat::Csv, &columns).unwrap();
+        assert_eq!(batch.id_columns, vec!["code", "name"]);
+        assert_eq!(batch.records[0].ids, vec!["A", "Kaaba"]);
+        assert_eq!(batch.records[0].input, Ok("21.4225,39.8262".to_string()));
+
+        let columns = BatchColumns { ids: Vec::new(), location: Some("site".to_string()) };
+        let error = BatchInput::parse(text, BatchFormat::Csv, &columns).unwrap_err();
+        assert_eq!(error.field(), Some("batch columns"));
+    }
+
+    #[test]
+    fn test_headerless_input() {
+        let batch = BatchInput::parse("48.8566,2.3522\nParis, France\n\n", BatchFormat::Csv, &BatchColumns::default()).unwrap();
+        let inputs: Vec<_> = batch.records.iter().map(|record| record.input.clone().unwrap()).collect();
+        assert_eq!(inputs, vec!["48.8566, 2.3522", "Paris, France"]);
+        assert!(batch.id_columns.is_empty());
+
+        assert_eq!(BatchFormat::sniff("\n{\"location\": \"Paris\"}\n"), BatchFormat::Jsonl);
+        assert_eq!(BatchFormat::sniff("\"Paris\"\n"), BatchFormat::Jsonl);
+        assert_eq!(BatchFormat::sniff("\"Paris, France\",1\n"), BatchFormat::Csv);
+        assert_eq!(BatchFormat::from_path(std::path::Path::new("mosques.NDJSON")), Some(BatchFormat::Jsonl));
+    }
+
+    #[test]
+    fn test_jsonl_input() {
+        let text = "{\"id\": 7, \"latitude\": 21.42, \"longitude\": 39.83}\n\n\"Paris\"\n{\"name\": \"x\"}\nnot json\n";
+        let batch = BatchInput::parse(text, BatchFormat::Jsonl, &BatchColumns::default()).unwrap();
+        assert_eq!(batch.id_columns, vec!["id"]);
+        let lines: Vec<usize> = batch.records.iter().map(|record| record.line).collect();
+        assert_eq!(lines, vec![1, 3, 4, 5]);
+        assert_eq!(batch.records[0].ids, vec!["7"]);
+        assert_eq!(batch.records[0].input, Ok("21.42,39.83".to_string()));
+        assert_eq!(batch.records[1].input, Ok("Paris".to_string()));
+        assert_eq!(batch.records[2].input.as_ref().unwrap_err().field(), Some("JSON line"));
+        assert!(batch.records[3].input.is_err());
+    }
+
+    #[test]
+    fn test_output_columns() {
+        let result = BatchResult {
+            line: 2,
+            ids: vec!["say \"hi\"".to_string(), "7".to_string(), "A".to_string(), "B".to_string()],
+            input: "Paris, France".to_string(),
+            outcome: Err(MecczError::LocationNotFound("Paris, France".to_string())),
+        };
+        let ids: Vec<String> = ["name", "bearing", "code", "Name"].map(String::from).to_vec();
+
+        // Quoting is left to the CSV writer
+        let mut csv = Vec::new();
+        let mut writer = BatchWriter::new(&mut csv, BatchFormat::Csv, ids.clone());
+        writer.write(&result).unwrap();
+        drop(writer);
+        let csv = String::from_utf8(csv).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert!(rows[0].starts_with("line,name,id_bearing,id_code,id_Name,input,"));
+        assert_eq!(rows[1], "2,\"say \"\"hi\"\"\",7,A,B,\"Paris, France\",,,,,,location_not_found,\"Location not found: Paris, France\"");
+
+        // Identifiers never overwrite the result's own keys
+        let mut jsonl = Vec::new();
+        let mut writer = BatchWriter::new(&mut jsonl, BatchFormat::Jsonl, ids);
+        writer.write(&result).unwrap();
+        drop(writer);
+        let line: serde_json::Value = serde_json::from_slice(&jsonl).unwrap();
+        assert_eq!(line["code"], "location_not_found");
+        assert_eq!(line["id_code"], "A");
+        assert_eq!((line["name"].as_str(), line["id_Name"].as_str()), (Some("say \"hi\""), Some("B")));
+    }
+
+    #[tokio::test]
+    async fn test_process_continues_past_failures() {
+        let app = MeccaApp::new(MockGeocoder, GreatCircleCalculator::new());
+        let text = "id,location\na,Paris\nb,Atlantis\nc,95;10\nd,Tokyo\n";
+        let batch = BatchInput::parse(text, BatchFormat::Csv, &BatchColumns::default()).unwrap();
+        let results: Vec<BatchResult> = process(&app, batch.records, 3).collect().await;
+
+        let ids: Vec<&str> = results.iter().map(|result| result.ids[0].as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "c", "d"], "results keep the input order");
+        assert!((results[0].outcome.as_ref().unwrap().1.bearing - 119.16).abs() < 0.01);
+        assert_eq!(results[1].outcome.as_ref().unwrap_err().code(), "location_not_found");
+        assert_eq!(results[2].outcome.as_ref().unwrap_err().code(), "out_of_range");
+        assert!(results[3].outcome.is_ok());
+
+        let mut summary = BatchSummary::default();
+        results.iter().for_each(|result| summary.record(result));
+        assert_eq!((summary.total, summary.succeeded, summary.failed), (4, 2, 2));
+        assert_eq!(summary.errors.get("location_not_found"), Some(&1));
+
+        let mut csv = Vec::new();
+        let mut writer = BatchWriter::new(&mut csv, BatchFormat::Csv, batch.id_columns.clone());
+        results.iter().try_for_each(|result| writer.write(result)).unwrap();
+        drop(writer);
+        let csv = String::from_utf8(csv).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows[0], "line,id,input,latitude,longitude,bearing,direction,distance_km,error_code,error");
+        assert_eq!(rows[1], "2,a,Paris,48.8566,2.3522,119.16,ESE,4496.2,,");
+        assert_eq!(rows[2], "3,b,Atlantis,,,,,,location_not_found,Location not found: Atlantis");
+
+        let mut jsonl = Vec::new();
+        let mut writer = BatchWriter::new(&mut jsonl, BatchFormat::Jsonl, batch.id_columns);
+        results.iter().try_for_each(|result| writer.write(result)).unwrap();
+        drop(writer);
+        let lines: Vec<serde_json::Value> =
+            String::from_utf8(jsonl).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines[0]["id"], "a");
+        assert_eq!(lines[0]["direction"], "ESE");
+        assert_eq!(lines[2]["code"], "out_of_range");
+        assert_eq!(lines[2]["field"], "latitude");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_geocoder() {
+        let app = MeccaApp::new(
+            RateLimitedGeocoder::new(MockGeocoder, RateLimiter::new(Duration::from_millis(40))),
+            GreatCircleCalculator::new(),
+        );
+        let text = "Paris\nTokyo\n48.8566,2.3522\nNew York\n";
+        let batch = BatchInput::parse(text, BatchFormat::Csv, &BatchColumns::default()).unwrap();
+        let started = Instant::now();
+        let results: Vec<BatchResult> = process(&app, batch.records, 4).collect().await;
+        // Three geocoded queries need two intervals; coordinates are not throttled
+        assert!(started.elapsed() >= Duration::from_millis(80));
+        assert!(started.elapsed() < Duration::from_millis(1000));
+        assert!(results.iter().all(|result| result.outcome.is_ok()));
+    }
+}
+
+#[cfg(test)]
+mod server_tests {
+    use super::*;
+    use super::integration_tests::MockGeocoder;
+    use crate::qibla::GreatCircleCalculator;
+    use crate::server::{router, CorsOrigins};
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn get(uri: &str, origin: Option<&str>, origins: &CorsOrigins) -> (StatusCode, axum::http::HeaderMap, serde_json::Value) {
+        let app = Arc::new(MeccaApp::new(MockGeocoder, GreatCircleCalculator::new()));
+        let mut request = Request::get(uri);
+        if let Some(origin) = origin {
+            request = request.header("Origin", origin);
+        }
+        let response = router(app, origins).oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let (parts, body) = response.into_parts();
+        let body = to_bytes(body, usize::MAX).await.unwrap();
+        (parts.status, parts.headers, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_qibla_endpoint() {
+        let (status, _, body) = get("/qibla?lat=48.8566&lon=2.3522", None, &CorsOrigins::Any).await;
+        assert_eq!(status, StatusCode::OK);
+        let qibla: QiblaDirection = serde_json::from_value(body).unwrap();
+        assert!((qibla.bearing - 119.16).abs() < 0.01);
+        assert_eq!(qibla.direction, "ESE");
+
+        let (status, _, body) = get("/qibla?q=tokyo", None, &CorsOrigins::Any).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["direction"], "WNW");
+
+        let (status, _, body) = get("/compass-table?q=paris", None, &CorsOrigins::Any).await;
+        assert_eq!(status, StatusCode::OK);
+        let table: CompassTable = serde_json::from_value(body).unwrap();
+        assert_eq!(table.entries.len(), 16);
+
+        let (status, _, body) = get("/geocode?q=new%20york&limit=3", None, &CorsOrigins::Any).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["location"]["longitude"], -74.006);
+    }
+
+    #[tokio::test]
+    async fn test_structured_errors() {
+        let cases = [
+            ("/qibla?lat=95&lon=2", StatusCode::BAD_REQUEST, "out_of_range", Some("latitude")),
+            ("/qibla?lat=north&lon=2", StatusCode::BAD_REQUEST, "parse_error", Some("latitude")),
+            ("/qibla?lat=48.8", StatusCode::BAD_REQUEST, "parse_error", Some("lon")),
+            ("/qibla?lat=1&lon=2&q=paris", StatusCode::BAD_REQUEST, "parse_error", Some("query")),
+            ("/compass-table", StatusCode::BAD_REQUEST, "parse_error", Some("query")),
+            ("/qibla?q=atlantis", StatusCode::NOT_FOUND, "location_not_found", None),
+            ("/geocode?q=paris&limit=500", StatusCode::BAD_REQUEST, "parse_error", Some("limit")),
+            ("/geocode", StatusCode::BAD_REQUEST, "parse_error", Some("q")),
+            ("/elsewhere", StatusCode::NOT_FOUND, "not_found", None),
+        ];
+        for (uri, expected_status, code, field) in cases {
+            let (status, _, body) = get(uri, None, &CorsOrigins::Any).await;
+            assert_eq!(status, expected_status, "{}", uri);
+            assert_eq!(body["code"], code, "{}", uri);
+            assert_eq!(body["field"].as_str(), field, "{}", uri);
+            assert!(body["error"].is_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_openapi_and_cors() {
+        let (status, headers, body) = get("/health", Some("https://app.example"), &CorsOrigins::Any).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+        assert_eq!(headers["access-control-allow-origin"], "*");
+
+        let origins = CorsOrigins::List(vec!["https://app.example".to_string()]);
+        let (_, headers, _) = get("/health", Some("https://app.example"), &origins).await;
+        assert_eq!(headers["access-control-allow-origin"], "https://app.example");
+        let (_, headers, _) = get("/health", Some("https://evil.example"), &origins).await;
+        assert!(!headers.contains_key("access-control-allow-origin"));
+
+        let (status, _, document) = get("/openapi.json", None, &CorsOrigins::Any).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(document["openapi"], "3.0.3");
+        for path in ["/qibla", "/compass-table", "/geocode", "/health"] {
+            assert!(document["paths"][path]["get"].is_object(), "{} is documented", path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod nominatim_tests {
+    use super::*;
+    use crate::geocoding::{parse_retry_after, HttpClient, NominatimGeocoder};
+    use crate::throttle::{RateLimiter, RetryPolicy};
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode, Uri};
+    use axum::response::{IntoResponse, Response};
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    const PARIS: &str = r#"[{"lat": "48.8566", "lon": "2.3522", "display_name": "Paris, France", "type": "city"}]"#;
+
+    /// A reply of the mock server: status, Retry-After, body and how long to wait first.
+    pub(super) type Reply = (u16, Option<&'static str>, &'static str, Duration);
+
+    #[derive(Default)]
+    pub(super) struct Mock {
+        replies: Mutex<VecDeque<Reply>>,
+        pub(super) requests: Mutex<Vec<(Instant, String, String)>>, // arrival, User-Agent, path and query
+    }
+
+    async fn reply(State(mock): State<Arc<Mock>>, headers: HeaderMap, uri: Uri) -> Response {
+        let user_agent = headers.get("user-agent").and_then(|value| value.to_str().ok()).unwrap_or_default();
+        mock.requests.lock().unwrap().push((Instant::now(), user_agent.to_string(), uri.to_string()));
+        let (status, retry_after, body, delay) = mock.replies.lock().unwrap().pop_front().unwrap_or((200, None, PARIS, Duration::ZERO));
+        tokio::time::sleep(delay).await;
+        let mut response = (StatusCode::from_u16(status).unwrap(), body).into_response();
+        if let Some(retry_after) = retry_after {
+            response.headers_mut().insert("retry-after", retry_after.parse().unwrap());
+        }
+        response
+    }
+
+    /// Serves `replies` in order on a local port, then Paris for any further request.
+    pub(super) async fn mock_server(replies: Vec<Reply>) -> (String, Arc<Mock>) {
+        let mock = Arc::new(Mock { replies: Mutex::new(replies.into()), ..Mock::default() });
+        let router = axum::Router::new().fallback(reply).with_state(mock.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        (url, mock)
+    }
+
+    /// A client without rate limit whose retries come quickly.
+    pub(super) fn client(max_retries: u32) -> HttpClient {
+        HttpClient::new().with_retry_policy(RetryPolicy { max_retries, initial_backoff: Duration::from_millis(10), max_backoff: Duration::from_secs(5) })
+    }
+
+    fn nominatim(url: &str, client: HttpClient) -> NominatimGeocoder {
+        NominatimGeocoder::new().with_base_url(url).with_client(client)
+    }
+
+    #[tokio::test]
+    async fn test_honors_retry_after() {
+        let (url, mock) = mock_server(vec![(429, Some("1"), "", Duration::ZERO)]).await;
+        let geocoder = nominatim(&url, client(3).with_user_agent("qibla-kiosk/2.0").unwrap()).with_email(Some("ops@example.org".to_string()));
+
+        let location = geocoder.geocode("paris").await.unwrap();
+        assert!((location.latitude - 48.8566).abs() < 1e-9);
+
+        {
+            let requests = mock.requests.lock().unwrap();
+            assert_eq!(requests.len(), 2);
+            assert!(requests[1].0 - requests[0].0 >= Duration::from_millis(950));
+            assert_eq!(requests[0].1, "qibla-kiosk/2.0");
+            assert!(requests[0].2.contains("q=paris"));
+            assert!(requests[0].2.contains("email=ops%40example.org"));
+        }
+
+        // A server asking for longer than the policy waits gets the error at once
+        let (url, mock) = mock_server(vec![(429, Some("3600"), "", Duration::ZERO)]).await;
+        let error = nominatim(&url, client(3)).geocode("paris").await.unwrap_err();
+        assert_eq!(error.code(), "rate_limited");
+        assert_eq!(mock.requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_user_agent() {
+        let error = client(0).with_user_agent("qibla\nkiosk").err().unwrap();
+        assert_eq!(error.field(), Some("user agent"));
+        assert!(client(0).with_user_agent("qibla-kiosk/2.0 (ops@example.org)").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_retries_only_transient_errors() {
+        let unavailable = (503, None, "", Duration::ZERO);
+        let (url, mock) = mock_server(vec![unavailable; 3]).await;
+        match nominatim(&url, client(2)).geocode("paris").await {
+            Err(MecczError::Transport { status: Some(503), .. }) => {}
+            other => panic!("expected a 503 transport error, got {:?}", other.map(|_| ())),
+        }
+        assert_eq!(mock.requests.lock().unwrap().len(), 3);
+
+        let (url, mock) = mock_server(vec![(500, None, "", Duration::ZERO)]).await;
+        assert!(nominatim(&url, client(2)).geocode("paris").await.is_ok());
+        assert_eq!(mock.requests.lock().unwrap().len(), 2);
+
+        let (url, mock) = mock_server(vec![(403, None, "", Duration::ZERO), (404, None, "", Duration::ZERO)]).await;
+        assert!(nominatim(&url, client(2)).geocode("paris").await.is_err());
+        assert_eq!(mock.requests.lock().unwrap().len(), 1);
+
+        let (url, mock) = mock_server(vec![(200, None, "<html>", Duration::ZERO)]).await;
+        assert_eq!(nominatim(&url, client(2)).geocode("paris").await.unwrap_err().code(), "malformed_response");
+        assert_eq!(mock.requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let (url, mock) = mock_server(vec![(200, None, PARIS, Duration::from_secs(2))]).await;
+        let geocoder = nominatim(&url, client(1).with_timeout(Duration::from_millis(100)).unwrap());
+
+        let started = Instant::now();
+        assert!(geocoder.geocode("paris").await.is_ok());
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(mock.requests.lock().unwrap().len(), 2);
+
+        let (url, _) = mock_server(vec![(200, None, PARIS, Duration::from_secs(2))]).await;
+        let geocoder = nominatim(&url, client(0).with_timeout(Duration::from_millis(100)).unwrap());
+        match geocoder.geocode("paris").await {
+            Err(MecczError::Transport { status: None, reason, .. }) => assert!(reason.contains("no response")),
+            other => panic!("expected a timeout, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_spaces_requests() {
+        let (url, mock) = mock_server(Vec::new()).await;
+        let geocoder = nominatim(&url, client(0).with_rate_limiter(Arc::new(RateLimiter::new(Duration::from_millis(200)))));
+        for _ in 0..3 {
+            geocoder.geocode("paris").await.unwrap();
+        }
+        let requests = mock.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        for pair in requests.windows(2) {
+            assert!(pair[1].0 - pair[0].0 >= Duration::from_millis(190));
+        }
+        assert!(requests[0].1.starts_with("meccz/"));
+    }
+
+    #[tokio::test]
+    async fn test_servers_do_not_share_cached_answers() {
+        use crate::cache::{CachedGeocoder, GeocodingCache};
+        use crate::providers::{PeliasGeocoder, PhotonGeocoder};
+
+        assert_eq!(NominatimGeocoder::new().source(), "nominatim");
+        assert_eq!(PhotonGeocoder::new().with_base_url("https://photon.komoot.io/").source(), "photon");
+        assert_eq!(PeliasGeocoder::new("http://localhost:4000/").source(), "pelias@http://localhost:4000");
+
+        let path = std::env::temp_dir().join(format!("meccz-cache-test-servers-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let (first_url, first) = mock_server(Vec::new()).await;
+        let (second_url, second) = mock_server(Vec::new()).await;
+        assert_eq!(nominatim(&first_url, client(0)).source(), format!("nominatim@{}", first_url));
+
+        let cached = |url: &str| CachedGeocoder::new(nominatim(url, client(0)), GeocodingCache::open(&path).unwrap());
+        let geocoder = cached(&first_url);
+        geocoder.geocode("paris").await.unwrap();
+        geocoder.persist().unwrap();
+        drop(geocoder);
+
+        // Same cache file, same service, another server: asked again
+        let geocoder = cached(&second_url);
+        geocoder.geocode("paris").await.unwrap();
+        geocoder.persist().unwrap();
+        drop(geocoder);
+        assert_eq!(first.requests.lock().unwrap().len(), 1);
+        assert_eq!(second.requests.lock().unwrap().len(), 1);
+
+        // Each server's answer is then served from the cache
+        cached(&first_url).geocode("paris").await.unwrap();
+        cached(&second_url).geocode("paris").await.unwrap();
+        assert_eq!(first.requests.lock().unwrap().len(), 1);
+        assert_eq!(second.requests.lock().unwrap().len(), 1);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_backoff_and_retry_after() {
+        let policy = RetryPolicy { max_retries: 5, initial_backoff: Duration::from_millis(100), max_backoff: Duration::from_secs(1) };
+        for (retry, full) in [(0, 100), (1, 200), (2, 400), (3, 800), (4, 1000)] {
+            let delay = policy.backoff(retry);
+            assert!(delay >= Duration::from_millis(full / 2) && delay <= Duration::from_millis(full), "{:?}", delay);
+        }
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(1))), Some(Duration::from_secs(1)));
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(2))), None);
+
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+        assert_eq!(RateLimiter::per_second(0.0).interval(), Duration::ZERO);
+    }
+}
+
+#[cfg(test)]
+mod provider_tests {
+    use super::*;
+    use super::nominatim_tests::{client, mock_server};
+    use crate::providers::{PeliasGeocoder, PhotonGeocoder, TemplateConfig, TemplateGeocoder};
+    use std::time::Duration;
+
+    const PHOTON_PARIS: &str = r#"{"type": "FeatureCollection", "features": [
+        {"type": "Feature", "geometry": {"type": "Point", "coordinates": [2.3483915, 48.8534951]},
+         "properties": {"name": "Paris", "city": "Paris", "state": "Île-de-France", "country": "France",
+                        "countrycode": "FR", "osm_value": "city", "extent": [2.224122, 48.902156, 2.4697602, 48.8155755]}},
+        {"type": "Feature", "geometry": {"type": "Point", "coordinates": [-95.555513, 33.6617962]},
+         "properties": {"name": "Paris", "state": "Texas", "country": "United States", "countrycode": "US", "osm_value": "town"}}
+    ]}"#;
+
+    const PELIAS_PARIS: &str = r#"{"type": "FeatureCollection", "features": [
+        {"type": "Feature", "geometry": {"type": "Point", "coordinates": [2.35, 48.86]}, "bbox": [2.22, 48.81, 2.47, 48.9],
+         "properties": {"layer": "locality", "name": "Paris", "label": "Paris, France", "confidence": 1,
+                        "locality": "Paris", "country": "France", "country_code": "FR"}}
+    ]}"#;
+
+    #[tokio::test]
+    async fn test_photon_geocoder() {
+        let (url, mock) = mock_server(vec![(200, None, PHOTON_PARIS, Duration::ZERO); 2]).await;
+        let geocoder = PhotonGeocoder::new().with_base_url(&format!("{}/", url)).with_client(client(0));
+
+        let candidates = geocoder.geocode_candidates("paris", 5).await.unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].display_name, "Paris, Île-de-France, France");
+        assert_eq!(candidates[0].kind, "city");
+        assert!((candidates[0].location.latitude - 48.8534951).abs() < 1e-9);
+        let bounding_box = candidates[0].bounding_box.clone().unwrap();
+        assert!(bounding_box.south < bounding_box.north && bounding_box.west < bounding_box.east);
+        assert_eq!(candidates[1].display_name, "Paris, Texas, United States");
+
+        let place = geocoder.reverse_geocode(&candidates[0].location).await.unwrap();
+        assert_eq!(place.city.as_deref(), Some("Paris"));
+        assert_eq!(place.country_code.as_deref(), Some("FR"));
+
+        let requests = mock.requests.lock().unwrap();
+        assert_eq!(requests[0].2, "/api?q=paris&limit=5");
+        assert!(requests[1].2.starts_with("/reverse?lat=48.8534951&lon=2.3483915"));
+    }
+
+    #[tokio::test]
+    async fn test_pelias_geocoder() {
+        let (url, mock) = mock_server(vec![(200, None, PELIAS_PARIS, Duration::ZERO); 2]).await;
+        let geocoder = PeliasGeocoder::new(&url).with_api_key(Some("ge-123".to_string())).with_client(client(0));
+
+        let candidates = geocoder.geocode_candidates("Paris, France", 3).await.unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].display_name, "Paris, France");
+        assert_eq!(candidates[0].kind, "locality");
+        assert_eq!(candidates[0].importance, Some(1.0));
+        assert_eq!(candidates[0].bounding_box.as_ref().map(|bounding_box| bounding_box.north), Some(48.9));
+
+        let place = geocoder.reverse_geocode(&candidates[0].location).await.unwrap();
+        assert_eq!((place.city.as_deref(), place.country_code.as_deref()), (Some("Paris"), Some("FR")));
+
+        {
+            let requests = mock.requests.lock().unwrap();
+            assert_eq!(requests[0].2, "/v1/search?text=Paris%2C%20France&size=3&api_key=ge-123");
+            assert!(requests[1].2.starts_with("/v1/reverse?point.lat=48.86&point.lon=2.35&size=1"));
+        }
+
+        let (url, _) = mock_server(vec![(200, None, r#"{"features": []}"#, Duration::ZERO)]).await;
+        let error = PeliasGeocoder::new(&url).with_client(client(0)).geocode("nowhere").await.unwrap_err();
+        assert_eq!(error.code(), "location_not_found");
+    }
+
+    fn template_config(url: &str) -> TemplateConfig {
+        serde_json::from_value(serde_json::json!({
+            "name": "acme",
+            "search_url": format!("{}/geocode?address={{query}}&max={{limit}}&key={{api_key}}", url),
+            "results": "/data/hits",
+            "latitude": "/position/lat",
+            "longitude": "/position/lng",
+            "display_name": "/formatted",
+            "importance": "/score",
+            "headers": {"Authorization": "Bearer {api_key}"}
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_template_geocoder() {
+        let body = r#"{"data": {"hits": [
+            {"formatted": "Istanbul, Türkiye", "score": 0.9, "position": {"lat": "41.0082", "lng": 28.9784}},
+            {"formatted": "Istanbul Airport", "score": 0.2, "position": {"lat": 41.2753, "lng": 28.7519}}
+        ]}}"#;
+        let (url, mock) = mock_server(vec![(200, None, body, Duration::ZERO)]).await;
+        let geocoder = TemplateGeocoder::new(template_config(&url)).with_api_key(Some("s3cr3t".to_string())).with_client(client(0));
+        assert_eq!(geocoder.source(), format!("acme@{}/geocode", url));
+
+        let candidates = geocoder.geocode_candidates("İstanbul", 2).await.unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].display_name, "Istanbul, Türkiye");
+        assert!((candidates[0].location.latitude - 41.0082).abs() < 1e-9);
+        assert_eq!(candidates[1].importance, Some(0.2));
+        assert_eq!(mock.requests.lock().unwrap()[0].2, "/geocode?address=%C4%B0stanbul&max=2&key=s3cr3t");
+
+        // No reverse_url: reverse geocoding is unsupported
+        assert_eq!(geocoder.reverse_geocode(&candidates[0].location).await.unwrap_err().code(), "unsupported");
+
+        let (url, _) = mock_server(vec![(200, None, r#"{"data": {"hits": [{"formatted": "?"}]}}"#, Duration::ZERO)]).await;
+        let geocoder = TemplateGeocoder::new(template_config(&url)).with_client(client(0));
+        assert_eq!(geocoder.geocode("x").await.unwrap_err().code(), "malformed_response");
+    }
+
+    #[test]
+    fn test_template_config_file() {
+        let path = std::env::temp_dir().join(format!("meccz-template-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"name": "acme", "search_url": "https://acme.test/?q={query}", "latitude": "/lat", "longitude": "/lon"}"#).unwrap();
+        let config = TemplateConfig::from_path(&path).unwrap();
+        assert_eq!(config.results, "");
+        assert_eq!(config.reverse_url, None);
+
+        std::fs::write(&path, r#"{"name": "acme", "search_url": "x", "latitude": "/lat", "longitude": "/lon", "lattitude": "/y"}"#).unwrap();
+        let error = TemplateConfig::from_path(&path).unwrap_err();
+        assert!(format!("{:#}", error).contains("lattitude"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
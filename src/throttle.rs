@@ -0,0 +1,135 @@
+use crate::error::MecczError;
+use crate::interfaces::{GeocodingCandidate, GeocodingService, Location, Place};
+use async_trait::async_trait;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Requests per second allowed by the Nominatim usage policy.
+pub const NOMINATIM_REQUESTS_PER_SECOND: f64 = 1.0;
+
+/// Spaces out callers so that at most one goes through every `interval`, in the order
+/// they arrived, however many wait concurrently.
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Option<Instant>>, // earliest instant the next caller may proceed
+}
+
+impl RateLimiter {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval, next_slot: Mutex::new(None) }
+    }
+
+    /// A limiter letting `rate` callers through per second; 0 lets everyone through.
+    pub fn per_second(rate: f64) -> Self {
+        if rate > 0.0 { Self::new(Duration::from_secs_f64(1.0 / rate)) } else { Self::new(Duration::ZERO) }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Waits for the next free slot. The slot is booked before sleeping, so concurrent
+    /// callers each get their own.
+    pub async fn wait(&self) {
+        let delay = {
+            let mut next_slot = self.next_slot.lock().expect("rate limiter lock poisoned");
+            let now = Instant::now();
+            let slot = next_slot.map_or(now, |next| next.max(now));
+            *next_slot = Some(slot + self.interval);
+            slot - now
+        };
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// How often and how patiently a failed request is tried again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32, // attempts after the first one
+    pub initial_backoff: Duration, // wait before the first retry, doubled for each further one
+    pub max_backoff: Duration, // longest wait, including a server's Retry-After
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Every request is tried once.
+    pub fn none() -> Self {
+        Self { max_retries: 0, ..Self::default() }
+    }
+
+    /// Wait before retry number `retry` (from 0): half the exponential delay plus a random
+    /// share of the other half, so that clients failing together do not retry together.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let delay = self.initial_backoff.saturating_mul(2u32.saturating_pow(retry)).min(self.max_backoff);
+        delay / 2 + delay.mul_f64(random_fraction() / 2.0)
+    }
+
+    /// Wait before retry number `retry` when the server asked for `retry_after`, or `None`
+    /// when that is longer than the policy is willing to wait.
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        match retry_after {
+            Some(delay) if delay > self.max_backoff => None,
+            Some(delay) => Some(delay),
+            None => Some(self.backoff(retry)),
+        }
+    }
+}
+
+/// A number in [0, 1) that differs between calls, from the randomly seeded std hasher.
+fn random_fraction() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos());
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// A geocoding service whose requests are spaced by a [`RateLimiter`]. Put it under
+/// the cache so that cached answers are not delayed.
+pub struct RateLimitedGeocoder<G: GeocodingService> {
+    inner: G,
+    limiter: RateLimiter,
+}
+
+impl<G: GeocodingService> RateLimitedGeocoder<G> {
+    pub fn new(inner: G, limiter: RateLimiter) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+#[async_trait]
+impl<G: GeocodingService + Send + Sync> GeocodingService for RateLimitedGeocoder<G> {
+    async fn geocode(&self, address: &str) -> Result<Location, MecczError> {
+        self.limiter.wait().await;
+        self.inner.geocode(address).await
+    }
+
+    async fn geocode_candidates(&self, address: &str, limit: usize) -> Result<Vec<GeocodingCandidate>, MecczError> {
+        self.limiter.wait().await;
+        self.inner.geocode_candidates(address, limit).await
+    }
+
+    async fn reverse_geocode(&self, location: &Location) -> Result<Place, MecczError> {
+        self.limiter.wait().await;
+        self.inner.reverse_geocode(location).await
+    }
+
+    fn source(&self) -> &str {
+        self.inner.source()
+    }
+
+    fn persist(&self) -> anyhow::Result<()> {
+        self.inner.persist()
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::{anyhow, Result};
+use chrono::{FixedOffset, Local, NaiveDate, NaiveTime, Offset, TimeZone};
+use std::str::FromStr;
+
+/// A time zone given on the command line: a fixed UTC offset, an IANA zone name or
+/// the local zone of this machine.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeZoneSpec {
+    Fixed(FixedOffset),
+    Named(chrono_tz::Tz),
+    Local,
+}
+
+impl TimeZoneSpec {
+    /// UTC offset in force at local noon on `date`, which sidesteps the
+    /// ambiguous hours around daylight saving transitions.
+    pub fn offset_on(&self, date: NaiveDate) -> FixedOffset {
+        let noon = date.and_time(NaiveTime::from_hms_opt(12, 0, 0).expect("noon is valid"));
+        match self {
+            TimeZoneSpec::Fixed(offset) => *offset,
+            TimeZoneSpec::Named(tz) => tz.offset_from_local_datetime(&noon).earliest()
+                .map(|offset| offset.fix())
+                .unwrap_or_else(|| tz.offset_from_utc_datetime(&noon).fix()),
+            TimeZoneSpec::Local => Local.offset_from_local_datetime(&noon).earliest()
+                .map(|offset| offset.fix())
+                .unwrap_or_else(|| Local.offset_from_utc_datetime(&noon).fix()),
+        }
+    }
+}
+
+impl FromStr for TimeZoneSpec {
+    type Err = anyhow::Error;
+
+    /// Accepts `local`, `UTC`/`Z`, offsets such as `+03:00`, `-0530` or `UTC+3`,
+    /// and IANA names such as `Europe/Paris`.
+    fn from_str(input: &str) -> Result<Self> {
+        let trimmed = input.trim();
+        if trimmed.eq_ignore_ascii_case("local") {
+            return Ok(TimeZoneSpec::Local);
+        }
+        if trimmed.eq_ignore_ascii_case("utc") || trimmed.eq_ignore_ascii_case("z") {
+            return Ok(TimeZoneSpec::Fixed(FixedOffset::east_opt(0).expect("zero offset is valid")));
+        }
+
+        let offset = trimmed
+            .strip_prefix("UTC")
+            .or_else(|| trimmed.strip_prefix("GMT"))
+            .unwrap_or(trimmed);
+        if offset.starts_with('+') || offset.starts_with('-') {
+            return parse_offset(offset).map(TimeZoneSpec::Fixed);
+        }
+
+        trimmed
+            .parse::<chrono_tz::Tz>()
+            .map(TimeZoneSpec::Named)
+            .map_err(|_| anyhow!("Unknown time zone: {} (use an offset like +03:00 or a name like Asia/Riyadh)", input))
+    }
+}
+
+fn parse_offset(offset: &str) -> Result<FixedOffset> {
+    let sign = if offset.starts_with('-') { -1 } else { 1 };
+    let digits = &offset[1..];
+    let (hours, minutes) = match digits.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if digits.len() > 2 => digits.split_at(digits.len() - 2),
+        None => (digits, "0"),
+    };
+
+    let hours: i32 = hours.parse().map_err(|_| anyhow!("Invalid UTC offset hours: {}", offset))?;
+    let minutes: i32 = minutes.parse().map_err(|_| anyhow!("Invalid UTC offset minutes: {}", offset))?;
+    if hours > 14 || minutes >= 60 {
+        return Err(anyhow!("UTC offset out of range: {}", offset));
+    }
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+        .ok_or_else(|| anyhow!("UTC offset out of range: {}", offset))
+}